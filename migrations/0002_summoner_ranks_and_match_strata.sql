@@ -0,0 +1,21 @@
+-- Ranked tiers per summoner and the sampling stratum each match counts towards
+CREATE TABLE IF NOT EXISTS summoner_ranks (
+    puuid TEXT,
+    queue_type TEXT,
+    tier TEXT,
+    rank TEXT,
+    league_points INTEGER,
+    wins INTEGER,
+    losses INTEGER,
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (puuid, queue_type)
+);
+
+CREATE TABLE IF NOT EXISTS match_strata (
+    match_id TEXT PRIMARY KEY,
+    region TEXT,
+    tier TEXT,
+    patch TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_match_strata_stratum ON match_strata(region, tier, patch);
@@ -0,0 +1,18 @@
+-- Dead-letter queue for summoners and matches that could not be processed
+CREATE TABLE IF NOT EXISTS failed_tasks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    task_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    region TEXT,
+    summoner_name TEXT,
+    error_kind TEXT,
+    http_status INTEGER,
+    attempts INTEGER,
+    last_error TEXT,
+    status TEXT DEFAULT 'failed',
+    first_failed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    last_failed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(task_type, target_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_failed_tasks_status ON failed_tasks(status, error_kind);
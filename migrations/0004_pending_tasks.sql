@@ -0,0 +1,12 @@
+-- Crawl queue persisted by a graceful drain and restored on the next start
+CREATE TABLE IF NOT EXISTS pending_tasks (
+    puuid TEXT PRIMARY KEY,
+    summoner_name TEXT,
+    region TEXT,
+    priority TEXT,
+    tier TEXT,
+    retries INTEGER,
+    retry_class TEXT,
+    not_before TEXT,
+    added_at TEXT
+);
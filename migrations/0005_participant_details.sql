@@ -0,0 +1,127 @@
+-- Remaining ParticipantDto stats (vision, pings, damage breakdowns, ...)
+ALTER TABLE participants ADD COLUMN all_in_pings INTEGER;
+ALTER TABLE participants ADD COLUMN assist_me_pings INTEGER;
+ALTER TABLE participants ADD COLUMN baron_kills INTEGER;
+ALTER TABLE participants ADD COLUMN bounty_level INTEGER;
+ALTER TABLE participants ADD COLUMN champ_experience INTEGER;
+ALTER TABLE participants ADD COLUMN command_pings INTEGER;
+ALTER TABLE participants ADD COLUMN champion_transform INTEGER;
+ALTER TABLE participants ADD COLUMN consumables_purchased INTEGER;
+ALTER TABLE participants ADD COLUMN damage_dealt_to_buildings INTEGER;
+ALTER TABLE participants ADD COLUMN damage_dealt_to_objectives INTEGER;
+ALTER TABLE participants ADD COLUMN damage_dealt_to_turrets INTEGER;
+ALTER TABLE participants ADD COLUMN damage_self_mitigated INTEGER;
+ALTER TABLE participants ADD COLUMN detector_wards_placed INTEGER;
+ALTER TABLE participants ADD COLUMN double_kills INTEGER;
+ALTER TABLE participants ADD COLUMN dragon_kills INTEGER;
+ALTER TABLE participants ADD COLUMN eligible_for_progression BOOLEAN;
+ALTER TABLE participants ADD COLUMN enemy_missing_pings INTEGER;
+ALTER TABLE participants ADD COLUMN enemy_vision_pings INTEGER;
+ALTER TABLE participants ADD COLUMN first_blood_assist BOOLEAN;
+ALTER TABLE participants ADD COLUMN first_tower_assist BOOLEAN;
+ALTER TABLE participants ADD COLUMN game_ended_in_early_surrender BOOLEAN;
+ALTER TABLE participants ADD COLUMN game_ended_in_surrender BOOLEAN;
+ALTER TABLE participants ADD COLUMN hold_pings INTEGER;
+ALTER TABLE participants ADD COLUMN get_back_pings INTEGER;
+ALTER TABLE participants ADD COLUMN inhibitor_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN inhibitors_lost INTEGER;
+ALTER TABLE participants ADD COLUMN items_purchased INTEGER;
+ALTER TABLE participants ADD COLUMN killing_sprees INTEGER;
+ALTER TABLE participants ADD COLUMN largest_critical_strike INTEGER;
+ALTER TABLE participants ADD COLUMN largest_killing_spree INTEGER;
+ALTER TABLE participants ADD COLUMN largest_multi_kill INTEGER;
+ALTER TABLE participants ADD COLUMN longest_time_spent_living INTEGER;
+ALTER TABLE participants ADD COLUMN magic_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN magic_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN magic_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN need_vision_pings INTEGER;
+ALTER TABLE participants ADD COLUMN nexus_kills INTEGER;
+ALTER TABLE participants ADD COLUMN nexus_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN nexus_lost INTEGER;
+ALTER TABLE participants ADD COLUMN objectives_stolen INTEGER;
+ALTER TABLE participants ADD COLUMN objectives_stolen_assists INTEGER;
+ALTER TABLE participants ADD COLUMN on_my_way_pings INTEGER;
+ALTER TABLE participants ADD COLUMN participant_id INTEGER;
+ALTER TABLE participants ADD COLUMN penta_kills INTEGER;
+ALTER TABLE participants ADD COLUMN physical_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN physical_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN physical_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN placement INTEGER;
+ALTER TABLE participants ADD COLUMN player_augment1 INTEGER;
+ALTER TABLE participants ADD COLUMN player_augment2 INTEGER;
+ALTER TABLE participants ADD COLUMN player_augment3 INTEGER;
+ALTER TABLE participants ADD COLUMN player_augment4 INTEGER;
+ALTER TABLE participants ADD COLUMN player_subteam_id INTEGER;
+ALTER TABLE participants ADD COLUMN push_pings INTEGER;
+ALTER TABLE participants ADD COLUMN profile_icon INTEGER;
+ALTER TABLE participants ADD COLUMN quadra_kills INTEGER;
+ALTER TABLE participants ADD COLUMN riot_id_game_name TEXT;
+ALTER TABLE participants ADD COLUMN riot_id_tagline TEXT;
+ALTER TABLE participants ADD COLUMN role TEXT;
+ALTER TABLE participants ADD COLUMN sight_wards_bought_in_game INTEGER;
+ALTER TABLE participants ADD COLUMN spell1_casts INTEGER;
+ALTER TABLE participants ADD COLUMN spell2_casts INTEGER;
+ALTER TABLE participants ADD COLUMN spell3_casts INTEGER;
+ALTER TABLE participants ADD COLUMN spell4_casts INTEGER;
+ALTER TABLE participants ADD COLUMN subteam_placement INTEGER;
+ALTER TABLE participants ADD COLUMN summoner1_casts INTEGER;
+ALTER TABLE participants ADD COLUMN summoner2_casts INTEGER;
+ALTER TABLE participants ADD COLUMN summoner_id TEXT;
+ALTER TABLE participants ADD COLUMN summoner_level INTEGER;
+ALTER TABLE participants ADD COLUMN team_early_surrendered BOOLEAN;
+ALTER TABLE participants ADD COLUMN team_position TEXT;
+ALTER TABLE participants ADD COLUMN time_ccing_others INTEGER;
+ALTER TABLE participants ADD COLUMN time_played INTEGER;
+ALTER TABLE participants ADD COLUMN total_ally_jungle_minions_killed INTEGER;
+ALTER TABLE participants ADD COLUMN total_damage_shielded_on_teammates INTEGER;
+ALTER TABLE participants ADD COLUMN total_enemy_jungle_minions_killed INTEGER;
+ALTER TABLE participants ADD COLUMN total_heal INTEGER;
+ALTER TABLE participants ADD COLUMN total_heals_on_teammates INTEGER;
+ALTER TABLE participants ADD COLUMN total_time_cc_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN total_time_spent_dead INTEGER;
+ALTER TABLE participants ADD COLUMN total_units_healed INTEGER;
+ALTER TABLE participants ADD COLUMN triple_kills INTEGER;
+ALTER TABLE participants ADD COLUMN true_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN true_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN true_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN turret_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN turrets_lost INTEGER;
+ALTER TABLE participants ADD COLUMN unreal_kills INTEGER;
+ALTER TABLE participants ADD COLUMN vision_score INTEGER;
+ALTER TABLE participants ADD COLUMN vision_cleared_pings INTEGER;
+ALTER TABLE participants ADD COLUMN vision_wards_bought_in_game INTEGER;
+ALTER TABLE participants ADD COLUMN wards_killed INTEGER;
+ALTER TABLE participants ADD COLUMN wards_placed INTEGER;
+ALTER TABLE participants ADD COLUMN missions TEXT;
+
+-- Full rune page: one row per rune, plus the three stat shards (style = 'statPerks')
+CREATE TABLE IF NOT EXISTS participant_perks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    match_id TEXT NOT NULL,
+    puuid TEXT NOT NULL,
+    style TEXT NOT NULL,
+    style_id INTEGER,
+    slot INTEGER NOT NULL,
+    perk_id INTEGER NOT NULL,
+    var1 INTEGER,
+    var2 INTEGER,
+    var3 INTEGER,
+    UNIQUE(match_id, puuid, style, slot)
+);
+
+CREATE INDEX IF NOT EXISTS idx_participant_perks_match_id ON participant_perks(match_id);
+CREATE INDEX IF NOT EXISTS idx_participant_perks_perk_id ON participant_perks(perk_id);
+
+-- Every entry of ParticipantDto.challenges, one row per name
+CREATE TABLE IF NOT EXISTS participant_challenges (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    match_id TEXT NOT NULL,
+    puuid TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value REAL,
+    value_json TEXT,
+    UNIQUE(match_id, puuid, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_participant_challenges_match_id ON participant_challenges(match_id);
+CREATE INDEX IF NOT EXISTS idx_participant_challenges_name ON participant_challenges(name);
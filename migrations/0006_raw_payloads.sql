@@ -0,0 +1,11 @@
+-- Original match and timeline responses, zstd-compressed, for rebuilding the
+-- normalised tables without calling the API again
+CREATE TABLE IF NOT EXISTS raw_payloads (
+    match_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    region TEXT,
+    payload BLOB NOT NULL,
+    raw_size INTEGER,
+    fetched_at TEXT,
+    PRIMARY KEY (match_id, kind)
+);
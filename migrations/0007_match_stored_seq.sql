@@ -0,0 +1,5 @@
+-- Sequence number assigned each time a match is stored; exports use it as a watermark.
+-- Unlike rowid it survives VACUUM.
+ALTER TABLE matches ADD COLUMN stored_seq INTEGER;
+UPDATE matches SET stored_seq = rowid;
+CREATE INDEX IF NOT EXISTS idx_matches_stored_seq ON matches(stored_seq);
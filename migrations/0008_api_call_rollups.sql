@@ -0,0 +1,12 @@
+-- Hourly aggregates of api_calls rows removed by the retention policy
+CREATE TABLE IF NOT EXISTS api_call_rollups (
+    hour TEXT NOT NULL,
+    endpoint TEXT NOT NULL,
+    region TEXT NOT NULL,
+    calls INTEGER NOT NULL,
+    errors INTEGER NOT NULL,
+    rate_limited INTEGER NOT NULL,
+    PRIMARY KEY (hour, endpoint, region)
+);
+CREATE INDEX IF NOT EXISTS idx_api_calls_timestamp ON api_calls(timestamp);
+CREATE INDEX IF NOT EXISTS idx_matches_game_version ON matches(game_version);
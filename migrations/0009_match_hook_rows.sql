@@ -0,0 +1,10 @@
+-- Rows produced by match hooks, replaced whenever a hook processes the match again
+CREATE TABLE IF NOT EXISTS match_hook_rows (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    match_id TEXT NOT NULL,
+    hook TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_match_hook_rows_match ON match_hook_rows(match_id, hook);
@@ -0,0 +1,171 @@
+-- Crawler schema for PostgreSQL, equivalent to SQLite schema version 4
+CREATE TABLE IF NOT EXISTS summoners (
+    puuid TEXT PRIMARY KEY,
+    summoner_id TEXT,
+    account_id TEXT,
+    summoner_name TEXT,
+    profile_icon_id INTEGER,
+    summoner_level INTEGER,
+    region TEXT,
+    created_at TIMESTAMPTZ DEFAULT now(),
+    updated_at TIMESTAMPTZ DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS matches (
+    match_id TEXT PRIMARY KEY,
+    game_creation BIGINT,
+    game_duration INTEGER,
+    game_end_timestamp BIGINT,
+    game_id BIGINT,
+    game_mode TEXT,
+    game_name TEXT,
+    game_type TEXT,
+    game_version TEXT,
+    map_id INTEGER,
+    platform_id TEXT,
+    queue_id INTEGER,
+    tournament_code TEXT,
+    region TEXT,
+    created_at TIMESTAMPTZ DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS participants (
+    id BIGSERIAL PRIMARY KEY,
+    match_id TEXT NOT NULL,
+    puuid TEXT NOT NULL,
+    summoner_name TEXT,
+    champion_id INTEGER,
+    champion_name TEXT,
+    team_id INTEGER,
+    position TEXT,
+    individual_position TEXT,
+    kills INTEGER,
+    deaths INTEGER,
+    assists INTEGER,
+    total_damage_dealt INTEGER,
+    total_damage_dealt_to_champions INTEGER,
+    total_damage_taken INTEGER,
+    gold_earned INTEGER,
+    gold_spent INTEGER,
+    turret_kills INTEGER,
+    inhibitor_kills INTEGER,
+    total_minions_killed INTEGER,
+    neutral_minions_killed INTEGER,
+    champion_level INTEGER,
+    items_0 INTEGER,
+    items_1 INTEGER,
+    items_2 INTEGER,
+    items_3 INTEGER,
+    items_4 INTEGER,
+    items_5 INTEGER,
+    items_6 INTEGER,
+    summoner_spell_1 INTEGER,
+    summoner_spell_2 INTEGER,
+    primary_rune_tree INTEGER,
+    secondary_rune_tree INTEGER,
+    win BOOLEAN,
+    first_blood_kill BOOLEAN,
+    first_tower_kill BOOLEAN,
+    UNIQUE (match_id, puuid)
+);
+
+CREATE TABLE IF NOT EXISTS teams (
+    id BIGSERIAL PRIMARY KEY,
+    match_id TEXT NOT NULL,
+    team_id INTEGER,
+    win BOOLEAN,
+    first_baron BOOLEAN,
+    first_dragon BOOLEAN,
+    first_inhibitor BOOLEAN,
+    first_rift_herald BOOLEAN,
+    first_tower BOOLEAN,
+    baron_kills INTEGER,
+    dragon_kills INTEGER,
+    inhibitor_kills INTEGER,
+    rift_herald_kills INTEGER,
+    tower_kills INTEGER,
+    UNIQUE (match_id, team_id)
+);
+
+CREATE TABLE IF NOT EXISTS bans (
+    id BIGSERIAL PRIMARY KEY,
+    match_id TEXT NOT NULL,
+    team_id INTEGER,
+    champion_id INTEGER,
+    pick_turn INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS crawler_state (
+    id INTEGER PRIMARY KEY,
+    last_processed_summoner TEXT,
+    total_summoners_processed INTEGER,
+    total_matches_processed INTEGER,
+    queue_size INTEGER,
+    last_update TIMESTAMPTZ DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS api_calls (
+    id BIGSERIAL PRIMARY KEY,
+    endpoint TEXT,
+    region TEXT,
+    timestamp TIMESTAMPTZ,
+    response_code INTEGER,
+    rate_limit_remaining INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS summoner_ranks (
+    puuid TEXT,
+    queue_type TEXT,
+    tier TEXT,
+    rank TEXT,
+    league_points INTEGER,
+    wins INTEGER,
+    losses INTEGER,
+    updated_at TIMESTAMPTZ DEFAULT now(),
+    PRIMARY KEY (puuid, queue_type)
+);
+
+CREATE TABLE IF NOT EXISTS match_strata (
+    match_id TEXT PRIMARY KEY,
+    region TEXT,
+    tier TEXT,
+    patch TEXT
+);
+
+CREATE TABLE IF NOT EXISTS failed_tasks (
+    id BIGSERIAL PRIMARY KEY,
+    task_type TEXT NOT NULL,
+    target_id TEXT NOT NULL,
+    region TEXT,
+    summoner_name TEXT,
+    error_kind TEXT,
+    http_status INTEGER,
+    attempts INTEGER,
+    last_error TEXT,
+    status TEXT DEFAULT 'failed',
+    first_failed_at TIMESTAMPTZ DEFAULT now(),
+    last_failed_at TIMESTAMPTZ DEFAULT now(),
+    UNIQUE (task_type, target_id)
+);
+
+CREATE TABLE IF NOT EXISTS pending_tasks (
+    puuid TEXT PRIMARY KEY,
+    summoner_name TEXT,
+    region TEXT,
+    priority TEXT,
+    tier TEXT,
+    retries BIGINT,
+    retry_class TEXT,
+    not_before TIMESTAMPTZ,
+    added_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_participants_match_id ON participants(match_id);
+CREATE INDEX IF NOT EXISTS idx_participants_puuid ON participants(puuid);
+CREATE INDEX IF NOT EXISTS idx_matches_game_creation ON matches(game_creation);
+CREATE INDEX IF NOT EXISTS idx_matches_queue_id ON matches(queue_id);
+CREATE INDEX IF NOT EXISTS idx_summoners_region ON summoners(region);
+CREATE INDEX IF NOT EXISTS idx_bans_match_id ON bans(match_id);
+CREATE INDEX IF NOT EXISTS idx_teams_match_id ON teams(match_id);
+CREATE INDEX IF NOT EXISTS idx_match_strata_stratum ON match_strata(region, tier, patch);
+CREATE INDEX IF NOT EXISTS idx_failed_tasks_status ON failed_tasks(status, error_kind);
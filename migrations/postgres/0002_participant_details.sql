@@ -0,0 +1,127 @@
+-- Remaining ParticipantDto stats (vision, pings, damage breakdowns, ...)
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS all_in_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS assist_me_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS baron_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS bounty_level INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS champ_experience INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS command_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS champion_transform INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS consumables_purchased INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS damage_dealt_to_buildings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS damage_dealt_to_objectives INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS damage_dealt_to_turrets INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS damage_self_mitigated INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS detector_wards_placed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS double_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS dragon_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS eligible_for_progression BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS enemy_missing_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS enemy_vision_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS first_blood_assist BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS first_tower_assist BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS game_ended_in_early_surrender BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS game_ended_in_surrender BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS hold_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS get_back_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS inhibitor_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS inhibitors_lost INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS items_purchased INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS killing_sprees INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS largest_critical_strike INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS largest_killing_spree INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS largest_multi_kill INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS longest_time_spent_living INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS magic_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS magic_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS magic_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS need_vision_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS nexus_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS nexus_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS nexus_lost INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS objectives_stolen INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS objectives_stolen_assists INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS on_my_way_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS participant_id INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS penta_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS physical_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS physical_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS physical_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS placement INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS player_augment1 INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS player_augment2 INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS player_augment3 INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS player_augment4 INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS player_subteam_id INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS push_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS profile_icon INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS quadra_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS riot_id_game_name TEXT;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS riot_id_tagline TEXT;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS role TEXT;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS sight_wards_bought_in_game INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS spell1_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS spell2_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS spell3_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS spell4_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS subteam_placement INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS summoner1_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS summoner2_casts INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS summoner_id TEXT;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS summoner_level INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS team_early_surrendered BOOLEAN;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS team_position TEXT;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS time_ccing_others INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS time_played INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_ally_jungle_minions_killed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_damage_shielded_on_teammates INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_enemy_jungle_minions_killed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_heal INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_heals_on_teammates INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_time_cc_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_time_spent_dead INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS total_units_healed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS triple_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS true_damage_dealt INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS true_damage_dealt_to_champions INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS true_damage_taken INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS turret_takedowns INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS turrets_lost INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS unreal_kills INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS vision_score INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS vision_cleared_pings INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS vision_wards_bought_in_game INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS wards_killed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS wards_placed INTEGER;
+ALTER TABLE participants ADD COLUMN IF NOT EXISTS missions TEXT;
+
+-- Full rune page: one row per rune, plus the three stat shards (style = 'statPerks')
+CREATE TABLE IF NOT EXISTS participant_perks (
+    id BIGSERIAL PRIMARY KEY,
+    match_id TEXT NOT NULL,
+    puuid TEXT NOT NULL,
+    style TEXT NOT NULL,
+    style_id INTEGER,
+    slot INTEGER NOT NULL,
+    perk_id INTEGER NOT NULL,
+    var1 INTEGER,
+    var2 INTEGER,
+    var3 INTEGER,
+    UNIQUE(match_id, puuid, style, slot)
+);
+
+CREATE INDEX IF NOT EXISTS idx_participant_perks_match_id ON participant_perks(match_id);
+CREATE INDEX IF NOT EXISTS idx_participant_perks_perk_id ON participant_perks(perk_id);
+
+-- Every entry of ParticipantDto.challenges, one row per name
+CREATE TABLE IF NOT EXISTS participant_challenges (
+    id BIGSERIAL PRIMARY KEY,
+    match_id TEXT NOT NULL,
+    puuid TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value DOUBLE PRECISION,
+    value_json TEXT,
+    UNIQUE(match_id, puuid, name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_participant_challenges_match_id ON participant_challenges(match_id);
+CREATE INDEX IF NOT EXISTS idx_participant_challenges_name ON participant_challenges(name);
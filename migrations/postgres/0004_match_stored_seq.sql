@@ -0,0 +1,6 @@
+-- Sequence number assigned each time a match is stored; exports use it as a watermark
+CREATE SEQUENCE IF NOT EXISTS matches_stored_seq;
+ALTER TABLE matches ADD COLUMN IF NOT EXISTS stored_seq BIGINT;
+UPDATE matches SET stored_seq = nextval('matches_stored_seq') WHERE stored_seq IS NULL;
+ALTER TABLE matches ALTER COLUMN stored_seq SET DEFAULT nextval('matches_stored_seq');
+CREATE INDEX IF NOT EXISTS idx_matches_stored_seq ON matches(stored_seq);
@@ -0,0 +1,7 @@
+-- Matches a match hook declined, so they are skipped instead of downloaded again
+CREATE TABLE IF NOT EXISTS vetoed_matches (
+    match_id TEXT PRIMARY KEY,
+    region TEXT NOT NULL,
+    hook TEXT NOT NULL,
+    vetoed_at TIMESTAMPTZ NOT NULL
+);
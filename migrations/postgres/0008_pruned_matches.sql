@@ -0,0 +1,6 @@
+-- Matches deleted by retention, so they are skipped instead of downloaded again
+CREATE TABLE IF NOT EXISTS pruned_matches (
+    match_id TEXT PRIMARY KEY,
+    game_version TEXT,
+    pruned_at TIMESTAMPTZ NOT NULL
+);
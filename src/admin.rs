@@ -0,0 +1,205 @@
+use crate::config::AdminConfig;
+use crate::crawler::CrawlerEngine;
+use crate::metrics::metrics;
+use crate::models::database::SummonerPriority;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+#[derive(Clone)]
+struct AdminState {
+    engine: Arc<CrawlerEngine>,
+    token: Option<String>,
+    drain_timeout: Duration,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnqueueRequest {
+    puuid: String,
+    region: String,
+    /// Defaults to `High` so manually queued players are crawled next
+    #[serde(default)]
+    priority: Option<SummonerPriority>,
+}
+
+/// Routes of the admin API. POST endpoints require `Authorization: Bearer <token>`
+/// when `config.token` is set.
+pub fn router(engine: Arc<CrawlerEngine>, config: &AdminConfig, drain_timeout: Duration) -> Router {
+    let state = AdminState {
+        engine,
+        token: config.token.clone(),
+        drain_timeout,
+    };
+
+    Router::new()
+        .route("/health", get(health))
+        .route("/ready", get(ready))
+        .route("/status", get(status))
+        .route("/metrics", get(prometheus_metrics))
+        .route("/pause", post(pause))
+        .route("/resume", post(resume))
+        .route("/enqueue", post(enqueue))
+        .route("/drain", post(drain))
+        .route("/reload", post(reload))
+        .with_state(state)
+}
+
+/// Bind the admin API's listener, so a bad address fails at startup rather than in
+/// the background
+pub async fn bind(address: &str) -> crate::Result<TcpListener> {
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to bind admin API to {}: {}", address, e))?;
+    log::info!("Admin API listening on {}", listener.local_addr()?);
+    Ok(listener)
+}
+
+/// Serve the admin API on `listener` until the process exits
+pub async fn serve(listener: TcpListener, router: Router) -> crate::Result<()> {
+    axum::serve(listener, router).await?;
+    Ok(())
+}
+
+/// Liveness stays 200 while the process is up; a crawler paused for lack of a working
+/// API key says so, since restarting it would not help
+async fn health(State(state): State<AdminState>) -> Json<serde_json::Value> {
+    match state.engine.auth_paused_since() {
+        Some(since) => Json(json!({
+            "status": "paused",
+            "reason": format!("every API key has been rejected since {}", since.to_rfc3339()),
+        })),
+        None => Json(json!({ "status": "ok" })),
+    }
+}
+
+async fn ready(State(state): State<AdminState>) -> Response {
+    let readiness = state.engine.readiness().await;
+    let check = |result: &std::result::Result<(), String>| match result {
+        Ok(()) => json!({ "ok": true }),
+        Err(e) => json!({ "ok": false, "error": e }),
+    };
+    let code = if readiness.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        code,
+        Json(json!({
+            "ready": readiness.is_ready(),
+            "checks": {
+                "database": check(&readiness.database),
+                "rate_limiter": check(&readiness.rate_limiter),
+                "api_keys": check(&readiness.api_keys),
+            },
+        })),
+    )
+        .into_response()
+}
+
+async fn status(State(state): State<AdminState>) -> Response {
+    Json(state.engine.get_status().await).into_response()
+}
+
+async fn prometheus_metrics() -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+        .into_response()
+}
+
+async fn pause(State(state): State<AdminState>, headers: HeaderMap) -> Response {
+    if !is_authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    state.engine.pause().await;
+    Json(json!({ "paused": true })).into_response()
+}
+
+async fn resume(State(state): State<AdminState>, headers: HeaderMap) -> Response {
+    if !is_authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    state.engine.resume().await;
+    Json(json!({ "paused": false })).into_response()
+}
+
+async fn enqueue(
+    State(state): State<AdminState>,
+    headers: HeaderMap,
+    Json(request): Json<EnqueueRequest>,
+) -> Response {
+    if !is_authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    let priority = request.priority.unwrap_or(SummonerPriority::High);
+    match state
+        .engine
+        .enqueue_player(&request.puuid, &request.region, priority.clone())
+        .await
+    {
+        Ok(()) => (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "puuid": request.puuid,
+                "region": request.region,
+                "priority": priority,
+            })),
+        )
+            .into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+/// Start a graceful drain and return without waiting for it; the crawler exits once
+/// the queue is persisted
+async fn drain(State(state): State<AdminState>, headers: HeaderMap) -> Response {
+    if !is_authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    log::info!("Drain requested through the admin API");
+    let engine = state.engine.clone();
+    let timeout = state.drain_timeout;
+    tokio::spawn(async move {
+        if let Err(e) = engine.drain(timeout).await {
+            log::error!("Failed to persist queue during drain: {}", e);
+        }
+    });
+    (StatusCode::ACCEPTED, Json(json!({ "draining": true }))).into_response()
+}
+
+/// Reload the configuration, applying it only if every changed key can change live
+async fn reload(State(state): State<AdminState>, headers: HeaderMap) -> Response {
+    if !is_authorized(&state, &headers) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid bearer token");
+    }
+    log::info!("Configuration reload requested through the admin API");
+    match state.engine.reload().await {
+        Ok(applied) => Json(json!({ "applied": applied })).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, &format!("{:#}", e)),
+    }
+}
+
+fn is_authorized(state: &AdminState, headers: &HeaderMap) -> bool {
+    let Some(token) = &state.token else {
+        return true;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        == Some(token.as_str())
+}
+
+fn error(code: StatusCode, message: &str) -> Response {
+    (code, Json(json!({ "error": message }))).into_response()
+}
@@ -1,29 +1,32 @@
-use super::{ApiError, Endpoints};
+use super::{ApiError, ApiKey, ApiKeyPool, Endpoints};
 use crate::config::Config;
-use crate::database::Database;
+use crate::database::{endpoint_template, Storage};
+use crate::metrics::metrics;
 use crate::models::database::DbApiCall;
 use crate::models::riot::*;
 use crate::models::MatchDto;
-use crate::rate_limiter::RateLimiter;
 use chrono::Utc;
 use reqwest::{Client, Response};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
 
 #[derive(Clone)]
 pub struct RiotApiClient {
     client: Client,
-    rate_limiter: Arc<RateLimiter>,
+    api_keys: Arc<ApiKeyPool>,
     config: Config,
-    database: Database,
+    database: Arc<dyn Storage>,
+    /// Requests sent by this client and its clones
+    requests_made: Arc<AtomicU64>,
 }
 
 impl RiotApiClient {
     pub fn new(
         config: Config,
-        rate_limiter: Arc<RateLimiter>,
-        database: Database,
+        api_keys: Arc<ApiKeyPool>,
+        database: Arc<dyn Storage>,
     ) -> Result<Self, ApiError> {
         let client = Client::builder()
             .timeout(Duration::from_secs(30))
@@ -32,13 +35,40 @@ impl RiotApiClient {
 
         Ok(Self {
             client,
-            rate_limiter,
+            api_keys,
             config,
             database,
+            requests_made: Arc::new(AtomicU64::new(0)),
         })
     }
 
+    /// Number of requests sent to the Riot API since the client was created
+    pub fn requests_made(&self) -> u64 {
+        self.requests_made.load(Ordering::Relaxed)
+    }
+
+    /// Send the request with the key that has the most headroom. A key retired by
+    /// the response hands the request to the next usable key.
     async fn make_request(&self, url: &str, region: &str) -> Result<Response, ApiError> {
+        loop {
+            let key = self.api_keys.choose().await?;
+            match self.make_request_with_key(&key, url, region).await {
+                Err(ApiError::Authentication)
+                    if key.is_retired() && self.api_keys.usable_keys() > 0 =>
+                {
+                    continue
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn make_request_with_key(
+        &self,
+        key: &ApiKey,
+        url: &str,
+        region: &str,
+    ) -> Result<Response, ApiError> {
         let endpoint = url
             .split(&self.config.base_url_for_region(region))
             .nth(1)
@@ -48,21 +78,36 @@ impl RiotApiClient {
             })
             .unwrap_or(url);
 
-        log::debug!("Making API request to URL: {}", url);
-        log::debug!("Endpoint: {}, Region: {}", endpoint, region);
+        tracing::debug!(url, endpoint, region, key = key.label(), "Requesting");
 
         // Acquire rate limit permit
-        self.rate_limiter
+        key.rate_limiter()
             .acquire_permit(endpoint, region)
             .await
             .map_err(|e| ApiError::RateLimiter(e.to_string()))?;
 
+        self.requests_made.fetch_add(1, Ordering::Relaxed);
+        let endpoint_label = endpoint_template(endpoint);
+        let started = Instant::now();
         let response = self
             .client
             .get(url)
-            .header("X-Riot-Token", &self.config.riot_api_key)
+            .header("X-Riot-Token", key.secret())
             .send()
-            .await?;
+            .await;
+        metrics()
+            .api_request_duration
+            .with_label_values(&[&endpoint_label, region])
+            .observe(started.elapsed().as_secs_f64());
+        let status = match &response {
+            Ok(response) => response.status().as_str().to_string(),
+            Err(_) => "error".to_string(),
+        };
+        metrics()
+            .api_requests
+            .with_label_values(&[&endpoint_label, region, &status])
+            .inc();
+        let response = response?;
 
         // Log API call
         let api_call = DbApiCall {
@@ -78,30 +123,47 @@ impl RiotApiClient {
                 .and_then(|s| s.parse().ok()),
         };
 
-        if let Err(e) = self.database.log_api_call(&api_call) {
+        if let Err(e) = self.database.log_api_call(&api_call).await {
             log::warn!("Failed to log API call: {}", e);
         }
 
         // Update rate limiters from headers
-        self.rate_limiter
+        key.rate_limiter()
             .update_limits_from_headers(endpoint, region, response.headers())
             .await;
 
+        if !matches!(response.status().as_u16(), 401 | 403) {
+            key.record_accepted();
+        }
+
         match response.status().as_u16() {
             200 => Ok(response),
             400 => Err(ApiError::BadRequest(
                 response.text().await.unwrap_or_default(),
             )),
-            401 | 403 => Err(ApiError::Authentication),
+            401 | 403 => {
+                key.record_auth_failure();
+                Err(ApiError::Authentication)
+            }
             404 => Err(ApiError::NotFound),
             429 => {
+                let limit_type = response
+                    .headers()
+                    .get("X-Rate-Limit-Type")
+                    .and_then(|h| h.to_str().ok())
+                    .unwrap_or("unspecified");
+                metrics()
+                    .api_rate_limited
+                    .with_label_values(&[limit_type])
+                    .inc();
+
                 let retry_after = response
                     .headers()
                     .get("Retry-After")
                     .and_then(|h| h.to_str().ok())
                     .and_then(|s| s.parse().ok());
 
-                self.rate_limiter.handle_429_response(retry_after).await;
+                key.rate_limiter().handle_429_response(retry_after).await;
                 Err(ApiError::RateLimit)
             }
             500..=599 => Err(ApiError::ServiceUnavailable),
@@ -116,27 +178,23 @@ impl RiotApiClient {
     where
         T: serde::de::DeserializeOwned,
     {
-        let max_retries = self.config.rate_limits.max_retries;
+        let text = self.fetch_text_with_retry(url, region).await?;
+        parse_response(url, &text)
+    }
+
+    /// Response body of a successful request, retrying retryable errors
+    async fn fetch_text_with_retry(&self, url: &str, region: &str) -> Result<String, ApiError> {
+        // Retry settings can change while the crawler runs; the pool holds the live ones
+        let limits = self.api_keys.rate_limit_config();
+        let max_retries = limits.max_retries;
         let mut retries = 0;
 
         loop {
             match self.make_request(url, region).await {
-                Ok(response) => {
-                    let text = response.text().await?;
-                    match serde_json::from_str::<T>(&text) {
-                        Ok(data) => return Ok(data),
-                        Err(e) => {
-                            log::error!("Failed to parse JSON response from {}: {}", url, e);
-                            log::debug!("Response body: {}", text);
-                            return Err(ApiError::Json(e));
-                        }
-                    }
-                }
+                Ok(response) => return Ok(response.text().await?),
                 Err(e) if e.is_retryable() && retries < max_retries => {
                     retries += 1;
-                    let delay = Duration::from_millis(
-                        self.config.rate_limits.retry_delay_ms * (1 << retries),
-                    );
+                    let delay = Duration::from_millis(limits.retry_delay_ms * (1 << retries));
                     log::warn!(
                         "Request failed (attempt {}/{}): {}. Retrying in {:?}",
                         retries,
@@ -165,6 +223,22 @@ impl RiotApiClient {
         self.make_request_with_retry(&url, region).await
     }
 
+    pub async fn get_account_by_riot_id(
+        &self,
+        region: &str,
+        game_name: &str,
+        tag_line: &str,
+    ) -> Result<AccountResponse, ApiError> {
+        let url = Endpoints::account_by_riot_id(&self.config, region, game_name, tag_line);
+        log::debug!(
+            "Fetching account for Riot ID: {}#{} in region: {}",
+            game_name,
+            tag_line,
+            region
+        );
+        self.make_request_with_retry(&url, region).await
+    }
+
     pub async fn get_summoner_by_puuid(
         &self,
         region: &str,
@@ -219,6 +293,32 @@ impl RiotApiClient {
         self.make_request_with_retry(&url, region).await
     }
 
+    /// The match together with the response body it was parsed from, for the raw archive
+    pub async fn get_match_payload(
+        &self,
+        region: &str,
+        match_id: &str,
+    ) -> Result<(MatchDto, String), ApiError> {
+        let url = Endpoints::match_by_id(&self.config, region, match_id);
+        log::debug!("Fetching match: {} in region: {}", match_id, region);
+        let text = self.fetch_text_with_retry(&url, region).await?;
+        let match_data = parse_response(&url, &text)?;
+        Ok((match_data, text))
+    }
+
+    /// Response body of the match timeline; the timeline is archived, not parsed
+    pub async fn get_match_timeline_payload(
+        &self,
+        region: &str,
+        match_id: &str,
+    ) -> Result<String, ApiError> {
+        let url = Endpoints::match_timeline(&self.config, region, match_id);
+        log::debug!("Fetching timeline: {} in region: {}", match_id, region);
+        let text = self.fetch_text_with_retry(&url, region).await?;
+        parse_response::<serde::de::IgnoredAny>(&url, &text)?;
+        Ok(text)
+    }
+
     pub async fn get_master_league(
         &self,
         region: &str,
@@ -261,8 +361,43 @@ impl RiotApiClient {
         self.make_request_with_retry(&url, region).await
     }
 
+    pub async fn get_league_entries_by_puuid(
+        &self,
+        region: &str,
+        puuid: &str,
+    ) -> Result<Vec<LeagueEntryDto>, ApiError> {
+        let url = Endpoints::league_entries_by_puuid(&self.config, region, puuid);
+        log::debug!(
+            "Fetching league entries for PUUID: {} in region: {}",
+            puuid,
+            region
+        );
+        self.make_request_with_retry(&url, region).await
+    }
+
+    pub async fn get_league_entries(
+        &self,
+        region: &str,
+        queue: &str,
+        tier: &str,
+        division: &str,
+        page: u32,
+    ) -> Result<Vec<LeagueEntryDto>, ApiError> {
+        let url = Endpoints::league_entries(&self.config, region, queue, tier, division, page);
+        log::debug!(
+            "Fetching {} {} ladder page {} for queue: {} in region: {}",
+            tier,
+            division,
+            page,
+            queue,
+            region
+        );
+        self.make_request_with_retry(&url, region).await
+    }
+
+    /// Rate limits of the usable keys, added up
     pub async fn get_rate_limit_status(&self) -> crate::rate_limiter::RateLimitStatus {
-        self.rate_limiter.get_rate_limit_status().await
+        self.api_keys.rate_limit_status().await
     }
 }
 
@@ -295,6 +430,20 @@ pub struct LeagueEntry {
     pub mini_series: Option<MiniSeries>,
 }
 
+/// League entry as returned by the `entries` endpoints, which carry the tier per entry
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+pub struct LeagueEntryDto {
+    pub puuid: String,
+    #[serde(rename = "queueType")]
+    pub queue_type: String,
+    pub tier: String,
+    pub rank: String,
+    #[serde(rename = "leaguePoints")]
+    pub league_points: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
 pub struct MiniSeries {
     pub losses: u32,
@@ -303,18 +452,30 @@ pub struct MiniSeries {
     pub wins: u32,
 }
 
+fn parse_response<T: serde::de::DeserializeOwned>(url: &str, text: &str) -> Result<T, ApiError> {
+    serde_json::from_str::<T>(text).map_err(|e| {
+        log::error!("Failed to parse JSON response from {}: {}", url, e);
+        log::debug!("Response body: {}", text);
+        ApiError::Json(e)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Config, CrawlerConfig, LoggingConfig, RateLimitConfig};
+    use crate::api::queues;
+    use crate::api::RETIRE_AFTER_AUTH_FAILURES;
+    use crate::config::{ApiKeyConfig, Config, CrawlerConfig, LoggingConfig, RateLimitConfig};
     use crate::database::Database;
-    use crate::rate_limiter::RateLimiter;
     use mockito::Server;
     use std::sync::Arc;
 
     fn test_config() -> Config {
         Config {
             riot_api_key: "RGAPI-test-key".to_string(),
+            api_keys: Vec::new(),
+            api_project: None,
+            api_base_url: None,
             database_url: ":memory:".to_string(),
             regions: vec!["na1".to_string()],
             rate_limits: RateLimitConfig {
@@ -329,19 +490,30 @@ mod tests {
                 batch_size: 10,
                 health_check_interval_seconds: 60,
                 state_save_interval_seconds: 300,
+                drain_timeout_seconds: 30,
+                hook_timeout_seconds: 10,
+                queue_id: 420,
+                seed_from_ladder: true,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
                 format: "json".to_string(),
             },
+            sampling: Default::default(),
+            budget: Default::default(),
+            archive: Default::default(),
+            retention: Default::default(),
+            admin: Default::default(),
+            events: Default::default(),
+            region_overrides: Default::default(),
         }
     }
 
     async fn setup_test_client() -> (RiotApiClient, Database) {
         let config = test_config();
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database.clone()).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database.clone())).unwrap();
         (client, database)
     }
 
@@ -395,8 +567,8 @@ mod tests {
 
         // Create a custom client for testing with mock server URL
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         // Construct the mock URL manually for testing
         let test_url = format!(
@@ -411,6 +583,100 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn test_retired_key_hands_requests_to_the_next_key() {
+        let mut server = Server::new_async().await;
+        let rejected = server
+            .mock("GET", "/lol/summoner/v4/summoners/by-puuid/test-puuid")
+            .match_header("X-Riot-Token", "RGAPI-expired")
+            .with_status(403)
+            .expect(RETIRE_AFTER_AUTH_FAILURES as usize)
+            .create_async()
+            .await;
+        let accepted = server
+            .mock("GET", "/lol/summoner/v4/summoners/by-puuid/test-puuid")
+            .match_header("X-Riot-Token", "RGAPI-fresh")
+            .with_status(200)
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        // Both keys belong to one application, and the expiring one has more headroom
+        let mut config = test_config();
+        config.riot_api_key = String::new();
+        config.api_keys = vec![
+            ApiKeyConfig {
+                label: "expiring".to_string(),
+                key: Some("RGAPI-expired".to_string()),
+                project: Some("production".to_string()),
+                limit_per_second: Some(50),
+                ..Default::default()
+            },
+            ApiKeyConfig {
+                label: "fresh".to_string(),
+                key: Some("RGAPI-fresh".to_string()),
+                project: Some("production".to_string()),
+                ..Default::default()
+            },
+        ];
+        let database = Database::new(":memory:").unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys.clone(), Arc::new(database)).unwrap();
+
+        let url = format!(
+            "{}/lol/summoner/v4/summoners/by-puuid/test-puuid",
+            server.url()
+        );
+        for _ in 1..RETIRE_AFTER_AUTH_FAILURES {
+            let result = client.make_request(&url, "mock").await;
+            assert!(matches!(result, Err(ApiError::Authentication)));
+        }
+        // The failure that retires the key is retried with the other one
+        assert!(client.make_request(&url, "mock").await.is_ok());
+        assert_eq!(api_keys.usable_keys(), 1);
+
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_requests_are_counted_in_metrics() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/lol/match/v5/matches/NA1_429")
+            .with_status(429)
+            .with_header("Retry-After", "0")
+            .with_header("X-Rate-Limit-Type", "method")
+            .create_async()
+            .await;
+
+        let (client, _) = setup_test_client().await;
+        let rate_limited = metrics().api_rate_limited.with_label_values(&["method"]);
+        let before = rate_limited.get();
+
+        let url = format!("{}/lol/match/v5/matches/NA1_429", server.url());
+        let result = client.make_request(&url, "metrics-test").await;
+        assert!(matches!(result, Err(ApiError::RateLimit)));
+        mock.assert_async().await;
+
+        assert_eq!(rate_limited.get(), before + 1);
+        let endpoint = endpoint_template(&url);
+        assert_eq!(
+            metrics()
+                .api_requests
+                .with_label_values(&[&endpoint, "metrics-test", "429"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics()
+                .api_request_duration
+                .with_label_values(&[&endpoint, "metrics-test"])
+                .get_sample_count(),
+            1
+        );
+    }
+
     #[tokio::test]
     async fn test_http_401_authentication_error() {
         let error = ApiError::Authentication;
@@ -448,8 +714,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -465,6 +731,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(clippy::assertions_on_constants)]
     async fn test_request_timeout_handling() {
         // Test that the client was created with proper timeout configuration
         // The timeout is configured during client construction but isn't directly exposed
@@ -553,6 +820,17 @@ mod tests {
         assert!(url.contains("americas.api.riotgames.com")); // Regional endpoint
     }
 
+    #[tokio::test]
+    async fn test_account_by_riot_id_endpoint() {
+        let config = test_config();
+        let url = Endpoints::account_by_riot_id(&config, "euw1", "Some Player", "EUW");
+
+        assert_eq!(
+            url,
+            "https://europe.api.riotgames.com/riot/account/v1/accounts/by-riot-id/Some%20Player/EUW"
+        );
+    }
+
     #[tokio::test]
     async fn test_master_league_endpoint() {
         let config = test_config();
@@ -615,8 +893,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -657,8 +935,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -673,6 +951,8 @@ mod tests {
         assert!(matches!(result, Err(ApiError::ServiceUnavailable)));
         // Should have waited for exponential backoff: 10ms + 20ms + 40ms = ~70ms minimum
         assert!(elapsed >= Duration::from_millis(60));
+        // Every attempt counts towards the request total
+        assert_eq!(client.requests_made(), 4);
 
         mock_error.assert_async().await;
     }
@@ -702,8 +982,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -748,8 +1028,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -789,8 +1069,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/match/v5/matches/by-puuid/test-puuid/ids?start=0&count=20",
@@ -849,8 +1129,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!("{}/lol/match/v5/matches/NA1_1234567890", mock_url);
 
@@ -904,8 +1184,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/league/v4/masterleagues/by-queue/RANKED_SOLO_5x5",
@@ -925,6 +1205,83 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn test_league_entries_by_puuid_request() {
+        let mut server = Server::new_async().await;
+        let config = test_config();
+        let mock_url = server.url();
+
+        let mock_response = r#"[
+            {
+                "leagueId": "test-league-id",
+                "puuid": "test-player-1",
+                "queueType": "RANKED_FLEX_SR",
+                "tier": "SILVER",
+                "rank": "I",
+                "leaguePoints": 12,
+                "wins": 5,
+                "losses": 7,
+                "veteran": false,
+                "inactive": false,
+                "freshBlood": false,
+                "hotStreak": false
+            },
+            {
+                "leagueId": "test-league-id-2",
+                "puuid": "test-player-1",
+                "queueType": "RANKED_SOLO_5x5",
+                "tier": "GOLD",
+                "rank": "III",
+                "leaguePoints": 55,
+                "wins": 80,
+                "losses": 75,
+                "veteran": false,
+                "inactive": false,
+                "freshBlood": false,
+                "hotStreak": true
+            }
+        ]"#;
+
+        let mock = server
+            .mock("GET", "/lol/league/v4/entries/by-puuid/test-player-1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(mock_response)
+            .create_async()
+            .await;
+
+        let database = Database::new(":memory:").unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
+
+        let test_url = format!("{}/lol/league/v4/entries/by-puuid/test-player-1", mock_url);
+
+        let entries: Vec<LeagueEntryDto> = client
+            .make_request_with_retry(&test_url, "mock")
+            .await
+            .unwrap();
+
+        let solo = entries
+            .iter()
+            .find(|e| e.queue_type == queues::RANKED_SOLO_5X5)
+            .unwrap();
+        assert_eq!(solo.tier, "GOLD");
+        assert_eq!(solo.rank, "III");
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_league_entries_endpoint() {
+        let config = test_config();
+        let url = Endpoints::league_entries(&config, "euw1", "RANKED_SOLO_5x5", "GOLD", "II", 3);
+
+        assert_eq!(
+            url,
+            "https://euw1.api.riotgames.com/lol/league/v4/entries/RANKED_SOLO_5x5/GOLD/II?page=3"
+        );
+    }
+
     #[tokio::test]
     async fn test_bad_request_400_error() {
         let mut server = Server::new_async().await;
@@ -941,8 +1298,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/Invalid@Name",
@@ -975,8 +1332,8 @@ mod tests {
             .await;
 
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = RiotApiClient::new(config, rate_limiter, database).unwrap();
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap();
 
         let test_url = format!(
             "{}/lol/summoner/v4/summoners/by-name/TestSummoner",
@@ -997,8 +1354,8 @@ mod tests {
     async fn test_concurrent_api_requests() {
         let config = test_config();
         let database = Database::new(":memory:").unwrap();
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let client = Arc::new(RiotApiClient::new(config, rate_limiter, database).unwrap());
+        let api_keys = Arc::new(ApiKeyPool::new(&config).unwrap());
+        let client = Arc::new(RiotApiClient::new(config, api_keys, Arc::new(database)).unwrap());
 
         let mut handles = vec![];
 
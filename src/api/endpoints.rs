@@ -53,6 +53,21 @@ impl Endpoints {
         url
     }
 
+    /// Account lookup by Riot ID (`gameName#tagLine`), served by the regional host
+    pub fn account_by_riot_id(
+        config: &Config,
+        region: &str,
+        game_name: &str,
+        tag_line: &str,
+    ) -> String {
+        format!(
+            "{}/riot/account/v1/accounts/by-riot-id/{}/{}",
+            config.regional_base_url_for_region(region),
+            urlencoding::encode(game_name),
+            urlencoding::encode(tag_line)
+        )
+    }
+
     pub fn match_by_id(config: &Config, region: &str, match_id: &str) -> String {
         format!(
             "{}/lol/match/v5/matches/{}",
@@ -77,6 +92,33 @@ impl Endpoints {
         )
     }
 
+    pub fn league_entries_by_puuid(config: &Config, region: &str, puuid: &str) -> String {
+        format!(
+            "{}/lol/league/v4/entries/by-puuid/{}",
+            config.base_url_for_region(region),
+            puuid
+        )
+    }
+
+    /// Paged ladder listing for a non-apex tier and division (e.g. GOLD / II)
+    pub fn league_entries(
+        config: &Config,
+        region: &str,
+        queue: &str,
+        tier: &str,
+        division: &str,
+        page: u32,
+    ) -> String {
+        format!(
+            "{}/lol/league/v4/entries/{}/{}/{}?page={}",
+            config.base_url_for_region(region),
+            queue,
+            tier,
+            division,
+            page
+        )
+    }
+
     pub fn master_league(config: &Config, region: &str, queue: &str) -> String {
         format!(
             "{}/lol/league/v4/masterleagues/by-queue/{}",
@@ -53,4 +53,32 @@ impl ApiError {
     pub fn should_retry_after_delay(&self) -> bool {
         matches!(self, ApiError::RateLimit | ApiError::ServiceUnavailable)
     }
+
+    /// Variant name, used to classify failures in the dead-letter queue
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ApiError::Http(_) => "Http",
+            ApiError::RateLimit => "RateLimit",
+            ApiError::Authentication => "Authentication",
+            ApiError::NotFound => "NotFound",
+            ApiError::ServiceUnavailable => "ServiceUnavailable",
+            ApiError::BadRequest(_) => "BadRequest",
+            ApiError::Json(_) => "Json",
+            ApiError::RateLimiter(_) => "RateLimiter",
+            ApiError::Api { .. } => "Api",
+            ApiError::Unknown(_) => "Unknown",
+        }
+    }
+
+    /// HTTP status behind the error, where the variant pins it down
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            ApiError::Http(e) => e.status().map(|s| s.as_u16()),
+            ApiError::RateLimit => Some(429),
+            ApiError::NotFound => Some(404),
+            ApiError::BadRequest(_) => Some(400),
+            ApiError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,362 @@
+use super::ApiError;
+use crate::config::{ApiKeyConfig, Config, RateLimitConfig};
+use crate::rate_limiter::{RateLimitStatus, RateLimiter};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Consecutive 401/403 responses after which a key is taken out of rotation
+pub const RETIRE_AFTER_AUTH_FAILURES: u32 = 3;
+
+/// How often key files are read again for a replacement key
+const KEY_FILE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// One Riot API key with its own rate limits
+#[derive(Debug)]
+pub struct ApiKey {
+    label: String,
+    project: String,
+    secret: RwLock<String>,
+    key_file: Option<PathBuf>,
+    rate_limiter: RateLimiter,
+    auth_failures: AtomicU32,
+    retired: AtomicBool,
+}
+
+impl ApiKey {
+    fn new(config: &ApiKeyConfig, limits: RateLimitConfig) -> crate::Result<Self> {
+        let secret = match (&config.key, &config.key_file) {
+            (Some(key), _) => key.clone(),
+            (None, Some(path)) => read_key_file(path)?,
+            (None, None) => {
+                anyhow::bail!("API key {} has neither a key nor a key file", config.label)
+            }
+        };
+        Ok(Self {
+            label: config.label.clone(),
+            project: config.project().to_string(),
+            secret: RwLock::new(secret),
+            key_file: config.key_file.clone(),
+            rate_limiter: RateLimiter::new(limits),
+            auth_failures: AtomicU32::new(0),
+            retired: AtomicBool::new(false),
+        })
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    pub fn secret(&self) -> String {
+        self.secret.read().unwrap().clone()
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    pub fn is_retired(&self) -> bool {
+        self.retired.load(Ordering::Relaxed)
+    }
+
+    /// The key was accepted, whatever the response was
+    pub fn record_accepted(&self) {
+        self.auth_failures.store(0, Ordering::Relaxed);
+    }
+
+    /// Count a 401/403, retiring the key once they repeat
+    pub fn record_auth_failure(&self) {
+        let failures = self.auth_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= RETIRE_AFTER_AUTH_FAILURES && !self.retired.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "Retiring API key {} after {} authentication failures in a row{}",
+                self.label,
+                failures,
+                if self.key_file.is_some() {
+                    "; a new key in its key file puts it back in rotation"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+
+    /// Application tokens left in the tighter of the two application windows
+    async fn headroom(&self) -> u32 {
+        let status = self.rate_limiter.get_rate_limit_status().await;
+        status
+            .application_tokens_per_second
+            .min(status.application_tokens_per_two_minutes)
+    }
+
+    /// Swap in the key file's contents if they changed, returning whether they did
+    fn reload_key_file(&self) -> crate::Result<bool> {
+        let Some(path) = &self.key_file else {
+            return Ok(false);
+        };
+        let secret = read_key_file(path)?;
+        if *self.secret.read().unwrap() == secret {
+            return Ok(false);
+        }
+        *self.secret.write().unwrap() = secret;
+        self.auth_failures.store(0, Ordering::Relaxed);
+        self.retired.store(false, Ordering::Relaxed);
+        log::info!(
+            "Loaded a new key for {} from {}",
+            self.label,
+            path.display()
+        );
+        Ok(true)
+    }
+}
+
+/// The configured API keys. Requests go to the key with the most headroom among the
+/// keys of one project: PUUIDs are encrypted per Riot application, so a PUUID one
+/// application returned means nothing to another's keys.
+#[derive(Debug)]
+pub struct ApiKeyPool {
+    keys: Vec<Arc<ApiKey>>,
+    project: Option<String>,
+    limits: RwLock<RateLimitConfig>,
+    last_file_check: Mutex<Instant>,
+}
+
+impl ApiKeyPool {
+    pub fn new(config: &Config) -> crate::Result<Self> {
+        let keys = config
+            .api_key_configs()
+            .iter()
+            .map(|key| ApiKey::new(key, key.rate_limits(&config.rate_limits)).map(Arc::new))
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Self {
+            keys,
+            project: config.api_project(),
+            limits: RwLock::new(config.rate_limits.clone()),
+            last_file_check: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// Project whose keys send requests
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    /// Retry settings; the per-key limiters hold the application limits
+    pub fn rate_limit_config(&self) -> RateLimitConfig {
+        self.limits.read().unwrap().clone()
+    }
+
+    fn keys_in_use(&self) -> impl Iterator<Item = &Arc<ApiKey>> {
+        self.keys
+            .iter()
+            .filter(|key| Some(key.project()) == self.project.as_deref())
+    }
+
+    /// Keys of the project that have not been retired
+    pub fn usable_keys(&self) -> usize {
+        self.keys_in_use().filter(|key| !key.is_retired()).count()
+    }
+
+    /// The usable key with the most headroom. Key files are checked for replacement
+    /// keys first, at most every few seconds.
+    pub async fn choose(&self) -> Result<Arc<ApiKey>, ApiError> {
+        let check_files = {
+            let mut last_check = self.last_file_check.lock().unwrap();
+            let due = last_check.elapsed() >= KEY_FILE_CHECK_INTERVAL;
+            if due {
+                *last_check = Instant::now();
+            }
+            due
+        };
+        if check_files {
+            self.reload_key_files();
+        }
+
+        let mut best: Option<(&Arc<ApiKey>, u32)> = None;
+        for key in self.keys_in_use().filter(|key| !key.is_retired()) {
+            let headroom = key.headroom().await;
+            if best.is_none_or(|(_, most)| headroom > most) {
+                best = Some((key, headroom));
+            }
+        }
+        best.map(|(key, _)| key.clone())
+            .ok_or(ApiError::Authentication)
+    }
+
+    /// Read every key file again, returning how many keys changed
+    pub fn reload_key_files(&self) -> usize {
+        let mut changed = 0;
+        for key in &self.keys {
+            match key.reload_key_file() {
+                Ok(true) => changed += 1,
+                Ok(false) => {}
+                Err(e) => log::warn!("Could not reload API key {}: {}", key.label(), e),
+            }
+        }
+        changed
+    }
+
+    /// Apply reloaded rate limits to every key
+    pub async fn update_config(&self, config: &Config) {
+        for key_config in config.api_key_configs() {
+            if let Some(key) = self.keys.iter().find(|k| k.label() == key_config.label) {
+                key.rate_limiter
+                    .update_config(key_config.rate_limits(&config.rate_limits))
+                    .await;
+            }
+        }
+        *self.limits.write().unwrap() = config.rate_limits.clone();
+    }
+
+    /// Rate limits of the usable keys, added up
+    pub async fn rate_limit_status(&self) -> RateLimitStatus {
+        let mut total = RateLimitStatus {
+            application_tokens_per_second: 0,
+            application_tokens_per_two_minutes: 0,
+            method_limiters_count: 0,
+            service_limiters_count: 0,
+        };
+        for key in self.keys_in_use().filter(|key| !key.is_retired()) {
+            let status = key.rate_limiter.get_rate_limit_status().await;
+            total.application_tokens_per_second += status.application_tokens_per_second;
+            total.application_tokens_per_two_minutes += status.application_tokens_per_two_minutes;
+            total.method_limiters_count += status.method_limiters_count;
+            total.service_limiters_count += status.service_limiters_count;
+        }
+        total
+    }
+
+    pub async fn status(&self) -> Vec<ApiKeyStatus> {
+        let mut statuses = Vec::with_capacity(self.keys.len());
+        for key in &self.keys {
+            statuses.push(ApiKeyStatus {
+                label: key.label.clone(),
+                project: key.project.clone(),
+                in_use: Some(key.project()) == self.project.as_deref(),
+                retired: key.is_retired(),
+                auth_failures: key.auth_failures.load(Ordering::Relaxed),
+                rate_limits: key.rate_limiter.get_rate_limit_status().await,
+            });
+        }
+        statuses
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyStatus {
+    pub label: String,
+    pub project: String,
+    /// Whether the key belongs to the project requests are sent with
+    pub in_use: bool,
+    pub retired: bool,
+    /// 401/403 responses in a row
+    pub auth_failures: u32,
+    pub rate_limits: RateLimitStatus,
+}
+
+fn read_key_file(path: &Path) -> crate::Result<String> {
+    let secret = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read API key file {}: {}", path.display(), e))?
+        .trim()
+        .to_string();
+    if secret.is_empty() {
+        anyhow::bail!("API key file {} is empty", path.display());
+    }
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_config(label: &str, project: Option<&str>) -> ApiKeyConfig {
+        ApiKeyConfig {
+            label: label.to_string(),
+            key: Some(format!("RGAPI-{}", label)),
+            project: project.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn pool_config(keys: Vec<ApiKeyConfig>) -> Config {
+        Config {
+            riot_api_key: String::new(),
+            api_keys: keys,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_choose_prefers_headroom_within_the_project() {
+        let mut busy = key_config("busy", Some("production"));
+        busy.limit_per_second = Some(15);
+        let mut config = pool_config(vec![
+            busy,
+            key_config("spare", Some("production")),
+            key_config("personal", None),
+        ]);
+        config.rate_limits.application_limit_per_second = 10;
+        let pool = ApiKeyPool::new(&config).unwrap();
+        assert_eq!(pool.project(), Some("production"));
+
+        // 15 tokens beat 10, until the busy key is down to 9
+        assert_eq!(pool.choose().await.unwrap().label(), "busy");
+        for _ in 0..6 {
+            pool.choose()
+                .await
+                .unwrap()
+                .rate_limiter()
+                .acquire_permit("/test", "na1")
+                .await
+                .unwrap();
+        }
+        assert_eq!(pool.choose().await.unwrap().label(), "spare");
+
+        // The personal key belongs to another application and is never chosen
+        let statuses = pool.status().await;
+        assert!(
+            !statuses
+                .iter()
+                .find(|s| s.label == "personal")
+                .unwrap()
+                .in_use
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retired_key_is_replaced_from_its_file() {
+        let path = std::env::temp_dir().join(format!("lol-crawler-key-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "RGAPI-expired\n").unwrap();
+        let config = pool_config(vec![ApiKeyConfig {
+            label: "development".to_string(),
+            key_file: Some(path.clone()),
+            ..Default::default()
+        }]);
+        let pool = ApiKeyPool::new(&config).unwrap();
+        let key = pool.choose().await.unwrap();
+        assert_eq!(key.secret(), "RGAPI-expired");
+
+        for _ in 0..RETIRE_AFTER_AUTH_FAILURES {
+            key.record_auth_failure();
+        }
+        assert!(key.is_retired());
+        assert_eq!(pool.usable_keys(), 0);
+        assert!(matches!(pool.choose().await, Err(ApiError::Authentication)));
+
+        // An unchanged file leaves the key retired
+        assert_eq!(pool.reload_key_files(), 0);
+        std::fs::write(&path, "RGAPI-renewed").unwrap();
+        assert_eq!(pool.reload_key_files(), 1);
+        let key = pool.choose().await.unwrap();
+        assert_eq!(key.secret(), "RGAPI-renewed");
+        assert!(!key.is_retired());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
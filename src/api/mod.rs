@@ -1,7 +1,9 @@
 mod client;
 mod endpoints;
 mod error;
+mod keys;
 
 pub use client::RiotApiClient;
 pub use endpoints::*;
 pub use error::ApiError;
+pub use keys::{ApiKey, ApiKeyPool, ApiKeyStatus, RETIRE_AFTER_AUTH_FAILURES};
@@ -1,16 +1,174 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Config file read when none is given: `lol-crawler.toml`, `.yaml`, `.yml` or `.json`
+/// in the working directory
+pub const DEFAULT_CONFIG_FILE: &str = "lol-crawler";
+
+/// Prefix of the environment variables that override any key, with `__` between
+/// levels, e.g. `LOL_CRAWLER__RATE_LIMITS__MAX_RETRIES=5`
+pub const ENV_PREFIX: &str = "LOL_CRAWLER";
+
+/// Settings a running crawler picks up when its configuration is reloaded, with
+/// everything under them; changing any other key needs a restart
+pub const RELOADABLE_KEYS: &[&str] = &[
+    "regions",
+    "region",
+    "crawler.queue_id",
+    "crawler.seed_from_ladder",
+    "rate_limits.application_limit_per_second",
+    "rate_limits.application_limit_per_two_minutes",
+    "rate_limits.retry_delay_ms",
+    "rate_limits.max_retries",
+    "budget",
+    "retention",
+    "logging.level",
+];
+
+/// Environment variables predating `ENV_PREFIX`, and the keys they set
+const LEGACY_ENV_VARS: &[(&str, &str)] = &[
+    ("RIOT_API_KEY", "riot_api_key"),
+    ("DATABASE_URL", "database_url"),
+    ("REGIONS", "regions"),
+    ("LOG_LEVEL", "logging.level"),
+    ("LOG_FORMAT", "logging.format"),
+    (
+        "APPLICATION_LIMIT_PER_SECOND",
+        "rate_limits.application_limit_per_second",
+    ),
+    (
+        "APPLICATION_LIMIT_PER_TWO_MINUTES",
+        "rate_limits.application_limit_per_two_minutes",
+    ),
+    (
+        "MAX_CONCURRENT_REQUESTS",
+        "rate_limits.max_concurrent_requests",
+    ),
+    ("QUEUE_SIZE_LIMIT", "crawler.queue_size_limit"),
+    ("BATCH_SIZE", "crawler.batch_size"),
+    (
+        "HEALTH_CHECK_INTERVAL_SECONDS",
+        "crawler.health_check_interval_seconds",
+    ),
+    (
+        "STATE_SAVE_INTERVAL_SECONDS",
+        "crawler.state_save_interval_seconds",
+    ),
+    ("DRAIN_TIMEOUT_SECONDS", "crawler.drain_timeout_seconds"),
+    ("CRAWL_QUEUE_ID", "crawler.queue_id"),
+    ("SEED_FROM_LADDER", "crawler.seed_from_ladder"),
+    ("HOOK_TIMEOUT_SECONDS", "crawler.hook_timeout_seconds"),
+    ("SAMPLING_ENABLED", "sampling.enabled"),
+    (
+        "SAMPLING_MATCHES_PER_STRATUM",
+        "sampling.matches_per_stratum",
+    ),
+    ("SAMPLING_TIER_TARGETS", "sampling.tier_targets"),
+    (
+        "SAMPLING_SEED_PLAYERS_PER_TIER",
+        "sampling.seed_players_per_tier",
+    ),
+    ("BUDGET_MAX_REQUESTS", "budget.max_requests"),
+    ("BUDGET_MAX_MATCHES", "budget.max_matches"),
+    ("BUDGET_MAX_DURATION_SECONDS", "budget.max_duration_seconds"),
+    ("BUDGET_IDLE_MINUTES", "budget.idle_minutes"),
+    ("ARCHIVE_RAW_PAYLOADS", "archive.enabled"),
+    ("ARCHIVE_TIMELINES", "archive.timelines"),
+    ("ARCHIVE_COMPRESSION_LEVEL", "archive.compression_level"),
+    ("RETENTION_API_CALLS_DAYS", "retention.api_calls_days"),
+    ("RETENTION_KEEP_PATCHES", "retention.keep_patches"),
+    ("RETENTION_KEEP_RAW_PAYLOADS", "retention.keep_raw_payloads"),
+    ("RETENTION_INTERVAL_MINUTES", "retention.interval_minutes"),
+    ("ADMIN_BIND_ADDRESS", "admin.bind_address"),
+    ("ADMIN_TOKEN", "admin.token"),
+    ("EVENT_WEBHOOK_URL", "events.webhook_url"),
+    ("EVENT_FILE", "events.file"),
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    /// Key labelled `default`; may be left empty when `api_keys` are set
     pub riot_api_key: String,
+    /// Further keys, e.g. a production key next to a development key
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Project whose keys send requests; defaults to that of the first key
+    pub api_project: Option<String>,
+    /// Send every request here instead of the Riot hosts, e.g. to a caching proxy
+    pub api_base_url: Option<String>,
     pub database_url: String,
     pub regions: Vec<String>,
     pub rate_limits: RateLimitConfig,
     pub crawler: CrawlerConfig,
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+    #[serde(default)]
+    pub budget: BudgetConfig,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    /// Per-region overrides from `[region.<id>]` sections, keyed by region
+    #[serde(default, rename = "region")]
+    pub region_overrides: HashMap<String, RegionConfig>,
+}
+
+/// An API key from an `[[api_keys]]` section
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    /// Name used in status and logs
+    pub label: String,
+    /// The key itself, unless it is read from `key_file`
+    pub key: Option<String>,
+    /// File holding the key. It is read again while the crawler runs, so an expired
+    /// key can be replaced without a restart.
+    pub key_file: Option<PathBuf>,
+    /// Keys of one Riot application share PUUID encryption and stand in for each
+    /// other; defaults to the label, so keys are only pooled when they say so
+    pub project: Option<String>,
+    /// Application limits of this key, instead of those in `rate_limits`
+    pub limit_per_second: Option<u32>,
+    pub limit_per_two_minutes: Option<u32>,
+}
+
+impl ApiKeyConfig {
+    pub fn project(&self) -> &str {
+        self.project.as_deref().unwrap_or(&self.label)
+    }
+
+    /// `base` with this key's application limits
+    pub fn rate_limits(&self, base: &RateLimitConfig) -> RateLimitConfig {
+        let mut limits = base.clone();
+        if let Some(limit) = self.limit_per_second {
+            limits.application_limit_per_second = limit;
+        }
+        if let Some(limit) = self.limit_per_two_minutes {
+            limits.application_limit_per_two_minutes = limit;
+        }
+        limits
+    }
+}
+
+/// Settings that differ for one region; unset fields fall back to the global ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegionConfig {
+    pub queue_id: Option<i32>,
+    pub seed_from_ladder: Option<bool>,
+    /// Matches stored in this region per run; its tasks are dropped once reached
+    pub max_matches: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct RateLimitConfig {
     pub application_limit_per_second: u32,
     pub application_limit_per_two_minutes: u32,
@@ -20,23 +178,226 @@ pub struct RateLimitConfig {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct CrawlerConfig {
     pub queue_size_limit: usize,
     pub batch_size: usize,
     pub health_check_interval_seconds: u64,
     pub state_save_interval_seconds: u64,
+    /// How long a graceful drain waits for in-flight work before abandoning it
+    #[serde(default = "default_drain_timeout_seconds")]
+    pub drain_timeout_seconds: u64,
+    /// Matches from other queues are skipped; ranks still come from the solo/duo ladder
+    #[serde(default = "default_queue_id")]
+    pub queue_id: i32,
+    /// Seed from the Master+ ladder when the queue is short at startup
+    #[serde(default = "default_seed_from_ladder")]
+    pub seed_from_ladder: bool,
+    /// Limit on each call of a match hook; a hook that runs longer is abandoned
+    #[serde(default = "default_hook_timeout_seconds")]
+    pub hook_timeout_seconds: u64,
+}
+
+fn default_drain_timeout_seconds() -> u64 {
+    30
+}
+
+fn default_queue_id() -> i32 {
+    crate::api::queues::RANKED_SOLO_QUEUE_ID
+}
+
+fn default_seed_from_ladder() -> bool {
+    true
+}
+
+fn default_hook_timeout_seconds() -> u64 {
+    10
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct LoggingConfig {
+    /// Default level, or a filter like `info,lol_crawler::api=debug`; `RUST_LOG` overrides it
     pub level: String,
+    /// `json`, `pretty` or `compact`
     pub format: String,
 }
 
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: "json".to_string(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Logging settings alone, for commands that run without the full configuration
+    pub fn from_env() -> Self {
+        let mut logging = Self::default();
+        if let Ok(level) = std::env::var("LOG_LEVEL") {
+            logging.level = level;
+        }
+        if let Ok(format) = std::env::var("LOG_FORMAT") {
+            logging.format = format;
+        }
+        logging
+    }
+}
+
+/// Rank-stratified sampling targets. A stratum is (region, tier, patch); the crawler
+/// favours players from strata that are below their match target on the current patch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SamplingConfig {
+    pub enabled: bool,
+    /// Default number of matches wanted per stratum
+    pub matches_per_stratum: u64,
+    /// Per-tier overrides of `matches_per_stratum`, keyed by tier name (e.g. "GOLD")
+    #[serde(default)]
+    pub tier_targets: HashMap<String, u64>,
+    /// Players seeded from each under-represented tier's ladder at startup
+    pub seed_players_per_tier: usize,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            matches_per_stratum: 1000,
+            tier_targets: HashMap::new(),
+            seed_players_per_tier: 20,
+        }
+    }
+}
+
+/// Limits that end a crawl run; `None` means unlimited. The run drains and exits with
+/// a summary report once any limit is reached.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BudgetConfig {
+    /// Total API requests made by this run
+    pub max_requests: Option<u64>,
+    /// Ranked matches stored by this run
+    pub max_matches: Option<u64>,
+    /// Wall-clock run time
+    pub max_duration_seconds: Option<u64>,
+    /// Stop when no new match has been stored for this many minutes
+    pub idle_minutes: Option<u64>,
+}
+
+impl BudgetConfig {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_requests.is_none()
+            && self.max_matches.is_none()
+            && self.max_duration_seconds.is_none()
+            && self.idle_minutes.is_none()
+    }
+}
+
+/// Raw payload archive: the original API responses, zstd-compressed next to the
+/// normalised tables so they can be rebuilt with `reprocess`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArchiveConfig {
+    pub enabled: bool,
+    /// Also fetch and archive each match's timeline (one extra request per match)
+    pub timelines: bool,
+    /// zstd level, 1 (fastest) to 22 (smallest)
+    pub compression_level: i32,
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timelines: false,
+            compression_level: 3,
+        }
+    }
+}
+
+/// Pruning applied periodically while the crawler runs; unset rules keep data forever
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// Days of individual `api_calls` rows to keep; older rows become hourly rollups
+    pub api_calls_days: Option<u32>,
+    /// Number of most recent patches whose matches are kept
+    pub keep_patches: Option<u32>,
+    /// Keep the archived raw payloads of pruned matches so `reprocess` can restore them
+    pub keep_raw_payloads: bool,
+    pub interval_minutes: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            api_calls_days: None,
+            keep_patches: None,
+            keep_raw_payloads: true,
+            interval_minutes: 60,
+        }
+    }
+}
+
+impl RetentionConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.api_calls_days.is_some() || self.keep_patches.is_some()
+    }
+}
+
+/// Embedded HTTP admin API; disabled unless a bind address is set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdminConfig {
+    /// Address to listen on, e.g. `127.0.0.1:8080`
+    pub bind_address: Option<String>,
+    /// Bearer token required by the control endpoints; unset leaves them open
+    pub token: Option<String>,
+}
+
+/// Upper bound of `events.webhook_retries`; with the default delay the last retry
+/// already waits over 8 minutes
+pub const MAX_WEBHOOK_RETRIES: u32 = 10;
+
+/// Where crawler events such as milestones and incidents are sent; they are always
+/// logged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventsConfig {
+    /// URL each event is POSTed to as JSON
+    pub webhook_url: Option<String>,
+    /// Retries of a failed webhook delivery, with the delay doubling each time
+    pub webhook_retries: u32,
+    pub webhook_retry_delay_ms: u64,
+    /// File each event is appended to as a line of JSON
+    pub file: Option<PathBuf>,
+    /// Tasks in a row in one region that must fail with 5xx or 429 responses before
+    /// it is reported
+    pub incident_threshold: u32,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            webhook_retries: 3,
+            webhook_retry_delay_ms: 1000,
+            file: None,
+            incident_threshold: 10,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             riot_api_key: String::new(),
+            api_keys: Vec::new(),
+            api_project: None,
+            api_base_url: None,
             database_url: "./data/lol_crawler.db".to_string(),
             regions: vec![
                 "na1".to_string(),
@@ -56,133 +417,353 @@ impl Default for Config {
                 batch_size: 100,
                 health_check_interval_seconds: 60,
                 state_save_interval_seconds: 300,
+                drain_timeout_seconds: default_drain_timeout_seconds(),
+                queue_id: default_queue_id(),
+                seed_from_ladder: default_seed_from_ladder(),
+                hook_timeout_seconds: default_hook_timeout_seconds(),
             },
-            logging: LoggingConfig {
-                level: "info".to_string(),
-                format: "json".to_string(),
-            },
+            logging: LoggingConfig::default(),
+            sampling: SamplingConfig::default(),
+            budget: BudgetConfig::default(),
+            archive: ArchiveConfig::default(),
+            retention: RetentionConfig::default(),
+            admin: AdminConfig::default(),
+            events: EventsConfig::default(),
+            region_overrides: HashMap::new(),
         }
     }
 }
 
 impl Config {
+    /// Load the layered configuration from the default file location
     pub fn from_env() -> crate::Result<Self> {
-        Self::from_env_with_dotenv(true)
+        Self::load(None)
+    }
+
+    /// Load and validate the layered configuration: defaults, then the config file
+    /// (`file`, or `DEFAULT_CONFIG_FILE` if present), then the legacy environment
+    /// variables, then `LOL_CRAWLER__*` variables. `.env` is read into the environment
+    /// first.
+    pub fn load(file: Option<&Path>) -> crate::Result<Self> {
+        let config = Self::load_unchecked(file)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The layered configuration without validation, for commands that only need part
+    /// of it, such as the database URL
+    pub fn load_unchecked(file: Option<&Path>) -> crate::Result<Self> {
+        dotenv::dotenv().ok();
+        Self::from_sources(file, &std::env::vars().collect())
     }
 
     #[cfg(test)]
     pub fn from_env_no_dotenv() -> crate::Result<Self> {
-        Self::from_env_with_dotenv(false)
+        let config = Self::from_sources(None, &std::env::vars().collect())?;
+        config.validate()?;
+        Ok(config)
     }
 
-    fn from_env_with_dotenv(load_dotenv: bool) -> crate::Result<Self> {
-        if load_dotenv {
-            dotenv::dotenv().ok();
-        }
+    fn from_sources(file: Option<&Path>, env: &HashMap<String, String>) -> crate::Result<Self> {
+        let mut builder =
+            ::config::Config::builder().add_source(::config::Config::try_from(&Config::default())?);
+        builder = match file {
+            Some(path) => builder.add_source(::config::File::from(path)),
+            None => {
+                builder.add_source(::config::File::with_name(DEFAULT_CONFIG_FILE).required(false))
+            }
+        };
+        builder = builder.add_source(LegacyEnv(env.clone())).add_source(
+            ::config::Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("__")
+                .separator("__")
+                .try_parsing(true)
+                .list_separator(",")
+                .with_list_parse_key("regions")
+                .source(Some(env.clone().into_iter().collect())),
+        );
 
-        let mut config = Config::default();
+        let mut config: Config = builder
+            .build()
+            .and_then(|layers| layers.try_deserialize())
+            .map_err(|e| anyhow::anyhow!("Invalid configuration: {}", e))?;
+        // Keys are case-insensitive in every layer; tiers are named in upper case
+        config.sampling.tier_targets = config
+            .sampling
+            .tier_targets
+            .into_iter()
+            .map(|(tier, target)| (tier.to_uppercase(), target))
+            .collect();
+        Ok(config)
+    }
 
-        if let Ok(api_key) = std::env::var("RIOT_API_KEY") {
-            config.riot_api_key = api_key;
-        }
+    /// Every API key: `riot_api_key` as `default` when set, then `api_keys`
+    pub fn api_key_configs(&self) -> Vec<ApiKeyConfig> {
+        let default_key = (!self.riot_api_key.is_empty()).then(|| ApiKeyConfig {
+            label: "default".to_string(),
+            key: Some(self.riot_api_key.clone()),
+            ..Default::default()
+        });
+        default_key
+            .into_iter()
+            .chain(self.api_keys.iter().cloned())
+            .collect()
+    }
 
-        if let Ok(db_url) = std::env::var("DATABASE_URL") {
-            config.database_url = db_url;
-        }
+    /// Project whose keys send requests
+    pub fn api_project(&self) -> Option<String> {
+        self.api_project.clone().or_else(|| {
+            self.api_key_configs()
+                .first()
+                .map(|key| key.project().to_string())
+        })
+    }
 
-        if let Ok(regions) = std::env::var("REGIONS") {
-            config.regions = regions.split(',').map(|s| s.trim().to_string()).collect();
-        }
+    /// Queue whose matches are stored in `region`
+    pub fn queue_id_for(&self, region: &str) -> i32 {
+        self.region_overrides
+            .get(region)
+            .and_then(|r| r.queue_id)
+            .unwrap_or(self.crawler.queue_id)
+    }
 
-        if let Ok(log_level) = std::env::var("LOG_LEVEL") {
-            config.logging.level = log_level;
-        }
+    /// Whether `region` is seeded from its Master+ ladder at startup
+    pub fn seeds_from_ladder(&self, region: &str) -> bool {
+        self.region_overrides
+            .get(region)
+            .and_then(|r| r.seed_from_ladder)
+            .unwrap_or(self.crawler.seed_from_ladder)
+    }
 
-        // Rate limiting configuration
-        if let Ok(app_limit_per_second) = std::env::var("APPLICATION_LIMIT_PER_SECOND") {
-            if let Ok(limit) = app_limit_per_second.parse::<u32>() {
-                config.rate_limits.application_limit_per_second = limit;
-            }
-        }
+    /// Match budget of `region` for one run, if it has its own
+    pub fn max_matches_for(&self, region: &str) -> Option<u64> {
+        self.region_overrides
+            .get(region)
+            .and_then(|r| r.max_matches)
+    }
 
-        if let Ok(app_limit_per_two_minutes) = std::env::var("APPLICATION_LIMIT_PER_TWO_MINUTES") {
-            if let Ok(limit) = app_limit_per_two_minutes.parse::<u32>() {
-                config.rate_limits.application_limit_per_two_minutes = limit;
-            }
-        }
+    /// Check the settings, e.g. again after command-line flags have overridden them.
+    /// Every problem is reported, one line per key.
+    pub fn validate(&self) -> crate::Result<()> {
+        let mut errors = ValidationErrors::default();
 
-        if let Ok(max_concurrent) = std::env::var("MAX_CONCURRENT_REQUESTS") {
-            if let Ok(limit) = max_concurrent.parse::<u32>() {
-                config.rate_limits.max_concurrent_requests = limit;
+        if self.riot_api_key.is_empty() {
+            if self.api_keys.is_empty() {
+                errors.add("riot_api_key", "is required");
             }
+        } else if !self.riot_api_key.starts_with("RGAPI-") {
+            errors.add("riot_api_key", "must start with 'RGAPI-'");
         }
 
-        // Crawler configuration
-        if let Ok(queue_limit) = std::env::var("QUEUE_SIZE_LIMIT") {
-            if let Ok(limit) = queue_limit.parse::<usize>() {
-                config.crawler.queue_size_limit = limit;
-            }
+        // Validate additional API keys
+        let mut labels: Vec<&str> = Vec::new();
+        if !self.riot_api_key.is_empty() {
+            labels.push("default");
         }
-
-        if let Ok(batch_size) = std::env::var("BATCH_SIZE") {
-            if let Ok(size) = batch_size.parse::<usize>() {
-                config.crawler.batch_size = size;
+        for (index, key) in self.api_keys.iter().enumerate() {
+            let prefix = format!("api_keys[{}]", index);
+            if key.label.is_empty() {
+                errors.add(&format!("{}.label", prefix), "is required");
+            } else if labels.contains(&key.label.as_str()) {
+                errors.add(
+                    &format!("{}.label", prefix),
+                    format!("'{}' is used by another key", key.label),
+                );
             }
-        }
-
-        if let Ok(health_interval) = std::env::var("HEALTH_CHECK_INTERVAL_SECONDS") {
-            if let Ok(seconds) = health_interval.parse::<u64>() {
-                config.crawler.health_check_interval_seconds = seconds;
+            labels.push(&key.label);
+            match (&key.key, &key.key_file) {
+                (Some(_), Some(_)) | (None, None) => {
+                    errors.add(&prefix, "needs exactly one of key and key_file")
+                }
+                (Some(value), None) if !value.starts_with("RGAPI-") => {
+                    errors.add(&format!("{}.key", prefix), "must start with 'RGAPI-'")
+                }
+                _ => {}
             }
-        }
-
-        if let Ok(save_interval) = std::env::var("STATE_SAVE_INTERVAL_SECONDS") {
-            if let Ok(seconds) = save_interval.parse::<u64>() {
-                config.crawler.state_save_interval_seconds = seconds;
+            if key.limit_per_second == Some(0) {
+                errors.add(
+                    &format!("{}.limit_per_second", prefix),
+                    "must be greater than 0",
+                );
             }
         }
-
-        // Validation
-        if config.riot_api_key.is_empty() {
-            anyhow::bail!("RIOT_API_KEY environment variable is required");
+        if let Some(project) = &self.api_project {
+            if !self
+                .api_key_configs()
+                .iter()
+                .any(|key| key.project() == project)
+            {
+                errors.add(
+                    "api_project",
+                    format!("no API key belongs to project '{}'", project),
+                );
+            }
         }
 
-        if !config.riot_api_key.starts_with("RGAPI-") {
-            anyhow::bail!("RIOT_API_KEY must start with 'RGAPI-'");
+        if let Some(url) = &self.api_base_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.add("api_base_url", "must be an http:// or https:// URL");
+            }
         }
 
         // Validate regions
         let valid_regions = [
             "na1", "euw1", "eun1", "kr", "br1", "jp1", "ru", "oc1", "tr1", "la1", "la2",
         ];
-        for region in &config.regions {
+        if self.regions.is_empty() {
+            errors.add("regions", "must name at least one region");
+        }
+        for region in &self.regions {
             if !valid_regions.contains(&region.as_str()) {
-                anyhow::bail!(
-                    "Invalid region '{}'. Valid regions: {}",
-                    region,
-                    valid_regions.join(", ")
+                errors.add(
+                    "regions",
+                    format!(
+                        "invalid region '{}', expected one of {}",
+                        region,
+                        valid_regions.join(", ")
+                    ),
+                );
+            }
+        }
+        for (region, overrides) in &self.region_overrides {
+            if !valid_regions.contains(&region.as_str()) {
+                errors.add(
+                    &format!("region.{}", region),
+                    format!(
+                        "invalid region, expected one of {}",
+                        valid_regions.join(", ")
+                    ),
+                );
+            }
+            if overrides.max_matches == Some(0) {
+                errors.add(
+                    &format!("region.{}.max_matches", region),
+                    "must be greater than 0",
                 );
             }
         }
 
         // Validate rate limits
-        if config.rate_limits.application_limit_per_second == 0 {
-            anyhow::bail!("APPLICATION_LIMIT_PER_SECOND must be greater than 0");
+        if self.rate_limits.application_limit_per_second == 0 {
+            errors.add(
+                "rate_limits.application_limit_per_second",
+                "must be greater than 0",
+            );
         }
 
-        if config.rate_limits.max_concurrent_requests == 0 {
-            anyhow::bail!("MAX_CONCURRENT_REQUESTS must be greater than 0");
+        if self.rate_limits.max_concurrent_requests == 0 {
+            errors.add(
+                "rate_limits.max_concurrent_requests",
+                "must be greater than 0",
+            );
         }
 
         // Validate crawler config
-        if config.crawler.queue_size_limit == 0 {
-            anyhow::bail!("QUEUE_SIZE_LIMIT must be greater than 0");
+        if self.crawler.queue_size_limit == 0 {
+            errors.add("crawler.queue_size_limit", "must be greater than 0");
         }
 
-        Ok(config)
+        if self.crawler.hook_timeout_seconds == 0 {
+            errors.add("crawler.hook_timeout_seconds", "must be greater than 0");
+        }
+
+        // Validate sampling config
+        for tier in self.sampling.tier_targets.keys() {
+            if tier.parse::<crate::models::Tier>().is_err() {
+                errors.add("sampling.tier_targets", format!("invalid tier '{}'", tier));
+            }
+        }
+
+        // Validate budget
+        if self.budget.idle_minutes == Some(0) {
+            errors.add("budget.idle_minutes", "must be greater than 0");
+        }
+
+        // Validate archive
+        if !(1..=22).contains(&self.archive.compression_level) {
+            errors.add("archive.compression_level", "must be between 1 and 22");
+        }
+
+        // Validate retention
+        if self.retention.keep_patches == Some(0) {
+            errors.add("retention.keep_patches", "must be greater than 0");
+        }
+
+        if self.retention.interval_minutes == 0 {
+            errors.add("retention.interval_minutes", "must be greater than 0");
+        }
+
+        // Validate logging
+        if !["json", "pretty", "compact"].contains(&self.logging.format.as_str()) {
+            errors.add("logging.format", "must be json, pretty or compact");
+        }
+
+        // Validate admin API
+        if let Some(address) = &self.admin.bind_address {
+            if address.parse::<std::net::SocketAddr>().is_err() {
+                errors.add(
+                    "admin.bind_address",
+                    "must be a socket address like 127.0.0.1:8080",
+                );
+            }
+        }
+
+        // Validate events
+        if let Some(url) = &self.events.webhook_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                errors.add("events.webhook_url", "must be an http:// or https:// URL");
+            }
+        }
+        if self.events.webhook_retries > MAX_WEBHOOK_RETRIES {
+            errors.add(
+                "events.webhook_retries",
+                format!("must be at most {}", MAX_WEBHOOK_RETRIES),
+            );
+        }
+        if self.events.incident_threshold == 0 {
+            errors.add("events.incident_threshold", "must be greater than 0");
+        }
+
+        errors.into_result()
+    }
+
+    /// Keys whose values differ between `self` and `other`, e.g. `rate_limits.max_retries`
+    pub fn changed_keys(&self, other: &Config) -> Vec<String> {
+        let mut changed = Vec::new();
+        diff_values(
+            "",
+            &serde_json::to_value(self).expect("config serializes"),
+            &serde_json::to_value(other).expect("config serializes"),
+            &mut changed,
+        );
+        changed
+    }
+
+    /// A copy safe to print: the API keys, admin token, webhook URL and database password
+    /// are masked
+    pub fn redacted(&self) -> Config {
+        let mut config = self.clone();
+        config.riot_api_key = "RGAPI-***".to_string();
+        for key in &mut config.api_keys {
+            if key.key.is_some() {
+                key.key = Some("RGAPI-***".to_string());
+            }
+        }
+        config.database_url = crate::database::redact_url(&config.database_url);
+        if config.admin.token.is_some() {
+            config.admin.token = Some("***".to_string());
+        }
+        // Webhook URLs usually carry their own token
+        if config.events.webhook_url.is_some() {
+            config.events.webhook_url = Some("***".to_string());
+        }
+        config
     }
 
     pub fn base_url_for_region(&self, region: &str) -> String {
+        if let Some(url) = &self.api_base_url {
+            return url.trim_end_matches('/').to_string();
+        }
         match region {
             "na1" => "https://na1.api.riotgames.com".to_string(),
             "euw1" => "https://euw1.api.riotgames.com".to_string(),
@@ -200,6 +781,9 @@ impl Config {
     }
 
     pub fn regional_base_url_for_region(&self, region: &str) -> String {
+        if let Some(url) = &self.api_base_url {
+            return url.trim_end_matches('/').to_string();
+        }
         match region {
             "na1" | "br1" | "la1" | "la2" => "https://americas.api.riotgames.com".to_string(),
             "euw1" | "eun1" | "tr1" | "ru" => "https://europe.api.riotgames.com".to_string(),
@@ -210,6 +794,121 @@ impl Config {
     }
 }
 
+/// Whether a running crawler can apply a change to `key`
+pub fn is_reloadable(key: &str) -> bool {
+    RELOADABLE_KEYS.iter().any(|reloadable| {
+        key == *reloadable
+            || key
+                .strip_prefix(reloadable)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+fn diff_values(
+    path: &str,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changed: &mut Vec<String>,
+) {
+    match (old, new) {
+        (serde_json::Value::Object(old), serde_json::Value::Object(new)) => {
+            let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let missing = serde_json::Value::Null;
+                diff_values(
+                    &child,
+                    old.get(key).unwrap_or(&missing),
+                    new.get(key).unwrap_or(&missing),
+                    changed,
+                );
+            }
+        }
+        (old, new) if old != new => changed.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// Problems found by `Config::validate`, keyed by setting
+#[derive(Default)]
+struct ValidationErrors(Vec<String>);
+
+impl ValidationErrors {
+    /// Record a problem with `key`, naming the legacy environment variable that sets it
+    fn add(&mut self, key: &str, message: impl std::fmt::Display) {
+        let legacy = LEGACY_ENV_VARS.iter().find(|(_, k)| *k == key);
+        self.0.push(match legacy {
+            Some((var, _)) => format!("{} ({}): {}", key, var, message),
+            None => format!("{}: {}", key, message),
+        });
+    }
+
+    fn into_result(self) -> crate::Result<()> {
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("Invalid configuration:\n  {}", self.0.join("\n  "))
+    }
+}
+
+/// The pre-prefix environment variables as a config layer. `REGIONS` and
+/// `SAMPLING_TIER_TARGETS` are comma-separated; empty admin and event
+/// settings are ignored.
+#[derive(Debug, Clone)]
+struct LegacyEnv(HashMap<String, String>);
+
+impl ::config::Source for LegacyEnv {
+    fn clone_into_box(&self) -> Box<dyn ::config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<::config::Map<String, ::config::Value>, ::config::ConfigError> {
+        let mut values = ::config::Map::new();
+        for (var, key) in LEGACY_ENV_VARS {
+            let Some(raw) = self.0.get(*var) else {
+                continue;
+            };
+            let origin = format!("environment variable {}", var);
+            let kind = match *var {
+                "REGIONS" => ::config::ValueKind::Array(
+                    raw.split(',')
+                        .map(|region| ::config::Value::new(Some(&origin), region.trim()))
+                        .collect(),
+                ),
+                "SAMPLING_TIER_TARGETS" => {
+                    // Format: "IRON=500,GOLD=2000"
+                    let mut targets = ::config::Map::new();
+                    for pair in raw.split(',').filter(|p| !p.trim().is_empty()) {
+                        let (tier, target) = pair.split_once('=').ok_or_else(|| {
+                            ::config::ConfigError::Message(format!(
+                                "Invalid {} entry '{}', expected TIER=matches",
+                                var, pair
+                            ))
+                        })?;
+                        targets.insert(
+                            tier.trim().to_uppercase(),
+                            ::config::Value::new(Some(&origin), target.trim()),
+                        );
+                    }
+                    ::config::ValueKind::Table(targets)
+                }
+                "ADMIN_BIND_ADDRESS" | "ADMIN_TOKEN" | "EVENT_WEBHOOK_URL" | "EVENT_FILE"
+                    if raw.is_empty() =>
+                {
+                    continue
+                }
+                _ => ::config::ValueKind::String(raw.clone()),
+            };
+            values.insert(key.to_string(), ::config::Value::new(Some(&origin), kind));
+        }
+        Ok(values)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,6 +921,7 @@ mod tests {
             "DATABASE_URL",
             "REGIONS",
             "LOG_LEVEL",
+            "LOG_FORMAT",
             "APPLICATION_LIMIT_PER_SECOND",
             "APPLICATION_LIMIT_PER_TWO_MINUTES",
             "MAX_CONCURRENT_REQUESTS",
@@ -229,6 +929,29 @@ mod tests {
             "BATCH_SIZE",
             "HEALTH_CHECK_INTERVAL_SECONDS",
             "STATE_SAVE_INTERVAL_SECONDS",
+            "DRAIN_TIMEOUT_SECONDS",
+            "CRAWL_QUEUE_ID",
+            "SEED_FROM_LADDER",
+            "HOOK_TIMEOUT_SECONDS",
+            "SAMPLING_ENABLED",
+            "SAMPLING_MATCHES_PER_STRATUM",
+            "SAMPLING_TIER_TARGETS",
+            "SAMPLING_SEED_PLAYERS_PER_TIER",
+            "BUDGET_MAX_REQUESTS",
+            "BUDGET_MAX_MATCHES",
+            "BUDGET_MAX_DURATION_SECONDS",
+            "BUDGET_IDLE_MINUTES",
+            "ARCHIVE_RAW_PAYLOADS",
+            "ARCHIVE_TIMELINES",
+            "ARCHIVE_COMPRESSION_LEVEL",
+            "RETENTION_API_CALLS_DAYS",
+            "RETENTION_KEEP_PATCHES",
+            "RETENTION_KEEP_RAW_PAYLOADS",
+            "RETENTION_INTERVAL_MINUTES",
+            "ADMIN_BIND_ADDRESS",
+            "ADMIN_TOKEN",
+            "EVENT_WEBHOOK_URL",
+            "EVENT_FILE",
         ];
 
         for var in &env_vars {
@@ -294,7 +1017,7 @@ mod tests {
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("RIOT_API_KEY environment variable is required"));
+            .contains("riot_api_key (RIOT_API_KEY): is required"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -309,7 +1032,7 @@ mod tests {
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("RIOT_API_KEY must start with 'RGAPI-'"));
+            .contains("riot_api_key (RIOT_API_KEY): must start with 'RGAPI-'"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -323,6 +1046,7 @@ mod tests {
         env::set_var("DATABASE_URL", "./test_data/custom.db");
         env::set_var("REGIONS", "na1,euw1,kr");
         env::set_var("LOG_LEVEL", "debug");
+        env::set_var("LOG_FORMAT", "compact");
         env::set_var("APPLICATION_LIMIT_PER_SECOND", "50");
         env::set_var("APPLICATION_LIMIT_PER_TWO_MINUTES", "500");
         env::set_var("MAX_CONCURRENT_REQUESTS", "25");
@@ -330,6 +1054,10 @@ mod tests {
         env::set_var("BATCH_SIZE", "200");
         env::set_var("HEALTH_CHECK_INTERVAL_SECONDS", "120");
         env::set_var("STATE_SAVE_INTERVAL_SECONDS", "600");
+        env::set_var("DRAIN_TIMEOUT_SECONDS", "45");
+        env::set_var("CRAWL_QUEUE_ID", "440");
+        env::set_var("SEED_FROM_LADDER", "false");
+        env::set_var("HOOK_TIMEOUT_SECONDS", "5");
 
         let config = Config::from_env_no_dotenv().unwrap();
 
@@ -338,6 +1066,7 @@ mod tests {
         assert_eq!(config.database_url, "./test_data/custom.db");
         assert_eq!(config.regions, vec!["na1", "euw1", "kr"]);
         assert_eq!(config.logging.level, "debug");
+        assert_eq!(config.logging.format, "compact");
         assert_eq!(config.rate_limits.application_limit_per_second, 50);
         assert_eq!(config.rate_limits.application_limit_per_two_minutes, 500);
         assert_eq!(config.rate_limits.max_concurrent_requests, 25);
@@ -345,6 +1074,10 @@ mod tests {
         assert_eq!(config.crawler.batch_size, 200);
         assert_eq!(config.crawler.health_check_interval_seconds, 120);
         assert_eq!(config.crawler.state_save_interval_seconds, 600);
+        assert_eq!(config.crawler.drain_timeout_seconds, 45);
+        assert_eq!(config.crawler.queue_id, 440);
+        assert!(!config.crawler.seed_from_ladder);
+        assert_eq!(config.crawler.hook_timeout_seconds, 5);
 
         setup_clean_env(); // Clean up after test
     }
@@ -383,7 +1116,7 @@ mod tests {
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("Invalid region 'invalid_region'"));
+            .contains("regions (REGIONS): invalid region 'invalid_region'"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -393,15 +1126,16 @@ mod tests {
         setup_clean_env();
         set_minimal_valid_env();
 
-        // Test invalid application limit - should use default
+        // Unparseable values are rejected, naming the key and where the value came from
         env::set_var("APPLICATION_LIMIT_PER_SECOND", "not_a_number");
-        let config = Config::from_env_no_dotenv().unwrap();
-        assert_eq!(config.rate_limits.application_limit_per_second, 20); // default
+        let error = Config::from_env_no_dotenv().unwrap_err().to_string();
+        assert!(error.contains("rate_limits.application_limit_per_second"));
+        assert!(error.contains("environment variable APPLICATION_LIMIT_PER_SECOND"));
 
-        // Test invalid queue size - should use default
+        env::remove_var("APPLICATION_LIMIT_PER_SECOND");
         env::set_var("QUEUE_SIZE_LIMIT", "invalid");
-        let config = Config::from_env_no_dotenv().unwrap();
-        assert_eq!(config.crawler.queue_size_limit, 100_000); // default
+        let error = Config::from_env_no_dotenv().unwrap_err().to_string();
+        assert!(error.contains("crawler.queue_size_limit"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -417,7 +1151,7 @@ mod tests {
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("APPLICATION_LIMIT_PER_SECOND must be greater than 0"));
+            .contains("rate_limits.application_limit_per_second (APPLICATION_LIMIT_PER_SECOND): must be greater than 0"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -430,26 +1164,41 @@ mod tests {
         env::set_var("MAX_CONCURRENT_REQUESTS", "0");
         let result = Config::from_env_no_dotenv();
         assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains(
+            "rate_limits.max_concurrent_requests (MAX_CONCURRENT_REQUESTS): must be greater than 0"
+        ));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_validation_zero_queue_size() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        env::set_var("QUEUE_SIZE_LIMIT", "0");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("MAX_CONCURRENT_REQUESTS must be greater than 0"));
+            .contains("crawler.queue_size_limit (QUEUE_SIZE_LIMIT): must be greater than 0"));
 
         setup_clean_env(); // Clean up after test
     }
 
     #[test]
-    fn test_validation_zero_queue_size() {
+    fn test_validation_unknown_log_format() {
         setup_clean_env();
         set_minimal_valid_env();
 
-        env::set_var("QUEUE_SIZE_LIMIT", "0");
+        env::set_var("LOG_FORMAT", "logfmt");
         let result = Config::from_env_no_dotenv();
         assert!(result.is_err());
         assert!(result
             .unwrap_err()
             .to_string()
-            .contains("QUEUE_SIZE_LIMIT must be greater than 0"));
+            .contains("logging.format (LOG_FORMAT): must be json, pretty or compact"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -582,7 +1331,7 @@ mod tests {
 
         // Empty regions string creates a single empty region which should fail validation
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("Invalid region"));
+        assert!(result.unwrap_err().to_string().contains("invalid region"));
 
         setup_clean_env(); // Clean up after test
     }
@@ -685,4 +1434,403 @@ mod tests {
 
         setup_clean_env(); // Clean up after test
     }
+
+    #[test]
+    fn test_sampling_config_from_env() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        // Disabled by default
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(!config.sampling.enabled);
+        assert_eq!(config.sampling.matches_per_stratum, 1000);
+
+        env::set_var("SAMPLING_ENABLED", "true");
+        env::set_var("SAMPLING_MATCHES_PER_STRATUM", "250");
+        env::set_var("SAMPLING_TIER_TARGETS", "iron=50, GOLD=2000");
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(config.sampling.enabled);
+        assert_eq!(config.sampling.matches_per_stratum, 250);
+        assert_eq!(config.sampling.tier_targets.get("IRON"), Some(&50));
+        assert_eq!(config.sampling.tier_targets.get("GOLD"), Some(&2000));
+
+        env::set_var("SAMPLING_TIER_TARGETS", "WOOD=10");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("invalid tier 'WOOD'"));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_budget_config_from_env() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        // Unlimited by default
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(config.budget.is_unlimited());
+
+        env::set_var("BUDGET_MAX_REQUESTS", "5000");
+        env::set_var("BUDGET_MAX_MATCHES", "200");
+        env::set_var("BUDGET_MAX_DURATION_SECONDS", "3600");
+        env::set_var("BUDGET_IDLE_MINUTES", "15");
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(!config.budget.is_unlimited());
+        assert_eq!(config.budget.max_requests, Some(5000));
+        assert_eq!(config.budget.max_matches, Some(200));
+        assert_eq!(config.budget.max_duration_seconds, Some(3600));
+        assert_eq!(config.budget.idle_minutes, Some(15));
+
+        env::set_var("BUDGET_IDLE_MINUTES", "0");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("BUDGET_IDLE_MINUTES"));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_archive_config_from_env() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(!config.archive.enabled);
+        assert!(!config.archive.timelines);
+        assert_eq!(config.archive.compression_level, 3);
+
+        env::set_var("ARCHIVE_RAW_PAYLOADS", "true");
+        env::set_var("ARCHIVE_TIMELINES", "true");
+        env::set_var("ARCHIVE_COMPRESSION_LEVEL", "19");
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(config.archive.enabled);
+        assert!(config.archive.timelines);
+        assert_eq!(config.archive.compression_level, 19);
+
+        env::set_var("ARCHIVE_COMPRESSION_LEVEL", "30");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("ARCHIVE_COMPRESSION_LEVEL"));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_retention_config_from_env() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(!config.retention.is_enabled());
+        assert!(config.retention.keep_raw_payloads);
+        assert_eq!(config.retention.interval_minutes, 60);
+
+        env::set_var("RETENTION_API_CALLS_DAYS", "14");
+        env::set_var("RETENTION_KEEP_PATCHES", "3");
+        env::set_var("RETENTION_KEEP_RAW_PAYLOADS", "false");
+        env::set_var("RETENTION_INTERVAL_MINUTES", "15");
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(config.retention.is_enabled());
+        assert_eq!(config.retention.api_calls_days, Some(14));
+        assert_eq!(config.retention.keep_patches, Some(3));
+        assert!(!config.retention.keep_raw_payloads);
+        assert_eq!(config.retention.interval_minutes, 15);
+
+        env::set_var("RETENTION_KEEP_PATCHES", "0");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("RETENTION_KEEP_PATCHES"));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_admin_config_from_env() {
+        setup_clean_env();
+        set_minimal_valid_env();
+
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert!(config.admin.bind_address.is_none());
+        assert!(config.admin.token.is_none());
+
+        env::set_var("ADMIN_BIND_ADDRESS", "127.0.0.1:8080");
+        env::set_var("ADMIN_TOKEN", "secret");
+        let config = Config::from_env_no_dotenv().unwrap();
+        assert_eq!(config.admin.bind_address.as_deref(), Some("127.0.0.1:8080"));
+        assert_eq!(config.admin.token.as_deref(), Some("secret"));
+
+        env::set_var("ADMIN_BIND_ADDRESS", "localhost");
+        let result = Config::from_env_no_dotenv();
+        assert!(result.is_err());
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("ADMIN_BIND_ADDRESS"));
+
+        setup_clean_env(); // Clean up after test
+    }
+
+    #[test]
+    fn test_events_config_from_env() {
+        let env = env_map(&[
+            ("RIOT_API_KEY", "RGAPI-test-key-123"),
+            ("EVENT_WEBHOOK_URL", ""),
+            ("EVENT_FILE", "./data/events.jsonl"),
+        ]);
+        let config = Config::from_sources(None, &env).unwrap();
+        assert!(config.events.webhook_url.is_none());
+        assert_eq!(
+            config.events.file.as_deref(),
+            Some(Path::new("./data/events.jsonl"))
+        );
+        assert_eq!(config.events.webhook_retries, 3);
+
+        let env = env_map(&[
+            ("RIOT_API_KEY", "RGAPI-test-key-123"),
+            ("EVENT_WEBHOOK_URL", "hooks.example.com/crawler"),
+        ]);
+        let error = Config::from_sources(None, &env)
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert!(error.to_string().contains(
+            "events.webhook_url (EVENT_WEBHOOK_URL): must be an http:// or https:// URL"
+        ));
+
+        let mut config =
+            Config::from_sources(None, &env_map(&[("RIOT_API_KEY", "RGAPI-x")])).unwrap();
+        config.events.webhook_retries = 32;
+        assert!(config
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("events.webhook_retries: must be at most 10"));
+    }
+
+    fn write_config_file(extension: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "lol-crawler-config-{}.{}",
+            uuid::Uuid::new_v4(),
+            extension
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn env_map(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_layered_file_env_and_prefixed_env() {
+        let path = write_config_file(
+            "toml",
+            r#"
+riot_api_key = "RGAPI-from-file"
+regions = ["euw1", "kr"]
+
+[rate_limits]
+retry_delay_ms = 250
+max_retries = 5
+
+[crawler]
+batch_size = 50
+
+[region.kr]
+queue_id = 440
+max_matches = 200
+"#,
+        );
+        let env = env_map(&[
+            ("BATCH_SIZE", "75"),
+            ("LOL_CRAWLER__CRAWLER__BATCH_SIZE", "80"),
+            ("LOL_CRAWLER__RATE_LIMITS__MAX_RETRIES", "7"),
+            ("LOL_CRAWLER__REGION__EUW1__SEED_FROM_LADDER", "false"),
+        ]);
+
+        let config = Config::from_sources(Some(&path), &env).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.riot_api_key, "RGAPI-from-file");
+        assert_eq!(config.regions, vec!["euw1", "kr"]);
+        // File beats defaults, legacy variables beat the file, prefixed ones beat both
+        assert_eq!(config.rate_limits.retry_delay_ms, 250);
+        assert_eq!(config.rate_limits.max_retries, 7);
+        assert_eq!(config.crawler.batch_size, 80);
+        assert_eq!(config.rate_limits.application_limit_per_second, 20);
+
+        assert_eq!(config.queue_id_for("kr"), 440);
+        assert_eq!(config.queue_id_for("euw1"), 420);
+        assert_eq!(config.max_matches_for("kr"), Some(200));
+        assert_eq!(config.max_matches_for("euw1"), None);
+        assert!(config.seeds_from_ladder("kr"));
+        assert!(!config.seeds_from_ladder("euw1"));
+    }
+
+    #[test]
+    fn test_yaml_config_file() {
+        let path = write_config_file(
+            "yaml",
+            "riot_api_key: RGAPI-yaml\nsampling:\n  enabled: true\n  tier_targets:\n    GOLD: 500\n",
+        );
+        let config = Config::from_sources(Some(&path), &HashMap::new()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(config.sampling.enabled);
+        assert_eq!(config.sampling.tier_targets.get("GOLD"), Some(&500));
+    }
+
+    #[test]
+    fn test_config_file_errors_name_the_key() {
+        let path = write_config_file("toml", "[rate_limits]\nretry_dely_ms = 10\n");
+        let error = Config::from_sources(Some(&path), &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("unknown field `retry_dely_ms`"), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+
+        let path = write_config_file("toml", "[crawler]\nbatch_size = \"lots\"\n");
+        let error = Config::from_sources(Some(&path), &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("crawler.batch_size"), "{}", error);
+        std::fs::remove_file(&path).unwrap();
+
+        let missing = std::env::temp_dir().join("lol-crawler-config-missing.toml");
+        assert!(Config::from_sources(Some(&missing), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_validation_reports_every_key() {
+        let mut config = Config::default();
+        config.region_overrides.insert(
+            "xx1".to_string(),
+            RegionConfig {
+                max_matches: Some(0),
+                ..Default::default()
+            },
+        );
+        config.crawler.queue_size_limit = 0;
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("riot_api_key (RIOT_API_KEY): is required"));
+        assert!(error.contains("region.xx1: invalid region"));
+        assert!(error.contains("region.xx1.max_matches: must be greater than 0"));
+        assert!(
+            error.contains("crawler.queue_size_limit (QUEUE_SIZE_LIMIT): must be greater than 0")
+        );
+        assert_eq!(error.lines().count(), 5);
+    }
+
+    #[test]
+    fn test_api_keys_from_file() {
+        let path = write_config_file(
+            "toml",
+            r#"
+riot_api_key = "RGAPI-development"
+api_project = "production"
+
+[[api_keys]]
+label = "production"
+key_file = "/run/secrets/riot-production"
+limit_per_second = 500
+
+[[api_keys]]
+label = "production-spare"
+key = "RGAPI-spare"
+project = "production"
+
+[[api_keys]]
+label = "default"
+key = "not-a-key"
+"#,
+        );
+        let config = Config::from_sources(Some(&path), &HashMap::new()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let keys = config.api_key_configs();
+        let labels: Vec<&str> = keys.iter().map(|k| k.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            ["default", "production", "production-spare", "default"]
+        );
+        assert_eq!(keys[1].project(), "production");
+        assert_eq!(keys[2].project(), "production");
+        assert_eq!(keys[0].project(), "default");
+        assert_eq!(
+            keys[1]
+                .rate_limits(&config.rate_limits)
+                .application_limit_per_second,
+            500
+        );
+        assert_eq!(config.api_project().as_deref(), Some("production"));
+
+        let error = config.validate().unwrap_err().to_string();
+        assert!(error.contains("api_keys[2].label: 'default' is used by another key"));
+        assert!(error.contains("api_keys[2].key: must start with 'RGAPI-'"));
+        assert_eq!(error.lines().count(), 3);
+
+        let redacted = serde_json::to_string(&config.redacted()).unwrap();
+        assert!(!redacted.contains("RGAPI-spare"));
+    }
+
+    #[test]
+    fn test_changed_keys_and_reloadability() {
+        let old = Config::default();
+        let mut new = old.clone();
+        assert!(old.changed_keys(&new).is_empty());
+
+        new.rate_limits.max_retries = 9;
+        new.database_url = "postgres://db/lol".to_string();
+        new.region_overrides
+            .insert("kr".to_string(), RegionConfig::default());
+        assert_eq!(
+            old.changed_keys(&new),
+            vec!["database_url", "rate_limits.max_retries", "region.kr"]
+        );
+
+        assert!(is_reloadable("rate_limits.max_retries"));
+        assert!(is_reloadable("region.kr"));
+        assert!(is_reloadable("logging.level"));
+        assert!(is_reloadable("retention.keep_patches"));
+        assert!(!is_reloadable("logging.format"));
+        assert!(!is_reloadable("rate_limits.max_concurrent_requests"));
+        assert!(!is_reloadable("database_url"));
+        assert!(!is_reloadable("regions_extra"));
+    }
+
+    #[test]
+    fn test_redacted_masks_secrets() {
+        let config = Config {
+            riot_api_key: "RGAPI-real-key".to_string(),
+            database_url: "postgres://crawler:hunter2@db/lol".to_string(),
+            admin: AdminConfig {
+                bind_address: None,
+                token: Some("secret".to_string()),
+            },
+            ..Default::default()
+        };
+
+        let redacted = serde_json::to_string(&config.redacted()).unwrap();
+        assert!(!redacted.contains("real-key"));
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("secret"));
+        assert!(redacted.contains("crawler"));
+    }
 }
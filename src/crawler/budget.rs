@@ -0,0 +1,193 @@
+use crate::config::BudgetConfig;
+use crate::models::database::DbCrawlerState;
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::time::Duration;
+
+/// Why a crawl run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum StopReason {
+    /// Stopped from outside, e.g. Ctrl-C or SIGTERM
+    Shutdown,
+    RequestBudget,
+    MatchBudget,
+    DurationBudget,
+    /// No new match was stored within the idle window
+    Idle,
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopReason::Shutdown => "shutdown requested",
+            StopReason::RequestBudget => "request budget reached",
+            StopReason::MatchBudget => "match budget reached",
+            StopReason::DurationBudget => "duration budget reached",
+            StopReason::Idle => "no new matches within the idle window",
+        })
+    }
+}
+
+/// Counters for the current run
+#[derive(Debug, Clone, Copy, Default, serde::Serialize)]
+pub struct RunProgress {
+    pub requests: u64,
+    pub summoners_processed: u64,
+    pub matches_stored: u64,
+    pub elapsed: Duration,
+    /// Time since the last new match was stored (or since the run started)
+    pub idle: Duration,
+}
+
+/// The first budget limit the run has reached, if any
+pub fn exceeded_budget(budget: &BudgetConfig, progress: &RunProgress) -> Option<StopReason> {
+    if budget
+        .max_requests
+        .is_some_and(|limit| progress.requests >= limit)
+    {
+        return Some(StopReason::RequestBudget);
+    }
+    if budget
+        .max_matches
+        .is_some_and(|limit| progress.matches_stored >= limit)
+    {
+        return Some(StopReason::MatchBudget);
+    }
+    if budget
+        .max_duration_seconds
+        .is_some_and(|limit| progress.elapsed >= Duration::from_secs(limit))
+    {
+        return Some(StopReason::DurationBudget);
+    }
+    if budget
+        .idle_minutes
+        .is_some_and(|limit| progress.idle >= Duration::from_secs(limit * 60))
+    {
+        return Some(StopReason::Idle);
+    }
+    None
+}
+
+/// Report printed when a run ends
+#[derive(Debug, Clone)]
+pub struct RunSummary {
+    pub stop_reason: StopReason,
+    pub started_at: Option<DateTime<Utc>>,
+    pub progress: RunProgress,
+    pub queue_size: usize,
+    /// Database totals as of the last state save
+    pub state: Option<DbCrawlerState>,
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Crawl run summary")?;
+        writeln!(f, "  Stopped:              {}", self.stop_reason)?;
+        if let Some(started_at) = self.started_at {
+            writeln!(f, "  Started:              {}", started_at.to_rfc3339())?;
+        }
+        writeln!(
+            f,
+            "  Duration:             {}s",
+            self.progress.elapsed.as_secs()
+        )?;
+        writeln!(f, "  API requests:         {}", self.progress.requests)?;
+        writeln!(
+            f,
+            "  Summoners processed:  {}",
+            self.progress.summoners_processed
+        )?;
+        writeln!(
+            f,
+            "  Matches stored:       {}",
+            self.progress.matches_stored
+        )?;
+        writeln!(f, "  Tasks left in queue:  {}", self.queue_size)?;
+        if let Some(state) = &self.state {
+            writeln!(
+                f,
+                "  Database totals:      {} matches, {} summoners",
+                state.total_matches_processed, state.total_summoners_processed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited_budget_never_stops() {
+        let progress = RunProgress {
+            requests: u64::MAX,
+            matches_stored: u64::MAX,
+            elapsed: Duration::from_secs(86_400 * 365),
+            ..Default::default()
+        };
+        assert_eq!(exceeded_budget(&BudgetConfig::default(), &progress), None);
+    }
+
+    #[test]
+    fn test_budget_limits() {
+        let budget = BudgetConfig {
+            max_requests: Some(100),
+            max_matches: Some(10),
+            max_duration_seconds: Some(60),
+            idle_minutes: Some(5),
+        };
+
+        let mut progress = RunProgress {
+            requests: 99,
+            matches_stored: 9,
+            elapsed: Duration::from_secs(59),
+            idle: Duration::from_secs(299),
+            ..Default::default()
+        };
+        assert_eq!(exceeded_budget(&budget, &progress), None);
+
+        progress.idle = Duration::from_secs(300);
+        assert_eq!(exceeded_budget(&budget, &progress), Some(StopReason::Idle));
+
+        progress.elapsed = Duration::from_secs(60);
+        assert_eq!(
+            exceeded_budget(&budget, &progress),
+            Some(StopReason::DurationBudget)
+        );
+
+        progress.matches_stored = 10;
+        assert_eq!(
+            exceeded_budget(&budget, &progress),
+            Some(StopReason::MatchBudget)
+        );
+
+        progress.requests = 100;
+        assert_eq!(
+            exceeded_budget(&budget, &progress),
+            Some(StopReason::RequestBudget)
+        );
+    }
+
+    #[test]
+    fn test_summary_report() {
+        let summary = RunSummary {
+            stop_reason: StopReason::MatchBudget,
+            started_at: None,
+            progress: RunProgress {
+                requests: 420,
+                summoners_processed: 12,
+                matches_stored: 200,
+                ..Default::default()
+            },
+            queue_size: 37,
+            state: None,
+        };
+
+        let report = summary.to_string();
+        assert!(report.contains("match budget reached"));
+        assert!(report.contains("API requests:         420"));
+        assert!(report.contains("Matches stored:       200"));
+        assert!(report.contains("Tasks left in queue:  37"));
+    }
+}
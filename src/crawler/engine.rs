@@ -1,40 +1,204 @@
+use super::budget::{exceeded_budget, RunProgress, RunSummary, StopReason};
+use super::failures::{retry_delay, TaskFailure};
+use super::hooks::MatchHook;
+use super::retention::apply_retention;
+use super::sampling::{StratumProgress, StratumTracker};
 use super::{queue::SummonerQueue, worker::CrawlerWorker};
-use crate::api::RiotApiClient;
-use crate::config::Config;
-use crate::database::Database;
-use crate::models::database::{DbCrawlerState, SummonerPriority, SummonerTask};
-use crate::rate_limiter::RateLimiter;
-use chrono::Utc;
-use std::sync::Arc;
-use std::time::Duration;
+use crate::api::{queues, ApiKeyPool, ApiKeyStatus, RiotApiClient};
+use crate::config::{is_reloadable, Config};
+use crate::database::Storage;
+use crate::events::{Event, EventBus, EventKind, EventSink};
+use crate::logging;
+use crate::models::database::{
+    DbCrawlerState, FailedTaskFilter, FailedTaskType, RetryClass, SummonerPriority, SummonerTask,
+    Tier,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
 use tokio::time::{interval, sleep};
+use tracing::Instrument;
+
+/// Loads the configuration again when the crawler is asked to reload it
+pub type ConfigSource = Box<dyn Fn() -> crate::Result<Config> + Send + Sync>;
+
+/// How often a summary event is emitted while the crawler runs
+const DAILY_SUMMARY_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
 pub struct CrawlerEngine {
     api_client: RiotApiClient,
-    database: Database,
+    database: Arc<dyn Storage>,
     summoner_queue: SummonerQueue,
     worker: CrawlerWorker,
-    config: Config,
+    stratum_tracker: Option<Arc<StratumTracker>>,
+    api_keys: Arc<ApiKeyPool>,
+    /// Replaced as a whole when the configuration is reloaded
+    config: std::sync::RwLock<Arc<Config>>,
+    /// Produces the configuration again for `reload`
+    config_source: Option<ConfigSource>,
+    events: Arc<EventBus>,
+    /// When the crawler paused itself for lack of a working API key
+    auth_paused_since: Mutex<Option<DateTime<Utc>>>,
+    /// Keys already reported as retired
+    retired_keys: Mutex<HashSet<String>>,
+    /// Tasks in a row that failed with each kind of incident, per region
+    incident_streaks: Mutex<HashMap<(EventKind, String), u32>>,
     running: Arc<tokio::sync::RwLock<bool>>,
+    paused: Arc<tokio::sync::RwLock<bool>>,
+    /// Task the crawler loop is working on; `None` while idle
+    in_flight: tokio::sync::Mutex<Option<SummonerTask>>,
+    /// Tasks set aside until their region is crawled again; persisted by a drain
+    parked: Mutex<Vec<SummonerTask>>,
+    shutdown: watch::Sender<ShutdownState>,
+    started_at: tokio::sync::RwLock<Option<DateTime<Utc>>>,
+    stop_reason: tokio::sync::RwLock<Option<StopReason>>,
+    summoners_processed: AtomicU64,
+    /// Matches stored as of the last time the count went up, and when that was
+    last_new_match: Mutex<(u64, Instant)>,
+}
+
+/// Wakes the background loops when the crawler stops, and tells the crawler loop to
+/// abandon its in-flight task when a drain times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownState {
+    Running,
+    Stopping,
+    Aborting,
 }
 
 impl CrawlerEngine {
-    pub fn new(config: Config, database: Database) -> crate::Result<Self> {
-        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limits.clone()));
-        let api_client = RiotApiClient::new(config.clone(), rate_limiter, database.clone())?;
-        let worker = CrawlerWorker::new(api_client.clone(), database.clone());
+    pub fn new(config: Config, database: Arc<dyn Storage>) -> crate::Result<Self> {
+        let api_keys = Arc::new(ApiKeyPool::new(&config)?);
+        let api_client = RiotApiClient::new(config.clone(), api_keys.clone(), database.clone())?;
+        let mut worker = CrawlerWorker::new(api_client.clone(), database.clone())
+            .with_hook_timeout(Duration::from_secs(config.crawler.hook_timeout_seconds));
+        worker.set_queue_ids(&config);
+        if config.archive.enabled {
+            worker = worker.with_archive(config.archive.clone());
+        }
         let summoner_queue = SummonerQueue::new();
 
+        let stratum_tracker = if config.sampling.enabled {
+            let tracker = Arc::new(StratumTracker::new(config.sampling.clone()));
+            worker = worker.with_stratum_tracker(tracker.clone());
+            Some(tracker)
+        } else {
+            None
+        };
+
+        let events = Arc::new(EventBus::new(&config.events)?);
+        worker = worker.with_events(events.clone());
+
         Ok(Self {
             api_client,
             database,
             summoner_queue,
             worker,
-            config,
+            stratum_tracker,
+            api_keys,
+            config: std::sync::RwLock::new(Arc::new(config)),
+            config_source: None,
+            events,
+            auth_paused_since: Mutex::new(None),
+            retired_keys: Mutex::new(HashSet::new()),
+            incident_streaks: Mutex::new(HashMap::new()),
             running: Arc::new(tokio::sync::RwLock::new(false)),
+            paused: Arc::new(tokio::sync::RwLock::new(false)),
+            in_flight: tokio::sync::Mutex::new(None),
+            parked: Mutex::new(Vec::new()),
+            shutdown: watch::channel(ShutdownState::Running).0,
+            started_at: tokio::sync::RwLock::new(None),
+            stop_reason: tokio::sync::RwLock::new(None),
+            summoners_processed: AtomicU64::new(0),
+            last_new_match: Mutex::new((0, Instant::now())),
         })
     }
 
+    /// Reload the configuration from `source` when asked to, e.g. on SIGHUP
+    pub fn with_config_source(mut self, source: ConfigSource) -> Self {
+        self.config_source = Some(source);
+        self
+    }
+
+    /// Run `hook` on every crawled match
+    pub fn with_match_hook(mut self, hook: Arc<dyn MatchHook>) -> Self {
+        self.worker = self.worker.with_match_hook(hook);
+        self
+    }
+
+    /// Also deliver crawler events to `sink`
+    pub fn with_event_sink(self, sink: Arc<dyn EventSink>) -> Self {
+        self.events.add_sink(sink);
+        self
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// The configuration currently in effect
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Load the configuration again from the config source and apply it, returning
+    /// the keys that changed
+    pub async fn reload(&self) -> crate::Result<Vec<String>> {
+        let Some(source) = &self.config_source else {
+            anyhow::bail!("No configuration source to reload from");
+        };
+        self.apply_config(source()?).await
+    }
+
+    /// Apply a new configuration to the running crawler, returning the keys that
+    /// changed. Regions, queues, rate limits, budgets and the log level take effect
+    /// live; if anything else changed, nothing is applied.
+    pub async fn apply_config(&self, config: Config) -> crate::Result<Vec<String>> {
+        config.validate()?;
+        let current = self.config();
+        let changed = current.changed_keys(&config);
+        if changed.is_empty() {
+            log::info!("Configuration unchanged, nothing to reload");
+            return Ok(changed);
+        }
+        let fixed: Vec<&str> = changed
+            .iter()
+            .map(String::as_str)
+            .filter(|key| !is_reloadable(key))
+            .collect();
+        if !fixed.is_empty() {
+            anyhow::bail!(
+                "Cannot change {} without a restart; nothing was reloaded",
+                fixed.join(", ")
+            );
+        }
+
+        if config.logging.level != current.logging.level {
+            logging::set_level(&config.logging.level)?;
+        }
+        self.api_keys.update_config(&config).await;
+        self.worker.set_queue_ids(&config);
+
+        let added_regions: Vec<String> = config
+            .regions
+            .iter()
+            .filter(|region| !current.regions.contains(region) && config.seeds_from_ladder(region))
+            .cloned()
+            .collect();
+        *self.config.write().unwrap() = Arc::new(config);
+        log::info!("Reloaded configuration: {}", changed.join(", "));
+        self.unpark_tasks().await;
+
+        if !added_regions.is_empty() && self.is_running().await {
+            self.seed_from_ladders(&added_regions).await;
+        }
+        Ok(changed)
+    }
+
     pub async fn start(&self) -> crate::Result<()> {
         {
             let mut running = self.running.write().await;
@@ -44,20 +208,46 @@ impl CrawlerEngine {
             }
             *running = true;
         }
+        self.shutdown.send_replace(ShutdownState::Running);
+        *self.started_at.write().await = Some(Utc::now());
+        *self.stop_reason.write().await = None;
+        *self.last_new_match.lock().unwrap() = (self.worker.matches_stored(), Instant::now());
 
         log::info!("Starting League of Legends crawler");
 
-        // First, seed with existing summoners from database
+        // Resume the queue persisted by the last graceful drain
+        self.restore_pending_tasks().await?;
+
+        if let Some(tracker) = &self.stratum_tracker {
+            tracker.load(self.database.as_ref()).await?;
+        }
+
+        // Tasks requeued from the dead-letter queue go first
+        self.replay_requeued_tasks().await?;
+
+        // Then seed with existing summoners from database
         self.seed_with_existing_summoners().await?;
 
         // If queue is empty or small, supplement with Master+ league players
         let queue_size = self.summoner_queue.total_size().await;
-        if queue_size < 100 {
+        let config = self.config();
+        let ladder_regions: Vec<String> = config
+            .regions
+            .iter()
+            .filter(|region| config.seeds_from_ladder(region))
+            .cloned()
+            .collect();
+        if ladder_regions.is_empty() {
+            log::info!(
+                "Ladder seeding disabled, starting with {} queued summoners",
+                queue_size
+            );
+        } else if queue_size < 100 {
             log::info!(
                 "Queue size ({}) below threshold, seeding with Master+ league players",
                 queue_size
             );
-            self.seed_with_master_league().await?;
+            self.seed_from_ladders(&ladder_regions).await;
         } else {
             log::info!(
                 "Sufficient existing summoners in queue ({}), skipping Master+ league seed",
@@ -65,32 +255,179 @@ impl CrawlerEngine {
             );
         }
 
+        // Counter the Master+ seeding bias by seeding from under-represented tiers
+        if self.stratum_tracker.is_some() {
+            self.seed_with_tier_ladders().await?;
+        }
+
+        self.events.emit(Event::new(
+            EventKind::CrawlerStarted,
+            format!(
+                "Crawler started in {} with {} queued tasks",
+                self.config().regions.join(", "),
+                self.summoner_queue.total_size().await
+            ),
+        ));
+
         // Spawn background tasks
         let crawler_task = self.spawn_crawler_task();
         let health_check_task = self.spawn_health_check_task();
         let state_save_task = self.spawn_state_save_task();
+        let budget_task = self.spawn_budget_task();
+        let retention_task = self.spawn_retention_task();
+        let api_key_task = self.spawn_api_key_task();
 
         // Wait for all tasks
-        tokio::try_join!(crawler_task, health_check_task, state_save_task)?;
+        let result = tokio::try_join!(
+            crawler_task,
+            health_check_task,
+            state_save_task,
+            budget_task,
+            retention_task,
+            api_key_task
+        );
 
-        Ok(())
+        let summary = self.run_summary().await;
+        let message = match &result {
+            Ok(_) => format!(
+                "Crawler stopped: {}, {} matches stored in {}s",
+                summary.stop_reason,
+                summary.progress.matches_stored,
+                summary.progress.elapsed.as_secs()
+            ),
+            Err(e) => format!("Crawler stopped on an error: {}", e),
+        };
+        self.events
+            .emit(Event::new(EventKind::CrawlerStopped, message));
+        self.events.flush().await;
+
+        result.map(|_| ())
     }
 
     pub async fn stop(&self) {
         log::info!("Stopping crawler");
         let mut running = self.running.write().await;
         *running = false;
+        self.shutdown.send_if_modified(|state| {
+            if *state == ShutdownState::Running {
+                *state = ShutdownState::Stopping;
+                true
+            } else {
+                false
+            }
+        });
     }
 
     async fn is_running(&self) -> bool {
         *self.running.read().await
     }
 
+    /// Stop pulling new work. The in-flight task finishes, and health checks and state
+    /// saves keep running.
+    pub async fn pause(&self) {
+        log::info!("Pausing crawler");
+        *self.paused.write().await = true;
+    }
+
+    pub async fn resume(&self) {
+        log::info!("Resuming crawler");
+        *self.paused.write().await = false;
+    }
+
+    /// Whether the crawler pulls no new work, because it was paused or drained, or
+    /// because no API key works
+    pub async fn is_paused(&self) -> bool {
+        *self.paused.read().await || self.auth_paused_since().is_some()
+    }
+
+    /// Gracefully stop the crawler: stop pulling new work, give the in-flight task up to
+    /// `timeout` to finish, then save crawler state and persist the queue so the next
+    /// start picks up where this one left off. A task still running at the timeout is
+    /// abandoned and persisted with the queue.
+    pub async fn drain(&self, timeout: Duration) -> crate::Result<()> {
+        log::info!("Draining crawler (timeout {}s)", timeout.as_secs());
+        *self.paused.write().await = true;
+
+        let finished = tokio::time::timeout(timeout, async {
+            while self.in_flight.lock().await.is_some() {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .is_ok();
+
+        let mut tasks = Vec::new();
+        if !finished {
+            log::warn!(
+                "In-flight work did not finish within {}s, abandoning it",
+                timeout.as_secs()
+            );
+            tasks.extend(self.in_flight.lock().await.clone());
+            self.shutdown.send_replace(ShutdownState::Aborting);
+        }
+        tasks.extend(self.summoner_queue.take_all().await);
+        tasks.append(&mut self.parked.lock().unwrap());
+
+        if let Err(e) = self.save_state().await {
+            log::error!("Failed to save crawler state: {}", e);
+        }
+        let persisted = self.database.save_pending_tasks(&tasks).await;
+        if persisted.is_ok() {
+            log::info!("Persisted {} queued tasks", tasks.len());
+        }
+
+        self.stop().await;
+        *self.paused.write().await = false;
+        persisted
+    }
+
+    /// Add the queued tasks to the persisted queue, which the next start restores,
+    /// returning how many were added. Used to seed a crawler that is not running.
+    pub async fn persist_queue(&self) -> crate::Result<usize> {
+        let tasks = self.summoner_queue.take_all().await;
+        let added = tasks.len();
+        if let Err(e) = self.database.append_pending_tasks(&tasks).await {
+            // Keep the tasks in memory rather than losing them
+            self.summoner_queue.push_batch(tasks).await;
+            return Err(e);
+        }
+        Ok(added)
+    }
+
+    /// Queue the parked tasks whose region is crawled again and has match budget left
+    async fn unpark_tasks(&self) {
+        let config = self.config();
+        let ready: Vec<SummonerTask> = {
+            let mut parked = self.parked.lock().unwrap();
+            let (ready, still_parked) = parked.drain(..).partition(|task| {
+                config.regions.contains(&task.region) && !self.region_budget_reached(&task.region)
+            });
+            *parked = still_parked;
+            ready
+        };
+        if !ready.is_empty() {
+            log::info!("Queueing {} parked tasks again", ready.len());
+            self.summoner_queue.push_batch(ready).await;
+        }
+    }
+
+    async fn restore_pending_tasks(&self) -> crate::Result<()> {
+        let tasks = self.database.take_pending_tasks().await?;
+        if !tasks.is_empty() {
+            log::info!(
+                "Restoring {} tasks persisted by the last drain",
+                tasks.len()
+            );
+            self.summoner_queue.push_batch(tasks).await;
+        }
+        Ok(())
+    }
+
     async fn seed_with_existing_summoners(&self) -> crate::Result<()> {
         log::info!("Seeding crawler with existing summoners from database");
 
         // Get existing summoners from database, prioritizing least recently updated
-        let summoners = self.database.get_existing_summoners_for_update(1000)?;
+        let summoners = self.database.get_existing_summoners_with_tier(1000).await?;
 
         if summoners.is_empty() {
             log::info!("No existing summoners found in database");
@@ -101,17 +438,23 @@ impl CrawlerEngine {
 
         // Create summoner tasks for existing users with medium priority
         // (lower than featured games but higher than newly discovered players)
-        let summoner_tasks: Vec<SummonerTask> = summoners
-            .into_iter()
-            .map(|(puuid, region)| SummonerTask {
-                puuid: puuid.clone(),
+        let mut summoner_tasks = Vec::with_capacity(summoners.len());
+        for (puuid, region, tier) in summoners {
+            let priority = self
+                .stratum_priority(&region, tier, SummonerPriority::Medium)
+                .await;
+            summoner_tasks.push(SummonerTask {
+                summoner_name: format!("Existing_Player_{}", puuid.get(..8).unwrap_or(&puuid)),
+                puuid,
                 region,
-                priority: SummonerPriority::Medium,
-                summoner_name: format!("Existing_Player_{}", &puuid[..8]),
+                priority,
                 added_at: chrono::Utc::now(),
                 retries: 0,
-            })
-            .collect();
+                tier,
+                not_before: None,
+                retry_class: None,
+            });
+        }
 
         self.summoner_queue.push_batch(summoner_tasks).await;
         log::info!("Queued existing summoners for match updates");
@@ -119,10 +462,19 @@ impl CrawlerEngine {
         Ok(())
     }
 
-    async fn seed_with_master_league(&self) -> crate::Result<()> {
-        log::info!("Seeding crawler with Master+ league players from all regions");
+    /// Queue up to 50 new players per region from the Master+ ladder
+    pub async fn seed_with_master_league(&self) -> crate::Result<()> {
+        self.seed_from_ladders(&self.config().regions).await;
+        Ok(())
+    }
+
+    async fn seed_from_ladders(&self, regions: &[String]) {
+        log::info!(
+            "Seeding crawler with Master+ league players from {}",
+            regions.join(", ")
+        );
 
-        for region in &self.config.regions {
+        for region in regions {
             match self.extract_summoners_from_master_league(region).await {
                 Ok(summoner_tasks) => {
                     let count = summoner_tasks.len();
@@ -147,8 +499,6 @@ impl CrawlerEngine {
 
         let total_size = self.summoner_queue.total_size().await;
         log::info!("Total queue size after Master+ league seed: {}", total_size);
-
-        Ok(())
     }
 
     async fn extract_summoners_from_master_league(
@@ -161,22 +511,33 @@ impl CrawlerEngine {
             .api_client
             .get_master_league(region, "RANKED_SOLO_5x5")
             .await?;
+        let tier = master_league.tier.parse::<Tier>().ok();
+        let priority = self
+            .stratum_priority(region, tier, SummonerPriority::High)
+            .await;
         let mut summoner_tasks = Vec::new();
 
         for entry in master_league.entries.into_iter().take(50) {
             // Limit to 50 for initial seeding
             // Check if we already have this summoner
-            match self.database.summoner_exists(&entry.puuid) {
+            match self.database.summoner_exists(&entry.puuid).await {
                 Ok(true) => continue, // Skip existing summoners
                 Ok(false) => {
                     // New summoner - add to high priority queue
                     summoner_tasks.push(SummonerTask {
                         puuid: entry.puuid.clone(),
-                        summoner_name: format!("Master_Player_{}", &entry.puuid[..8]), // Temporary name, will be resolved later
+                        // Temporary name, will be resolved later
+                        summoner_name: format!(
+                            "Master_Player_{}",
+                            entry.puuid.get(..8).unwrap_or(&entry.puuid)
+                        ),
                         region: region.to_string(),
-                        priority: SummonerPriority::High,
+                        priority: priority.clone(),
                         added_at: Utc::now(),
                         retries: 0,
+                        tier,
+                        not_before: None,
+                        retry_class: None,
                     });
                 }
                 Err(e) => {
@@ -184,11 +545,17 @@ impl CrawlerEngine {
                     // Add anyway to be safe
                     summoner_tasks.push(SummonerTask {
                         puuid: entry.puuid.clone(),
-                        summoner_name: format!("Master_Player_{}", &entry.puuid[..8]),
+                        summoner_name: format!(
+                            "Master_Player_{}",
+                            entry.puuid.get(..8).unwrap_or(&entry.puuid)
+                        ),
                         region: region.to_string(),
-                        priority: SummonerPriority::High,
+                        priority: priority.clone(),
                         added_at: Utc::now(),
                         retries: 0,
+                        tier,
+                        not_before: None,
+                        retry_class: None,
                     });
                 }
             }
@@ -202,109 +569,583 @@ impl CrawlerEngine {
         Ok(summoner_tasks)
     }
 
+    /// Seed players from the ladders of tiers that are below their sampling target
+    async fn seed_with_tier_ladders(&self) -> crate::Result<()> {
+        let Some(tracker) = &self.stratum_tracker else {
+            return Ok(());
+        };
+        let per_tier = self.config().sampling.seed_players_per_tier;
+
+        for region in &self.config().regions {
+            for tier in tracker.under_represented_tiers(region).await {
+                // Apex tiers are covered by the Master+ league seed
+                if tier.is_apex() {
+                    continue;
+                }
+
+                match self
+                    .extract_summoners_from_tier_ladder(region, tier, per_tier)
+                    .await
+                {
+                    Ok(summoner_tasks) => {
+                        log::info!(
+                            "Added {} {} summoners from {} ladder for stratified sampling",
+                            summoner_tasks.len(),
+                            tier,
+                            region
+                        );
+                        self.summoner_queue.push_batch(summoner_tasks).await;
+                    }
+                    Err(e) => {
+                        log::error!(
+                            "Failed to seed {} ladder for region {}: {}",
+                            tier,
+                            region,
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn extract_summoners_from_tier_ladder(
+        &self,
+        region: &str,
+        tier: Tier,
+        limit: usize,
+    ) -> crate::Result<Vec<SummonerTask>> {
+        let priority = self
+            .stratum_priority(region, Some(tier), SummonerPriority::High)
+            .await;
+        let mut summoner_tasks = Vec::new();
+
+        for division in ["I", "II", "III", "IV"] {
+            if summoner_tasks.len() >= limit {
+                break;
+            }
+
+            let entries = self
+                .api_client
+                .get_league_entries(region, queues::RANKED_SOLO_5X5, tier.as_str(), division, 1)
+                .await?;
+
+            for entry in entries {
+                if summoner_tasks.len() >= limit {
+                    break;
+                }
+                if self
+                    .database
+                    .summoner_exists(&entry.puuid)
+                    .await
+                    .unwrap_or(false)
+                {
+                    continue;
+                }
+
+                summoner_tasks.push(SummonerTask {
+                    summoner_name: format!(
+                        "{}_Player_{}",
+                        tier,
+                        entry.puuid.get(..8).unwrap_or(&entry.puuid)
+                    ),
+                    puuid: entry.puuid,
+                    region: region.to_string(),
+                    priority: priority.clone(),
+                    added_at: Utc::now(),
+                    retries: 0,
+                    tier: Some(tier),
+                    not_before: None,
+                    retry_class: None,
+                });
+            }
+        }
+
+        Ok(summoner_tasks)
+    }
+
+    /// Priority for a player of the given tier: stratum-driven when sampling is
+    /// enabled, otherwise the source's default priority
+    async fn stratum_priority(
+        &self,
+        region: &str,
+        tier: Option<Tier>,
+        default: SummonerPriority,
+    ) -> SummonerPriority {
+        match &self.stratum_tracker {
+            Some(tracker) => tracker.priority_for(region, tier).await,
+            None => default,
+        }
+    }
+
+    /// Put a summoner that exhausted its retries into the dead-letter queue
+    async fn record_failed_summoner(&self, task: &SummonerTask, error: &anyhow::Error) {
+        tracing::warn!("Giving up on summoner");
+        let failed = TaskFailure::from_error(error).into_failed_task(
+            FailedTaskType::Summoner,
+            &task.puuid,
+            &task.region,
+            Some(&task.summoner_name),
+            task.retries as i32 + 1,
+        );
+        if let Err(e) = self.database.record_failed_task(&failed).await {
+            tracing::error!(error = %e, "Failed to record dead letter");
+        }
+    }
+
+    /// Pick up dead-letter tasks that were marked for requeueing. Summoners go back on
+    /// the queue; matches are retried directly since the queue only holds summoners.
+    async fn replay_requeued_tasks(&self) -> crate::Result<()> {
+        let requeued = self
+            .database
+            .get_failed_tasks(&FailedTaskFilter::default(), Some("requeued"), i32::MAX)
+            .await?;
+        if requeued.is_empty() {
+            return Ok(());
+        }
+
+        log::info!("Replaying {} requeued tasks", requeued.len());
+
+        let mut summoner_tasks = Vec::new();
+        for failed in requeued {
+            match failed.task_type {
+                FailedTaskType::Summoner => summoner_tasks.push(SummonerTask {
+                    summoner_name: failed.summoner_name.unwrap_or_else(|| {
+                        format!(
+                            "Requeued_Player_{}",
+                            failed.target_id.get(..8).unwrap_or(&failed.target_id)
+                        )
+                    }),
+                    puuid: failed.target_id,
+                    region: failed.region,
+                    priority: SummonerPriority::Medium,
+                    added_at: Utc::now(),
+                    retries: 0,
+                    tier: None,
+                    not_before: None,
+                    retry_class: None,
+                }),
+                FailedTaskType::Match => {
+                    match self
+                        .worker
+                        .retry_failed_match(&failed.target_id, &failed.region)
+                        .await
+                    {
+                        Ok(new_tasks) => self.summoner_queue.push_batch(new_tasks).await,
+                        Err(e) => {
+                            log::warn!("Requeued match {} failed again: {}", failed.target_id, e)
+                        }
+                    }
+                }
+            }
+        }
+
+        self.summoner_queue.push_batch(summoner_tasks).await;
+        Ok(())
+    }
+
     async fn spawn_crawler_task(&self) -> crate::Result<()> {
         let running = self.running.clone();
-        let mut processed_count = 0;
-        let mut matches_processed = 0;
 
         while *running.read().await {
-            // Check if queue is empty
-            if self.summoner_queue.is_empty().await {
-                log::debug!("Queue is empty, waiting for new summoners");
-                sleep(Duration::from_secs(30)).await;
+            // Claim the next task while holding the in-flight slot, so a drain either sees
+            // the pause before we pop or waits for the task we popped
+            let task = {
+                let mut in_flight = self.in_flight.lock().await;
+                if self.is_paused().await {
+                    None
+                } else {
+                    let task = self.summoner_queue.pop().await;
+                    in_flight.clone_from(&task);
+                    task
+                }
+            };
+
+            let Some(task) = task else {
+                let wait = if self.is_paused().await {
+                    Duration::from_secs(1)
+                } else if self.summoner_queue.is_empty().await {
+                    log::debug!("Queue is empty, waiting for new summoners");
+                    Duration::from_secs(30)
+                } else {
+                    // Only delayed retries are left; wait for the next one to come due
+                    self.summoner_queue
+                        .next_due()
+                        .await
+                        .and_then(|due| (due - Utc::now()).to_std().ok())
+                        .unwrap_or_default()
+                        .clamp(Duration::from_millis(100), Duration::from_secs(30))
+                };
+                self.sleep_unless_stopped(wait).await;
+                continue;
+            };
+
+            if !self.config().regions.contains(&task.region) {
+                tracing::debug!(
+                    puuid = %task.puuid,
+                    region = %task.region,
+                    "Parking task, the region is no longer crawled"
+                );
+                self.parked.lock().unwrap().push(task);
+                *self.in_flight.lock().await = None;
                 continue;
             }
 
-            // Process next summoner
-            if let Some(task) = self.summoner_queue.pop().await {
-                match self.worker.process_summoner(&task).await {
-                    Ok(new_tasks) => {
-                        processed_count += 1;
-                        let match_count = new_tasks.len();
-                        matches_processed += match_count;
+            if self.region_budget_reached(&task.region) {
+                tracing::debug!(
+                    puuid = %task.puuid,
+                    region = %task.region,
+                    "Parking task, the region's match budget is used up"
+                );
+                self.parked.lock().unwrap().push(task);
+                *self.in_flight.lock().await = None;
+                continue;
+            }
 
-                        log::info!(
-                            "Processed summoner {} ({}), discovered {} new summoners",
-                            task.summoner_name,
-                            task.puuid,
-                            match_count
-                        );
+            // Strata may have filled up since the task was queued; push those players
+            // behind everything that still contributes to an under-represented stratum
+            if let Some(tracker) = &self.stratum_tracker {
+                if task.priority != SummonerPriority::Low
+                    && tracker.is_saturated(&task.region, task.tier).await
+                {
+                    let mut demoted = task;
+                    demoted.priority = SummonerPriority::Low;
+                    self.summoner_queue.push(demoted).await;
+                    *self.in_flight.lock().await = None;
+                    continue;
+                }
+            }
 
-                        // Add new summoners to queue
-                        if !new_tasks.is_empty() {
-                            self.summoner_queue.push_batch(new_tasks).await;
-                        }
+            // Everything logged while working on the task carries its puuid and attempt
+            let span = tracing::info_span!(
+                "summoner_task",
+                puuid = %task.puuid,
+                region = %task.region,
+                attempt = task.retries + 1
+            );
 
-                        // Periodic queue cleanup
-                        if processed_count % 100 == 0 {
-                            self.summoner_queue.remove_duplicates().await;
-                            let (high, medium, low) = self.summoner_queue.size().await;
-                            log::info!(
-                                "Queue status: {} high, {} medium, {} low priority summoners",
-                                high,
-                                medium,
-                                low
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("Failed to process summoner {}: {}", task.summoner_name, e);
-
-                        // Retry logic
-                        if task.retries < 3 {
-                            let mut retry_task = task.clone();
-                            retry_task.retries += 1;
-                            retry_task.priority = SummonerPriority::Low; // Demote on retry
-                            self.summoner_queue.push(retry_task).await;
-                        }
-                    }
+            // A drain that times out aborts the task; it has already been persisted
+            let result = {
+                let mut shutdown = self.shutdown.subscribe();
+                tokio::select! {
+                    result = self.worker.process_summoner(&task).instrument(span.clone()) => Some(result),
+                    _ = shutdown.wait_for(|state| *state == ShutdownState::Aborting) => None,
                 }
+            };
+            let Some(result) = result else {
+                tracing::warn!(parent: &span, "Abandoned in-flight summoner");
+                break;
+            };
 
-                // Rate limiting - small delay between requests
-                sleep(Duration::from_millis(100)).await;
-            }
+            self.finish_task(&task, result).instrument(span).await;
+
+            // Follow-up work is queued, so the task is no longer in flight
+            *self.in_flight.lock().await = None;
+
+            // Rate limiting - small delay between requests
+            sleep(Duration::from_millis(100)).await;
         }
 
         log::info!(
             "Crawler task completed. Processed {} summoners, {} matches",
-            processed_count,
-            matches_processed
+            self.summoners_processed.load(Ordering::Relaxed),
+            self.worker.matches_stored()
         );
         Ok(())
     }
 
+    /// Queue the follow-up work for a processed task, or schedule its retry
+    async fn finish_task(&self, task: &SummonerTask, result: crate::Result<Vec<SummonerTask>>) {
+        match result {
+            Ok(new_tasks) => {
+                let processed_count = self.summoners_processed.fetch_add(1, Ordering::Relaxed) + 1;
+                self.incident_streaks
+                    .lock()
+                    .unwrap()
+                    .retain(|(_, region), _| *region != task.region);
+
+                if let Err(e) = self
+                    .database
+                    .resolve_failed_task(FailedTaskType::Summoner, &task.puuid)
+                    .await
+                {
+                    tracing::warn!(error = %e, "Failed to clear dead letter");
+                }
+
+                // Add new summoners to queue
+                if !new_tasks.is_empty() {
+                    self.summoner_queue.push_batch(new_tasks).await;
+                }
+
+                // Periodic queue cleanup
+                if processed_count.is_multiple_of(100) {
+                    self.summoner_queue.remove_duplicates().await;
+                    let (high, medium, low) = self.summoner_queue.size().await;
+                    tracing::info!(high, medium, low, "Queue status");
+                }
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to process summoner");
+
+                // Retry with a per-error-class backoff, keeping the original priority
+                let failure = TaskFailure::from_error(&e);
+                let retry_class = failure.retry_class();
+                if retry_class == RetryClass::Authentication {
+                    self.check_api_keys().await;
+                }
+                self.record_incident(task, &failure);
+                if retry_class.is_retryable() && task.retries < 3 {
+                    let delay = retry_delay(retry_class, task.retries);
+                    tracing::info!(
+                        delay_seconds = delay.num_seconds(),
+                        retry_class = ?retry_class,
+                        "Scheduling retry"
+                    );
+                    let mut retry_task = task.clone();
+                    retry_task.retries += 1;
+                    retry_task.retry_class = Some(retry_class);
+                    retry_task.not_before = Some(Utc::now() + delay);
+                    self.summoner_queue.push(retry_task).await;
+                } else {
+                    self.record_failed_summoner(task, &e).await;
+                }
+            }
+        }
+    }
+
+    /// Count a failed task towards its region's incident streak, emitting an event when
+    /// the streak reaches the threshold. Storage failures are reported right away.
+    fn record_incident(&self, task: &SummonerTask, failure: &TaskFailure) {
+        let (kind, threshold) = match failure.retry_class() {
+            RetryClass::ServiceUnavailable => (
+                EventKind::ServerErrors,
+                self.config().events.incident_threshold,
+            ),
+            RetryClass::RateLimited => (
+                EventKind::RateLimitStorm,
+                self.config().events.incident_threshold,
+            ),
+            _ if failure.error_kind == "Database" => (EventKind::StorageFailure, 1),
+            _ => return,
+        };
+        let streak = {
+            let mut streaks = self.incident_streaks.lock().unwrap();
+            let streak = streaks.entry((kind, task.region.clone())).or_insert(0);
+            *streak += 1;
+            *streak
+        };
+        if streak != threshold {
+            return;
+        }
+
+        let message = match kind {
+            EventKind::ServerErrors => format!(
+                "{} tasks in a row failed with server errors in {}: {}",
+                streak, task.region, failure.message
+            ),
+            EventKind::RateLimitStorm => format!(
+                "{} tasks in a row were rate limited in {}",
+                streak, task.region
+            ),
+            _ => format!(
+                "Failed to store data for {}: {}",
+                task.puuid, failure.message
+            ),
+        };
+        self.events
+            .emit(Event::new(kind, message).in_region(&task.region));
+    }
+
+    /// Whether `region` has stored as many matches as its own budget allows
+    fn region_budget_reached(&self, region: &str) -> bool {
+        self.config()
+            .max_matches_for(region)
+            .is_some_and(|limit| self.worker.matches_stored_in(region) >= limit)
+    }
+
+    /// Drain and stop the run once any configured budget is used up, or once every
+    /// region has used up its own match budget. Budgets are read on every tick so
+    /// reloaded ones take effect.
+    async fn spawn_budget_task(&self) -> crate::Result<()> {
+        let mut interval = interval(Duration::from_secs(1));
+        let mut shutdown = self.shutdown.subscribe();
+        let mut exhausted_regions = HashSet::new();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => break,
+            }
+
+            // A reload can raise a region's budget again
+            let config = self.config();
+            for region in &config.regions {
+                if !self.region_budget_reached(region) {
+                    exhausted_regions.remove(region);
+                } else if exhausted_regions.insert(region.clone()) {
+                    log::info!("Match budget of {} reached, parking its tasks", region);
+                }
+            }
+
+            let progress = self.progress().await;
+            let reason = exceeded_budget(&config.budget, &progress).or_else(|| {
+                (!config.regions.is_empty()
+                    && config
+                        .regions
+                        .iter()
+                        .all(|region| exhausted_regions.contains(region)))
+                .then_some(StopReason::MatchBudget)
+            });
+            if let Some(reason) = reason {
+                self.events.emit(Event::new(
+                    EventKind::BudgetReached,
+                    format!("Stopping crawl: {}", reason),
+                ));
+                *self.stop_reason.write().await = Some(reason);
+                let timeout = Duration::from_secs(config.crawler.drain_timeout_seconds);
+                if let Err(e) = self.drain(timeout).await {
+                    log::error!("Failed to persist queue during drain: {}", e);
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Counters for the current run
+    pub async fn progress(&self) -> RunProgress {
+        let matches_stored = self.worker.matches_stored();
+        let idle = {
+            let mut last_new_match = self.last_new_match.lock().unwrap();
+            if matches_stored > last_new_match.0 {
+                *last_new_match = (matches_stored, Instant::now());
+            }
+            last_new_match.1.elapsed()
+        };
+        let elapsed = self
+            .started_at
+            .read()
+            .await
+            .and_then(|started_at| (Utc::now() - started_at).to_std().ok())
+            .unwrap_or_default();
+
+        RunProgress {
+            requests: self.api_client.requests_made(),
+            summoners_processed: self.summoners_processed.load(Ordering::Relaxed),
+            matches_stored,
+            elapsed,
+            idle,
+        }
+    }
+
+    /// Report for the end of a run. Tasks persisted by a drain count as left in the queue.
+    pub async fn run_summary(&self) -> RunSummary {
+        let persisted = self.database.get_pending_tasks_count().await.unwrap_or(0) as usize;
+        RunSummary {
+            stop_reason: self
+                .stop_reason
+                .read()
+                .await
+                .unwrap_or(StopReason::Shutdown),
+            started_at: *self.started_at.read().await,
+            progress: self.progress().await,
+            queue_size: self.summoner_queue.total_size().await + persisted,
+            state: self.database.get_crawler_state().await.ok().flatten(),
+        }
+    }
+
+    /// Sleep for `duration`, returning early if the crawler is stopped meanwhile
+    async fn sleep_unless_stopped(&self, duration: Duration) {
+        let mut shutdown = self.shutdown.subscribe();
+        tokio::select! {
+            _ = sleep(duration) => {}
+            _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => {}
+        }
+    }
+
     async fn spawn_health_check_task(&self) -> crate::Result<()> {
         let mut interval = interval(Duration::from_secs(
-            self.config.crawler.health_check_interval_seconds,
+            self.config().crawler.health_check_interval_seconds,
         ));
         let running = self.running.clone();
+        let mut shutdown = self.shutdown.subscribe();
+        let mut last_summary = (Instant::now(), self.progress().await);
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => break,
+            }
 
             if !*running.read().await {
                 break;
             }
 
+            if last_summary.0.elapsed() >= DAILY_SUMMARY_INTERVAL {
+                let progress = self.progress().await;
+                let since = &last_summary.1;
+                self.events.emit(Event::new(
+                    EventKind::DailySummary,
+                    format!(
+                        "Last 24h: {} matches stored, {} summoners processed, {} requests; \
+                         {} tasks queued",
+                        progress.matches_stored - since.matches_stored,
+                        progress.summoners_processed - since.summoners_processed,
+                        progress.requests - since.requests,
+                        self.summoner_queue.total_size().await
+                    ),
+                ));
+                last_summary = (Instant::now(), progress);
+            }
+
             // Get current stats
             let (high, medium, low) = self.summoner_queue.size().await;
+            let delayed = self.summoner_queue.delayed_size().await;
             let rate_limit_status = self.api_client.get_rate_limit_status().await;
 
-            let matches_count = self.database.get_matches_count().unwrap_or(0);
-            let summoners_count = self.database.get_summoners_count().unwrap_or(0);
-            let participants_count = self.database.get_participants_count().unwrap_or(0);
+            let matches_count = self.database.get_matches_count().await.unwrap_or(0);
+            let summoners_count = self.database.get_summoners_count().await.unwrap_or(0);
+            let participants_count = self.database.get_participants_count().await.unwrap_or(0);
 
-            log::info!(
-                "Health Check - Queue: {}H/{}M/{}L, DB: {}M/{}S/{}P, Rate Limits: {}/{}",
-                high,
-                medium,
-                low,
-                matches_count,
-                summoners_count,
-                participants_count,
-                rate_limit_status.application_tokens_per_second,
-                rate_limit_status.application_tokens_per_two_minutes
+            tracing::info!(
+                queue_high = high,
+                queue_medium = medium,
+                queue_low = low,
+                queue_delayed = delayed,
+                matches = matches_count,
+                summoners = summoners_count,
+                participants = participants_count,
+                tokens_per_second = rate_limit_status.application_tokens_per_second,
+                tokens_per_two_minutes = rate_limit_status.application_tokens_per_two_minutes,
+                usable_api_keys = self.api_keys.usable_keys(),
+                "Health check"
             );
+
+            if let Some(since) = self.auth_paused_since() {
+                log::warn!(
+                    "Crawler is paused: every API key has been rejected since {}",
+                    since.to_rfc3339()
+                );
+            } else if self.is_paused().await {
+                log::info!("Crawler is paused, not pulling new work");
+            }
+
+            if let Some(tracker) = &self.stratum_tracker {
+                for region in &self.config().regions {
+                    let progress: Vec<String> = tracker
+                        .progress(std::slice::from_ref(region))
+                        .await
+                        .iter()
+                        .map(|p| format!("{}={}/{}", p.tier, p.matches, p.target))
+                        .collect();
+                    if !progress.is_empty() {
+                        log::info!("Sampling progress {}: {}", region, progress.join(" "));
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -312,32 +1153,22 @@ impl CrawlerEngine {
 
     async fn spawn_state_save_task(&self) -> crate::Result<()> {
         let mut interval = interval(Duration::from_secs(
-            self.config.crawler.state_save_interval_seconds,
+            self.config().crawler.state_save_interval_seconds,
         ));
         let running = self.running.clone();
+        let mut shutdown = self.shutdown.subscribe();
 
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => break,
+            }
 
             if !*running.read().await {
                 break;
             }
 
-            // Save crawler state
-            let total_queue_size = self.summoner_queue.total_size().await;
-            let matches_count = self.database.get_matches_count().unwrap_or(0);
-            let summoners_count = self.database.get_summoners_count().unwrap_or(0);
-
-            let state = DbCrawlerState {
-                id: 1,
-                last_processed_summoner: None, // Could track this if needed
-                total_summoners_processed: summoners_count as i32,
-                total_matches_processed: matches_count as i32,
-                queue_size: total_queue_size as i32,
-                last_update: Utc::now(),
-            };
-
-            if let Err(e) = self.database.update_crawler_state(&state) {
+            if let Err(e) = self.save_state().await {
                 log::error!("Failed to save crawler state: {}", e);
             } else {
                 log::debug!("Crawler state saved");
@@ -347,39 +1178,306 @@ impl CrawlerEngine {
         Ok(())
     }
 
+    /// Pause the crawler once no API key is left to send requests with, e.g. because a
+    /// development key expired, and resume it once a key file provides a working key.
+    /// Both are announced through the alert webhook. This pause is tracked apart from
+    /// `pause`, so a crawler paused by an operator stays paused when a key comes back.
+    pub async fn check_api_keys(&self) {
+        let statuses = self.api_keys.status().await;
+        for key in statuses.iter().filter(|key| key.in_use && key.retired) {
+            if self.retired_keys.lock().unwrap().insert(key.label.clone()) {
+                self.events.emit(Event::new(
+                    EventKind::ApiKeyRetired,
+                    format!(
+                        "API key {} was retired after {} authentication failures in a row",
+                        key.label, key.auth_failures
+                    ),
+                ));
+            }
+        }
+
+        let usable = self.api_keys.usable_keys() > 0;
+        let transition = {
+            let mut paused_since = self.auth_paused_since.lock().unwrap();
+            match (usable, *paused_since) {
+                (false, None) => {
+                    *paused_since = Some(Utc::now());
+                    Some(EventKind::ApiKeysExpired)
+                }
+                (true, Some(_)) => {
+                    *paused_since = None;
+                    Some(EventKind::ApiKeysRecovered)
+                }
+                _ => None,
+            }
+        };
+
+        let event = match transition {
+            Some(EventKind::ApiKeysExpired) => {
+                log::warn!("Pausing crawler, no API key works");
+                let labels: Vec<String> = statuses
+                    .into_iter()
+                    .filter(|key| key.in_use)
+                    .map(|key| key.label)
+                    .collect();
+                Event::new(
+                    EventKind::ApiKeysExpired,
+                    format!(
+                        "Every API key was rejected ({}); the crawler is paused until a key \
+                         file holds a working key",
+                        labels.join(", ")
+                    ),
+                )
+            }
+            Some(_) => {
+                // Keys retired from here on are news again
+                self.retired_keys.lock().unwrap().clear();
+                let message = if *self.paused.read().await {
+                    "A working API key is back; the crawler stays paused until resumed"
+                } else {
+                    log::info!("Resuming crawler, a working API key is back");
+                    "A working API key is back; the crawler resumed"
+                };
+                Event::new(EventKind::ApiKeysRecovered, message)
+            }
+            None => return,
+        };
+        self.events.emit(event);
+    }
+
+    /// Look for replacement keys while paused for lack of one
+    async fn spawn_api_key_task(&self) -> crate::Result<()> {
+        let mut interval = interval(Duration::from_secs(10));
+        let mut shutdown = self.shutdown.subscribe();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => break,
+            }
+
+            if self.auth_paused_since().is_some() {
+                self.api_keys.reload_key_files();
+            }
+            self.check_api_keys().await;
+        }
+
+        Ok(())
+    }
+
+    /// When the crawler paused itself because no API key works
+    pub fn auth_paused_since(&self) -> Option<DateTime<Utc>> {
+        *self.auth_paused_since.lock().unwrap()
+    }
+
+    async fn spawn_retention_task(&self) -> crate::Result<()> {
+        let running = self.running.clone();
+        let mut shutdown = self.shutdown.subscribe();
+        // The first pass runs immediately, so a restarted crawler prunes right away
+        let mut wait = Duration::ZERO;
+
+        loop {
+            tokio::select! {
+                _ = sleep(wait) => {}
+                _ = shutdown.wait_for(|state| *state != ShutdownState::Running) => break,
+            }
+
+            if !*running.read().await {
+                break;
+            }
+
+            // Read on every pass so reloaded retention settings take effect
+            let retention = self.config().retention.clone();
+            wait = Duration::from_secs(retention.interval_minutes * 60);
+            if !retention.is_enabled() {
+                continue;
+            }
+
+            match apply_retention(self.database.as_ref(), &retention, Utc::now()).await {
+                Ok(summary) if summary.api_calls_rolled_up > 0 || summary.matches_pruned > 0 => {
+                    log::info!(
+                        "Retention - rolled up {} API calls, pruned {} matches from patches [{}]",
+                        summary.api_calls_rolled_up,
+                        summary.matches_pruned,
+                        summary.pruned_patches.join(", ")
+                    )
+                }
+                Ok(_) => log::debug!("Retention - nothing to prune"),
+                Err(e) => log::error!("Retention pass failed: {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn save_state(&self) -> crate::Result<()> {
+        let total_queue_size = self.summoner_queue.total_size().await;
+        let matches_count = self.database.get_matches_count().await.unwrap_or(0);
+        let summoners_count = self.database.get_summoners_count().await.unwrap_or(0);
+
+        let state = DbCrawlerState {
+            id: 1,
+            last_processed_summoner: None, // Could track this if needed
+            total_summoners_processed: summoners_count as i32,
+            total_matches_processed: matches_count as i32,
+            queue_size: total_queue_size as i32,
+            last_update: Utc::now(),
+        };
+
+        self.database.update_crawler_state(&state).await
+    }
+
+    pub fn api_client(&self) -> &RiotApiClient {
+        &self.api_client
+    }
+
+    pub fn api_keys(&self) -> &ApiKeyPool {
+        &self.api_keys
+    }
+
+    /// Queue a player for crawling from outside the crawler, e.g. through the admin API
+    pub async fn enqueue_player(
+        &self,
+        puuid: &str,
+        region: &str,
+        priority: SummonerPriority,
+    ) -> crate::Result<()> {
+        if puuid.is_empty() {
+            anyhow::bail!("puuid must not be empty");
+        }
+        let config = self.config();
+        if !config.regions.iter().any(|r| r == region) {
+            anyhow::bail!(
+                "Region {} is not one of the crawled regions ({})",
+                region,
+                config.regions.join(", ")
+            );
+        }
+
+        log::info!("Queueing player {} in {} ({:?})", puuid, region, priority);
+        self.summoner_queue
+            .push(SummonerTask {
+                puuid: puuid.to_string(),
+                summoner_name: format!("Queued_Player_{}", puuid.get(..8).unwrap_or(puuid)),
+                region: region.to_string(),
+                priority,
+                added_at: Utc::now(),
+                retries: 0,
+                tier: None,
+                not_before: None,
+                retry_class: None,
+            })
+            .await;
+        Ok(())
+    }
+
+    /// Whether the crawler can do work: the database answers queries, the rate limiter
+    /// answers within a second and an API key works
+    pub async fn readiness(&self) -> Readiness {
+        let database = self
+            .database
+            .get_matches_count()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string());
+        let rate_limiter = tokio::time::timeout(
+            Duration::from_secs(1),
+            self.api_client.get_rate_limit_status(),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|_| "rate limiter did not respond within 1s".to_string());
+        let api_keys = match self.auth_paused_since() {
+            Some(since) => Err(format!(
+                "every API key has been rejected since {}",
+                since.to_rfc3339()
+            )),
+            None => Ok(()),
+        };
+
+        Readiness {
+            database,
+            rate_limiter,
+            api_keys,
+        }
+    }
+
     pub async fn get_status(&self) -> CrawlerStatus {
         let (high, medium, low) = self.summoner_queue.size().await;
+        let delayed = self.summoner_queue.delayed_size().await;
+        let parked = self.parked.lock().unwrap().len();
         let rate_limit_status = self.api_client.get_rate_limit_status().await;
 
+        let strata = match &self.stratum_tracker {
+            Some(tracker) => tracker.progress(&self.config().regions).await,
+            None => Vec::new(),
+        };
+
         CrawlerStatus {
             running: self.is_running().await,
-            queue_sizes: QueueSizes { high, medium, low },
+            paused: self.is_paused().await,
+            run: self.progress().await,
+            queue_sizes: QueueSizes {
+                high,
+                medium,
+                low,
+                delayed,
+                parked,
+            },
             rate_limit_status,
+            api_keys: self.api_keys.status().await,
+            auth_paused_since: self.auth_paused_since(),
+            strata,
             database_stats: DatabaseStats {
-                matches: self.database.get_matches_count().unwrap_or(0),
-                summoners: self.database.get_summoners_count().unwrap_or(0),
-                participants: self.database.get_participants_count().unwrap_or(0),
+                matches: self.database.get_matches_count().await.unwrap_or(0),
+                summoners: self.database.get_summoners_count().await.unwrap_or(0),
+                participants: self.database.get_participants_count().await.unwrap_or(0),
             },
         }
     }
 }
 
+/// Outcome of each readiness check, with the error when it failed
 #[derive(Debug)]
+pub struct Readiness {
+    pub database: std::result::Result<(), String>,
+    pub rate_limiter: std::result::Result<(), String>,
+    pub api_keys: std::result::Result<(), String>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.database.is_ok() && self.rate_limiter.is_ok() && self.api_keys.is_ok()
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct CrawlerStatus {
     pub running: bool,
+    pub paused: bool,
+    pub run: RunProgress,
     pub queue_sizes: QueueSizes,
     pub rate_limit_status: crate::rate_limiter::RateLimitStatus,
+    pub api_keys: Vec<ApiKeyStatus>,
+    /// Set while the crawler is paused because every API key was rejected
+    pub auth_paused_since: Option<DateTime<Utc>>,
+    /// Sampling progress on the current patch; empty when sampling is disabled
+    pub strata: Vec<StratumProgress>,
     pub database_stats: DatabaseStats,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct QueueSizes {
     pub high: usize,
     pub medium: usize,
     pub low: usize,
+    /// Tasks waiting for a scheduled retry
+    pub delayed: usize,
+    /// Tasks set aside until their region is crawled again and has match budget left
+    pub parked: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DatabaseStats {
     pub matches: i64,
     pub summoners: i64,
@@ -0,0 +1,179 @@
+use crate::api::ApiError;
+use crate::database::StorageError;
+use crate::models::database::{DbFailedTask, FailedTaskType, RetryClass};
+use chrono::{Duration, Utc};
+
+/// Upper bound on the delay between two attempts of the same task
+const MAX_RETRY_DELAY_SECONDS: i64 = 3600;
+
+/// Classification of an error that made a summoner or match task fail
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskFailure {
+    pub error_kind: String,
+    pub http_status: Option<u16>,
+    pub message: String,
+}
+
+impl TaskFailure {
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let (error_kind, http_status) = if let Some(api_error) = error.downcast_ref::<ApiError>() {
+            (api_error.kind(), api_error.status_code())
+        } else if is_storage_error(error) {
+            ("Database", None)
+        } else {
+            ("Other", None)
+        };
+
+        Self {
+            error_kind: error_kind.to_string(),
+            http_status,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn retry_class(&self) -> RetryClass {
+        match self.error_kind.as_str() {
+            "RateLimit" | "RateLimiter" => RetryClass::RateLimited,
+            "ServiceUnavailable" => RetryClass::ServiceUnavailable,
+            "Http" => RetryClass::Network,
+            "Authentication" => RetryClass::Authentication,
+            "Json" => RetryClass::Parse,
+            "NotFound" | "BadRequest" => RetryClass::Permanent,
+            "Api" => match self.http_status {
+                Some(429) => RetryClass::RateLimited,
+                Some(500..=599) => RetryClass::ServiceUnavailable,
+                Some(400..=499) => RetryClass::Permanent,
+                _ => RetryClass::Other,
+            },
+            _ => RetryClass::Other,
+        }
+    }
+
+    pub fn into_failed_task(
+        self,
+        task_type: FailedTaskType,
+        target_id: &str,
+        region: &str,
+        summoner_name: Option<&str>,
+        attempts: i32,
+    ) -> DbFailedTask {
+        let now = Utc::now();
+        DbFailedTask {
+            id: None,
+            task_type,
+            target_id: target_id.to_string(),
+            region: region.to_string(),
+            summoner_name: summoner_name.map(str::to_string),
+            error_kind: self.error_kind,
+            http_status: self.http_status,
+            attempts,
+            last_error: self.message,
+            status: "failed".to_string(),
+            first_failed_at: now,
+            last_failed_at: now,
+        }
+    }
+}
+
+/// Whether a storage backend failed anywhere in the error's chain, including driver
+/// errors that reached the crawler without being wrapped
+fn is_storage_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.is::<StorageError>()
+            || cause.is::<rusqlite::Error>()
+            || cause.is::<tokio_postgres::Error>()
+    })
+}
+
+/// Exponential backoff before retry number `retries + 1`: the class's base delay doubled
+/// per previous retry, capped at an hour
+pub fn retry_delay(class: RetryClass, retries: u32) -> Duration {
+    let seconds = class
+        .base_delay_seconds()
+        .saturating_mul(1i64 << retries.min(20))
+        .min(MAX_RETRY_DELAY_SECONDS);
+    Duration::seconds(seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classifies_api_errors() {
+        let error = anyhow::Error::from(ApiError::NotFound);
+        let failure = TaskFailure::from_error(&error);
+        assert_eq!(failure.error_kind, "NotFound");
+        assert_eq!(failure.http_status, Some(404));
+
+        let error = anyhow::Error::from(ApiError::Api {
+            status: 418,
+            message: "teapot".to_string(),
+        });
+        let failure = TaskFailure::from_error(&error);
+        assert_eq!(failure.error_kind, "Api");
+        assert_eq!(failure.http_status, Some(418));
+
+        let json_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let failure = TaskFailure::from_error(&ApiError::Json(json_error).into());
+        assert_eq!(failure.error_kind, "Json");
+        assert_eq!(failure.http_status, None);
+    }
+
+    #[test]
+    fn test_classifies_storage_errors() {
+        let error = crate::Database::open(":memory:")
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM missing", &[], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap_err();
+        assert_eq!(TaskFailure::from_error(&error).error_kind, "Database");
+
+        let error =
+            anyhow::Error::from(StorageError::WriterStopped).context("Failed to store match");
+        let failure = TaskFailure::from_error(&error);
+        assert_eq!(failure.error_kind, "Database");
+        assert_eq!(failure.retry_class(), RetryClass::Other);
+    }
+
+    #[test]
+    fn test_classifies_other_errors() {
+        let error = anyhow::Error::from(rusqlite::Error::QueryReturnedNoRows);
+        assert_eq!(TaskFailure::from_error(&error).error_kind, "Database");
+
+        let error = anyhow::anyhow!("something else");
+        let failure = TaskFailure::from_error(&error);
+        assert_eq!(failure.error_kind, "Other");
+        assert_eq!(failure.message, "something else");
+
+        let failed = failure.into_failed_task(FailedTaskType::Match, "NA1_1", "na1", None, 1);
+        assert_eq!(failed.target_id, "NA1_1");
+        assert_eq!(failed.status, "failed");
+    }
+
+    #[test]
+    fn test_retry_classes_and_backoff() {
+        let failure = TaskFailure::from_error(&ApiError::ServiceUnavailable.into());
+        assert_eq!(failure.retry_class(), RetryClass::ServiceUnavailable);
+        let failure = TaskFailure::from_error(&ApiError::NotFound.into());
+        assert!(!failure.retry_class().is_retryable());
+        let failure = TaskFailure::from_error(
+            &ApiError::Api {
+                status: 502,
+                message: "bad gateway".to_string(),
+            }
+            .into(),
+        );
+        assert_eq!(failure.retry_class(), RetryClass::ServiceUnavailable);
+
+        // Outages back off longer than parse errors
+        assert!(retry_delay(RetryClass::ServiceUnavailable, 0) > retry_delay(RetryClass::Parse, 0));
+        assert_eq!(retry_delay(RetryClass::Parse, 0), Duration::seconds(5));
+        assert_eq!(retry_delay(RetryClass::Parse, 2), Duration::seconds(20));
+        assert_eq!(
+            retry_delay(RetryClass::ServiceUnavailable, 30),
+            Duration::seconds(MAX_RETRY_DELAY_SECONDS)
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use crate::database::Storage;
+use crate::metrics::metrics;
+use crate::models::database::DbMatchHookRow;
+use crate::models::match_v5::MatchDto;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+/// A crawled match as hooks see it
+#[derive(Debug, Clone, Copy)]
+pub struct HookedMatch<'a> {
+    /// Platform the match was crawled from
+    pub region: &'a str,
+    pub match_data: &'a MatchDto,
+    /// Timeline response, when timelines are fetched for the raw archive
+    pub timeline: Option<&'a serde_json::Value>,
+}
+
+/// Extra row a hook stores for a match, in the `match_hook_rows` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookRow {
+    /// What the row holds, e.g. the name of a computed feature
+    pub kind: String,
+    pub data: serde_json::Value,
+}
+
+impl HookRow {
+    pub fn new(kind: impl Into<String>, data: serde_json::Value) -> Self {
+        Self {
+            kind: kind.into(),
+            data,
+        }
+    }
+}
+
+/// Custom processing for every crawled match, registered with
+/// `CrawlerEngine::with_match_hook`. A hook that fails, panics or runs past
+/// `crawler.hook_timeout_seconds` is logged and counted in `hook_errors_total`, and never
+/// costs the crawler a match.
+#[async_trait]
+pub trait MatchHook: Send + Sync {
+    /// Name the hook's rows are stored under
+    fn name(&self) -> &str;
+
+    /// Whether the match is stored. Runs before anything is written; a match declined
+    /// by any hook is skipped like a match from another queue. A hook that fails or
+    /// times out lets the match through.
+    async fn should_store(&self, _hooked: &HookedMatch<'_>) -> crate::Result<bool> {
+        Ok(true)
+    }
+
+    /// Runs once the match is stored. The rows returned replace the ones the hook
+    /// stored for the match before.
+    async fn on_match_stored(&self, _hooked: &HookedMatch<'_>) -> crate::Result<Vec<HookRow>> {
+        Ok(Vec::new())
+    }
+}
+
+/// How long each hook call may run unless configured otherwise
+pub(crate) const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The registered hooks, run in registration order
+#[derive(Clone)]
+pub(crate) struct MatchHooks {
+    hooks: Vec<Arc<dyn MatchHook>>,
+    /// Limit on each call of a hook
+    timeout: Duration,
+}
+
+impl Default for MatchHooks {
+    fn default() -> Self {
+        Self {
+            hooks: Vec::new(),
+            timeout: DEFAULT_HOOK_TIMEOUT,
+        }
+    }
+}
+
+impl MatchHooks {
+    pub(crate) fn add(&mut self, hook: Arc<dyn MatchHook>) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Name of the first hook that declines the match, if any
+    pub(crate) async fn declined_by(&self, hooked: &HookedMatch<'_>) -> Option<String> {
+        for hook in &self.hooks {
+            let declined = self
+                .isolate(hook.as_ref(), "should_store", hook.should_store(hooked))
+                .await
+                == Some(false);
+            if declined {
+                tracing::debug!(hook = hook.name(), "Hook declined the match");
+                return Some(hook.name().to_string());
+            }
+        }
+        None
+    }
+
+    /// Run every hook on a stored match and store the rows they produce
+    pub(crate) async fn match_stored(&self, hooked: &HookedMatch<'_>, database: &dyn Storage) {
+        let match_id = &hooked.match_data.metadata.match_id;
+        for hook in &self.hooks {
+            let Some(rows) = self
+                .isolate(
+                    hook.as_ref(),
+                    "on_match_stored",
+                    hook.on_match_stored(hooked),
+                )
+                .await
+            else {
+                continue;
+            };
+            let created_at = Utc::now();
+            let rows: Vec<DbMatchHookRow> = rows
+                .into_iter()
+                .map(|row| DbMatchHookRow {
+                    match_id: match_id.clone(),
+                    hook: hook.name().to_string(),
+                    kind: row.kind,
+                    data: row.data,
+                    created_at,
+                })
+                .collect();
+            if let Err(e) = database.store_hook_rows(match_id, hook.name(), &rows).await {
+                count_hook_error(hook.as_ref(), "store_rows");
+                tracing::warn!(hook = hook.name(), error = %e, "Failed to store hook rows");
+            }
+        }
+    }
+
+    /// The hook's result, or `None` once its error, panic or timeout has been logged
+    async fn isolate<T>(
+        &self,
+        hook: &dyn MatchHook,
+        stage: &str,
+        mut future: Pin<Box<dyn Future<Output = crate::Result<T>> + Send + '_>>,
+    ) -> Option<T> {
+        let guarded = std::future::poll_fn(|cx| {
+            match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                Ok(Poll::Ready(result)) => Poll::Ready(Ok(result)),
+                Ok(Poll::Pending) => Poll::Pending,
+                Err(panic) => Poll::Ready(Err(panic)),
+            }
+        });
+        let Ok(outcome) = tokio::time::timeout(self.timeout, guarded).await else {
+            count_hook_error(hook, stage);
+            tracing::warn!(
+                hook = hook.name(),
+                stage,
+                timeout_ms = self.timeout.as_millis() as u64,
+                "Match hook timed out"
+            );
+            return None;
+        };
+
+        match outcome {
+            Ok(Ok(value)) => Some(value),
+            Ok(Err(e)) => {
+                count_hook_error(hook, stage);
+                tracing::warn!(hook = hook.name(), stage, error = %e, "Match hook failed");
+                None
+            }
+            Err(panic) => {
+                count_hook_error(hook, stage);
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                tracing::error!(
+                    hook = hook.name(),
+                    stage,
+                    "Match hook panicked: {}",
+                    message
+                );
+                None
+            }
+        }
+    }
+}
+
+fn count_hook_error(hook: &dyn MatchHook, stage: &str) {
+    metrics()
+        .hook_errors
+        .with_label_values(&[hook.name(), stage])
+        .inc();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::models::fixtures::match_dto;
+
+    /// Stores the game duration, and declines matches shorter than `min_duration`
+    struct MinDuration {
+        min_duration: i64,
+    }
+
+    #[async_trait]
+    impl MatchHook for MinDuration {
+        fn name(&self) -> &str {
+            "duration"
+        }
+
+        async fn should_store(&self, hooked: &HookedMatch<'_>) -> crate::Result<bool> {
+            Ok(hooked.match_data.info.game_duration >= self.min_duration)
+        }
+
+        async fn on_match_stored(&self, hooked: &HookedMatch<'_>) -> crate::Result<Vec<HookRow>> {
+            Ok(vec![HookRow::new(
+                "game_duration",
+                serde_json::json!({ "seconds": hooked.match_data.info.game_duration }),
+            )])
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl MatchHook for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn should_store(&self, _hooked: &HookedMatch<'_>) -> crate::Result<bool> {
+            anyhow::bail!("feature store is down")
+        }
+
+        async fn on_match_stored(&self, _hooked: &HookedMatch<'_>) -> crate::Result<Vec<HookRow>> {
+            panic!("bad plugin")
+        }
+    }
+
+    /// Never answers
+    struct Stalled;
+
+    #[async_trait]
+    impl MatchHook for Stalled {
+        fn name(&self) -> &str {
+            "stalled"
+        }
+
+        async fn should_store(&self, _hooked: &HookedMatch<'_>) -> crate::Result<bool> {
+            std::future::pending().await
+        }
+    }
+
+    fn hook_rows(db: &Database, match_id: &str) -> Vec<(String, String, String)> {
+        db.query_map(
+            "SELECT hook, kind, data FROM match_hook_rows WHERE match_id = ?1 ORDER BY id",
+            &[&match_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hooks_can_decline_matches() {
+        let mut hooks = MatchHooks::default();
+        hooks.add(Arc::new(Failing));
+        hooks.add(Arc::new(MinDuration { min_duration: 900 }));
+        let long = match_dto("NA1_1");
+        let mut short = match_dto("NA1_2");
+        short.info.game_duration = 300;
+
+        // The failing hook lets matches through; the duration hook decides
+        let hooked = |match_data| HookedMatch {
+            region: "na1",
+            match_data,
+            timeline: None,
+        };
+        assert_eq!(hooks.declined_by(&hooked(&long)).await, None);
+        assert_eq!(
+            hooks.declined_by(&hooked(&short)).await.as_deref(),
+            Some("duration")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stalled_hooks_time_out() {
+        let mut hooks = MatchHooks::default();
+        hooks.add(Arc::new(Stalled));
+        hooks.add(Arc::new(MinDuration { min_duration: 900 }));
+        hooks.set_timeout(Duration::from_millis(20));
+        let errors_before = metrics()
+            .hook_errors
+            .with_label_values(&["stalled", "should_store"])
+            .get();
+
+        let mut short = match_dto("NA1_1");
+        short.info.game_duration = 300;
+        let declined_by = hooks
+            .declined_by(&HookedMatch {
+                region: "na1",
+                match_data: &short,
+                timeline: None,
+            })
+            .await;
+
+        // The stalled hook is abandoned and the next hook still decides
+        assert_eq!(declined_by.as_deref(), Some("duration"));
+        assert_eq!(
+            metrics()
+                .hook_errors
+                .with_label_values(&["stalled", "should_store"])
+                .get(),
+            errors_before + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_hooks_do_not_stop_the_others() {
+        let db = Database::new(":memory:").unwrap();
+        let mut hooks = MatchHooks::default();
+        hooks.add(Arc::new(Failing));
+        hooks.add(Arc::new(MinDuration { min_duration: 0 }));
+        let errors_before = metrics()
+            .hook_errors
+            .with_label_values(&["failing", "on_match_stored"])
+            .get();
+
+        let mut match_data = match_dto("NA1_1");
+        for _ in 0..2 {
+            hooks
+                .match_stored(
+                    &HookedMatch {
+                        region: "na1",
+                        match_data: &match_data,
+                        timeline: None,
+                    },
+                    &db,
+                )
+                .await;
+            match_data.info.game_duration += 60;
+        }
+
+        // Rows from the second run replace the first run's
+        assert_eq!(
+            hook_rows(&db, "NA1_1"),
+            [(
+                "duration".to_string(),
+                "game_duration".to_string(),
+                r#"{"seconds":1860}"#.to_string()
+            )]
+        );
+        assert_eq!(
+            metrics()
+                .hook_errors
+                .with_label_values(&["failing", "on_match_stored"])
+                .get(),
+            errors_before + 2
+        );
+    }
+}
@@ -1,7 +1,21 @@
+mod budget;
 mod engine;
+mod failures;
+mod hooks;
 mod queue;
+mod reprocess;
+mod retention;
+mod sampling;
+mod seeding;
 mod worker;
 
-pub use engine::CrawlerEngine;
+pub use budget::{RunProgress, RunSummary, StopReason};
+pub use engine::{CrawlerEngine, CrawlerStatus, DatabaseStats, QueueSizes, Readiness};
+pub use failures::TaskFailure;
+pub use hooks::{HookRow, HookedMatch, MatchHook};
 pub use queue::SummonerQueue;
+pub use reprocess::{reprocess_archive, ReprocessSummary};
+pub use retention::{apply_retention, RetentionSummary};
+pub use sampling::{patch_from_game_version, StratumProgress, StratumTracker};
+pub use seeding::{parse_seed_list, SeedPlayer};
 pub use worker::CrawlerWorker;
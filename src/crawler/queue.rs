@@ -1,5 +1,8 @@
+use crate::metrics::metrics;
 use crate::models::database::{SummonerPriority, SummonerTask};
-use std::collections::VecDeque;
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
 use tokio::sync::RwLock;
 
 #[derive(Debug)]
@@ -7,6 +10,60 @@ pub struct SummonerQueue {
     high_priority: RwLock<VecDeque<SummonerTask>>,
     medium_priority: RwLock<VecDeque<SummonerTask>>,
     low_priority: RwLock<VecDeque<SummonerTask>>,
+    /// Tasks scheduled for a later retry, moved to their priority queue once due
+    delayed: RwLock<BinaryHeap<DelayedTask>>,
+}
+
+/// Heap entry ordered so the task with the earliest `not_before` is on top
+#[derive(Debug)]
+struct DelayedTask(SummonerTask);
+
+impl DelayedTask {
+    fn due_at(&self) -> DateTime<Utc> {
+        self.0.not_before.unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+}
+
+impl PartialEq for DelayedTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.due_at() == other.due_at()
+    }
+}
+
+impl Eq for DelayedTask {}
+
+impl PartialOrd for DelayedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DelayedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.due_at().cmp(&self.due_at())
+    }
+}
+
+fn is_due(task: &SummonerTask, now: DateTime<Utc>) -> bool {
+    task.not_before.is_none_or(|not_before| not_before <= now)
+}
+
+/// Move the queue depth gauge for the queue `task` is entering (positive `delta`) or
+/// leaving; delayed tasks are counted apart from their priority
+fn track_depth(task: &SummonerTask, delayed: bool, delta: i64) {
+    let queue = if delayed {
+        "delayed"
+    } else {
+        match task.priority {
+            SummonerPriority::High => "high",
+            SummonerPriority::Medium => "medium",
+            SummonerPriority::Low => "low",
+        }
+    };
+    metrics()
+        .queue_depth
+        .with_label_values(&[queue, &task.region])
+        .add(delta);
 }
 
 impl SummonerQueue {
@@ -15,10 +72,22 @@ impl SummonerQueue {
             high_priority: RwLock::new(VecDeque::new()),
             medium_priority: RwLock::new(VecDeque::new()),
             low_priority: RwLock::new(VecDeque::new()),
+            delayed: RwLock::new(BinaryHeap::new()),
         }
     }
 
+    /// Queue a task; tasks with a `not_before` in the future are held back until due
     pub async fn push(&self, task: SummonerTask) {
+        if !is_due(&task, Utc::now()) {
+            track_depth(&task, true, 1);
+            self.delayed.write().await.push(DelayedTask(task));
+            return;
+        }
+        self.push_ready(task).await;
+    }
+
+    async fn push_ready(&self, task: SummonerTask) {
+        track_depth(&task, false, 1);
         match task.priority {
             SummonerPriority::High => {
                 let mut queue = self.high_priority.write().await;
@@ -39,8 +108,16 @@ impl SummonerQueue {
         let mut high_tasks = Vec::new();
         let mut medium_tasks = Vec::new();
         let mut low_tasks = Vec::new();
+        let mut delayed_tasks = Vec::new();
+        let now = Utc::now();
 
         for task in tasks {
+            let delayed = !is_due(&task, now);
+            track_depth(&task, delayed, 1);
+            if delayed {
+                delayed_tasks.push(DelayedTask(task));
+                continue;
+            }
             match task.priority {
                 SummonerPriority::High => high_tasks.push(task),
                 SummonerPriority::Medium => medium_tasks.push(task),
@@ -68,9 +145,43 @@ impl SummonerQueue {
                 queue.push_back(task);
             }
         }
+
+        if !delayed_tasks.is_empty() {
+            self.delayed.write().await.extend(delayed_tasks);
+        }
+    }
+
+    /// Move delayed tasks whose retry time has come into their priority queue
+    async fn promote_due(&self) {
+        let now = Utc::now();
+        let mut due = Vec::new();
+        {
+            let mut delayed = self.delayed.write().await;
+            while delayed.peek().is_some_and(|task| is_due(&task.0, now)) {
+                if let Some(DelayedTask(task)) = delayed.pop() {
+                    track_depth(&task, true, -1);
+                    due.push(task);
+                }
+            }
+        }
+
+        for task in due {
+            self.push_ready(task).await;
+        }
     }
 
+    /// Next task by priority, or `None` if nothing is due yet
     pub async fn pop(&self) -> Option<SummonerTask> {
+        let task = self.pop_ready().await;
+        if let Some(task) = &task {
+            track_depth(task, false, -1);
+        }
+        task
+    }
+
+    async fn pop_ready(&self) -> Option<SummonerTask> {
+        self.promote_due().await;
+
         // Try high priority first
         {
             let mut queue = self.high_priority.write().await;
@@ -101,23 +212,59 @@ impl SummonerQueue {
         (high_size, medium_size, low_size)
     }
 
+    /// Number of tasks waiting for a scheduled retry
+    pub async fn delayed_size(&self) -> usize {
+        self.delayed.read().await.len()
+    }
+
+    /// When the earliest delayed task becomes due
+    pub async fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.delayed.read().await.peek().map(DelayedTask::due_at)
+    }
+
+    /// Ready and delayed tasks combined
     pub async fn total_size(&self) -> usize {
         let (high, medium, low) = self.size().await;
-        high + medium + low
+        high + medium + low + self.delayed_size().await
     }
 
     pub async fn is_empty(&self) -> bool {
         self.total_size().await == 0
     }
 
+    /// Remove and return every task, ready or delayed, e.g. to persist the queue on shutdown
+    pub async fn take_all(&self) -> Vec<SummonerTask> {
+        let mut tasks = Vec::new();
+        tasks.extend(self.high_priority.write().await.drain(..));
+        tasks.extend(self.medium_priority.write().await.drain(..));
+        tasks.extend(self.low_priority.write().await.drain(..));
+        for task in &tasks {
+            track_depth(task, false, -1);
+        }
+        for DelayedTask(task) in self.delayed.write().await.drain() {
+            track_depth(&task, true, -1);
+            tasks.push(task);
+        }
+        tasks
+    }
+
     pub async fn clear(&self) {
         let mut high = self.high_priority.write().await;
         let mut medium = self.medium_priority.write().await;
         let mut low = self.low_priority.write().await;
+        let mut delayed = self.delayed.write().await;
+
+        for task in high.iter().chain(medium.iter()).chain(low.iter()) {
+            track_depth(task, false, -1);
+        }
+        for DelayedTask(task) in delayed.iter() {
+            track_depth(task, true, -1);
+        }
 
         high.clear();
         medium.clear();
         low.clear();
+        delayed.clear();
     }
 
     pub async fn peek_next(&self) -> Option<SummonerPriority> {
@@ -162,6 +309,8 @@ impl SummonerQueue {
         while let Some(task) = queue_guard.pop_front() {
             if seen.insert(task.puuid.clone()) {
                 new_queue.push_back(task);
+            } else {
+                track_depth(&task, false, -1);
             }
         }
 
@@ -188,6 +337,9 @@ mod tests {
             priority,
             added_at: Utc::now(),
             retries: 0,
+            tier: None,
+            not_before: None,
+            retry_class: None,
         }
     }
 
@@ -254,4 +406,78 @@ mod tests {
         assert_eq!(medium, 1);
         assert_eq!(low, 1);
     }
+
+    #[tokio::test]
+    async fn test_delayed_tasks_held_until_due() {
+        let queue = SummonerQueue::new();
+
+        let mut later = create_test_task("later", SummonerPriority::High);
+        later.not_before = Some(Utc::now() + chrono::Duration::seconds(60));
+        let mut soon = create_test_task("soon", SummonerPriority::High);
+        soon.not_before = Some(Utc::now() + chrono::Duration::seconds(30));
+        queue.push(later).await;
+        queue.push_batch(vec![soon]).await;
+        queue
+            .push(create_test_task("ready", SummonerPriority::Low))
+            .await;
+
+        assert_eq!(queue.delayed_size().await, 2);
+        assert_eq!(queue.total_size().await, 3);
+        let next_due = queue.next_due().await.unwrap();
+        assert!(next_due < Utc::now() + chrono::Duration::seconds(45));
+
+        // The ready low-priority task goes first even though the delayed ones are High
+        assert_eq!(queue.pop().await.unwrap().puuid, "ready");
+        assert!(queue.pop().await.is_none());
+        assert!(!queue.is_empty().await);
+
+        // Once due, a delayed task keeps its original priority
+        let mut overdue = create_test_task("overdue", SummonerPriority::High);
+        overdue.not_before = Some(Utc::now() - chrono::Duration::seconds(1));
+        queue.push(overdue).await;
+        queue.delayed.write().await.push(DelayedTask({
+            let mut task = create_test_task("promoted", SummonerPriority::Medium);
+            task.not_before = Some(Utc::now() - chrono::Duration::seconds(1));
+            task
+        }));
+        assert_eq!(queue.pop().await.unwrap().puuid, "overdue");
+        assert_eq!(queue.pop().await.unwrap().puuid, "promoted");
+        assert_eq!(queue.delayed_size().await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_depth_gauge_follows_tasks() {
+        let depth = |queue: &str| {
+            metrics()
+                .queue_depth
+                .with_label_values(&[queue, "depth-test"])
+                .get()
+        };
+        let task = |puuid: &str, priority| SummonerTask {
+            region: "depth-test".to_string(),
+            ..create_test_task(puuid, priority)
+        };
+        let queue = SummonerQueue::new();
+
+        queue.push(task("a", SummonerPriority::High)).await;
+        queue
+            .push_batch(vec![
+                task("b", SummonerPriority::Low),
+                task("b", SummonerPriority::Low),
+                SummonerTask {
+                    not_before: Some(Utc::now() + chrono::Duration::seconds(60)),
+                    ..task("c", SummonerPriority::High)
+                },
+            ])
+            .await;
+        assert_eq!((depth("high"), depth("low"), depth("delayed")), (1, 2, 1));
+
+        queue.remove_duplicates().await;
+        assert_eq!(depth("low"), 1);
+        queue.pop().await.unwrap();
+        assert_eq!(depth("high"), 0);
+
+        queue.take_all().await;
+        assert_eq!((depth("high"), depth("low"), depth("delayed")), (0, 0, 0));
+    }
 }
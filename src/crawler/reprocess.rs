@@ -0,0 +1,151 @@
+use super::worker::match_bundle;
+use crate::database::{decode_payload, Storage};
+use crate::models::database::RawPayloadKind;
+use crate::models::match_v5::MatchDto;
+use crate::Result;
+
+/// Archived matches read per batch
+const BATCH_SIZE: i32 = 500;
+
+/// Outcome of rebuilding the normalised tables from the raw archive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReprocessSummary {
+    pub reprocessed: u64,
+    pub failed: u64,
+}
+
+/// Rebuild the normalised rows of archived matches from their stored payloads, without
+/// calling the API. With `match_id` only that match is rebuilt. A payload that no longer
+/// parses is logged and counted, and the remaining matches are still processed.
+pub async fn reprocess_archive(
+    storage: &dyn Storage,
+    match_id: Option<&str>,
+) -> Result<ReprocessSummary> {
+    let mut summary = ReprocessSummary::default();
+    if let Some(match_id) = match_id {
+        reprocess_match(storage, match_id).await?;
+        summary.reprocessed = 1;
+        return Ok(summary);
+    }
+
+    let mut after: Option<String> = None;
+    loop {
+        let match_ids = storage
+            .get_archived_match_ids(after.as_deref(), BATCH_SIZE)
+            .await?;
+        let Some(last) = match_ids.last() else {
+            break;
+        };
+        after = Some(last.clone());
+
+        for match_id in &match_ids {
+            match reprocess_match(storage, match_id).await {
+                Ok(()) => summary.reprocessed += 1,
+                Err(e) => {
+                    log::warn!("Failed to reprocess match {}: {}", match_id, e);
+                    summary.failed += 1;
+                }
+            }
+        }
+        log::info!(
+            "Reprocessed {} archived matches ({} failed)",
+            summary.reprocessed,
+            summary.failed
+        );
+    }
+    Ok(summary)
+}
+
+async fn reprocess_match(storage: &dyn Storage, match_id: &str) -> Result<()> {
+    let payload = storage
+        .get_raw_payload(match_id, RawPayloadKind::Match)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No archived payload for match {}", match_id))?;
+    let match_data: MatchDto = serde_json::from_str(&decode_payload(&payload)?)?;
+
+    // The stratum was attributed at crawl time and is left as stored
+    let bundle = match_bundle(&match_data, &payload.region, payload.fetched_at);
+    storage.store_match_bundle(&bundle).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{encode_payload, Database};
+    use crate::models::database::DbBan;
+    use crate::models::fixtures::{match_dto, match_json};
+    use chrono::Utc;
+
+    fn archived_bundle(match_id: &str, json: &str) -> crate::models::database::DbMatchBundle {
+        let mut bundle = match_bundle(&match_dto(match_id), "na1", Utc::now());
+        bundle.raw_payloads =
+            vec![encode_payload(match_id, RawPayloadKind::Match, "na1", json, 3).unwrap()];
+        bundle
+    }
+
+    fn ban_count(db: &Database, match_id: &str) -> i64 {
+        db.query_row(
+            "SELECT COUNT(*) FROM bans WHERE match_id = ?1",
+            &[&match_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reprocess_rebuilds_rows_from_archive() {
+        let db = Database::new(":memory:").unwrap();
+
+        // Normalised rows that drifted from the payload, e.g. written by an older version
+        let mut bundle = archived_bundle("NA1_1", &match_json("NA1_1"));
+        bundle.bans.truncate(1);
+        bundle.bans.push(DbBan {
+            id: None,
+            match_id: "NA1_1".to_string(),
+            team_id: 200,
+            champion_id: 1,
+            pick_turn: 6,
+        });
+        bundle.bans.push(bundle.bans[1].clone());
+        db.store_match_bundle(&bundle).unwrap();
+        assert_eq!(ban_count(&db, "NA1_1"), 3);
+
+        let summary = reprocess_archive(&db, None).await.unwrap();
+        assert_eq!(
+            summary,
+            ReprocessSummary {
+                reprocessed: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(ban_count(&db, "NA1_1"), 2);
+        assert!(db
+            .get_raw_payload("NA1_1", RawPayloadKind::Match)
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reprocess_skips_unreadable_payloads() {
+        let db = Database::new(":memory:").unwrap();
+        db.store_match_bundle(&archived_bundle("NA1_1", &match_json("NA1_1")))
+            .unwrap();
+        db.store_match_bundle(&archived_bundle("NA1_2", "{\"truncated\":"))
+            .unwrap();
+
+        let summary = reprocess_archive(&db, None).await.unwrap();
+        assert_eq!(summary.reprocessed, 1);
+        assert_eq!(summary.failed, 1);
+
+        // A single match reports its error
+        assert!(reprocess_archive(&db, Some("NA1_2")).await.is_err());
+        assert!(reprocess_archive(&db, Some("NA1_404")).await.is_err());
+        assert_eq!(
+            reprocess_archive(&db, Some("NA1_1")).await.unwrap(),
+            ReprocessSummary {
+                reprocessed: 1,
+                failed: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use super::sampling::{parse_patch, patch_from_game_version};
+use crate::config::RetentionConfig;
+use crate::database::Storage;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::BTreeSet;
+
+/// Rows or matches handled per transaction, so a pass never holds the writer for long
+const BATCH_SIZE: i64 = 1000;
+
+/// What one retention pass removed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionSummary {
+    pub api_calls_rolled_up: u64,
+    pub matches_pruned: u64,
+    /// Patches whose matches were pruned
+    pub pruned_patches: Vec<String>,
+}
+
+/// Apply the retention rules once: roll up old API calls, prune matches from patches
+/// older than the newest `keep_patches`, then reclaim the freed space
+pub async fn apply_retention(
+    storage: &dyn Storage,
+    config: &RetentionConfig,
+    now: DateTime<Utc>,
+) -> crate::Result<RetentionSummary> {
+    let mut summary = RetentionSummary::default();
+
+    if let Some(days) = config.api_calls_days {
+        let cutoff = now - TimeDelta::days(days.into());
+        loop {
+            let rolled_up = storage.rollup_api_calls(cutoff, BATCH_SIZE).await?;
+            summary.api_calls_rolled_up += rolled_up;
+            if rolled_up < BATCH_SIZE as u64 {
+                break;
+            }
+        }
+    }
+
+    if let Some(keep) = config.keep_patches {
+        let game_versions = storage.get_game_versions().await?;
+        let (versions, patches) = versions_to_prune(&game_versions, keep as usize);
+        summary.pruned_patches = patches;
+        loop {
+            let pruned = storage
+                .prune_matches(&versions, config.keep_raw_payloads, BATCH_SIZE)
+                .await?;
+            summary.matches_pruned += pruned;
+            if pruned < BATCH_SIZE as u64 {
+                break;
+            }
+        }
+    }
+
+    if summary.api_calls_rolled_up > 0 || summary.matches_pruned > 0 {
+        storage.reclaim_space().await?;
+    }
+    Ok(summary)
+}
+
+/// Game versions, and their patches, that fall outside the newest `keep` patches.
+/// Versions whose patch cannot be parsed are never pruned.
+fn versions_to_prune(game_versions: &[String], keep: usize) -> (Vec<String>, Vec<String>) {
+    let patches: BTreeSet<(u32, u32)> = game_versions
+        .iter()
+        .filter_map(|v| parse_patch(&patch_from_game_version(v)))
+        .collect();
+    // Fewer patches than `keep` means nothing is old enough to prune
+    let Some(&oldest_kept) = keep
+        .checked_sub(1)
+        .and_then(|i| patches.iter().rev().nth(i))
+    else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let versions: Vec<String> = game_versions
+        .iter()
+        .filter(|v| parse_patch(&patch_from_game_version(v)).is_some_and(|p| p < oldest_kept))
+        .cloned()
+        .collect();
+    let pruned_patches = patches
+        .iter()
+        .filter(|p| **p < oldest_kept)
+        .map(|(major, minor)| format!("{}.{}", major, minor))
+        .collect();
+    (versions, pruned_patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::models::database::{DbApiCall, DbMatchBundle};
+    use crate::models::fixtures;
+
+    fn versions(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_versions_to_prune_keeps_newest_patches() {
+        let game_versions = versions(&[
+            "14.1.555.1",
+            "14.1.556.2",
+            "13.24.1.1",
+            "14.10.1.1",
+            "14.2.1.1",
+            "garbage",
+        ]);
+
+        let (pruned, patches) = versions_to_prune(&game_versions, 2);
+        assert_eq!(pruned, versions(&["14.1.555.1", "14.1.556.2", "13.24.1.1"]));
+        assert_eq!(patches, versions(&["13.24", "14.1"]));
+
+        assert_eq!(
+            versions_to_prune(&game_versions, 10).0,
+            Vec::<String>::new()
+        );
+        assert_eq!(versions_to_prune(&[], 1).0, Vec::<String>::new());
+    }
+
+    fn bundle(match_id: &str, game_version: &str) -> DbMatchBundle {
+        let mut bundle = fixtures::bundle(match_id);
+        bundle.match_data.game_version = game_version.to_string();
+        bundle
+    }
+
+    #[tokio::test]
+    async fn test_apply_retention() {
+        let db = Database::new(":memory:").unwrap();
+        let now = Utc::now();
+        for days_ago in [40, 35, 1] {
+            db.log_api_call(&DbApiCall {
+                id: None,
+                endpoint: "/lol/match/v5/matches/NA1_1".to_string(),
+                region: "na1".to_string(),
+                timestamp: now - TimeDelta::days(days_ago),
+                response_code: 200,
+                rate_limit_remaining: None,
+            })
+            .unwrap();
+        }
+        db.store_match_bundle(&bundle("NA1_OLD", "13.24.1.1"))
+            .unwrap();
+        db.store_match_bundle(&bundle("NA1_NEW", "14.1.1.1"))
+            .unwrap();
+
+        // Nothing configured, nothing removed
+        let summary = apply_retention(&db, &RetentionConfig::default(), now)
+            .await
+            .unwrap();
+        assert_eq!(summary, RetentionSummary::default());
+
+        let config = RetentionConfig {
+            api_calls_days: Some(30),
+            keep_patches: Some(1),
+            ..Default::default()
+        };
+        let summary = apply_retention(&db, &config, now).await.unwrap();
+        assert_eq!(summary.api_calls_rolled_up, 2);
+        assert_eq!(summary.matches_pruned, 1);
+        assert_eq!(summary.pruned_patches, versions(&["13.24"]));
+
+        assert!(!db.match_exists("NA1_OLD").unwrap());
+        assert!(db.match_exists("NA1_NEW").unwrap());
+        assert_eq!(db.get_participants_count().unwrap(), 10);
+        assert_eq!(db.get_api_call_rollups().unwrap()[0].calls, 1);
+
+        // A second pass finds nothing left to do
+        let summary = apply_retention(&db, &config, now).await.unwrap();
+        assert_eq!(summary.matches_pruned, 0);
+        assert_eq!(summary.api_calls_rolled_up, 0);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::config::SamplingConfig;
+use crate::database::Storage;
+use crate::models::database::{SummonerPriority, Tier};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// (region, tier, patch) - `None` tier means the player's rank could not be determined
+type StratumKey = (String, Option<Tier>, String);
+
+/// Tracks how many matches have been stored per (region, tier, patch) stratum and turns
+/// that into crawl priorities: strata far below their target are crawled first, saturated
+/// strata are only crawled when nothing else is queued.
+#[derive(Debug)]
+pub struct StratumTracker {
+    config: SamplingConfig,
+    counts: RwLock<HashMap<StratumKey, u64>>,
+    current_patch: RwLock<Option<String>>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StratumProgress {
+    pub region: String,
+    pub tier: Tier,
+    pub patch: String,
+    pub matches: u64,
+    pub target: u64,
+}
+
+impl StratumTracker {
+    pub fn new(config: SamplingConfig) -> Self {
+        Self {
+            config,
+            counts: RwLock::new(HashMap::new()),
+            current_patch: RwLock::new(None),
+        }
+    }
+
+    /// Load existing stratum counts from the database
+    pub async fn load(&self, database: &dyn Storage) -> crate::Result<()> {
+        let rows = database.get_stratum_counts().await?;
+        let mut counts = self.counts.write().await;
+        let mut current_patch = self.current_patch.write().await;
+
+        for (region, tier, patch, count) in rows {
+            if is_newer_patch(&patch, current_patch.as_deref()) {
+                *current_patch = Some(patch.clone());
+            }
+            counts.insert((region, tier, patch), count as u64);
+        }
+
+        log::info!(
+            "Loaded {} sampling strata (current patch: {})",
+            counts.len(),
+            current_patch.as_deref().unwrap_or("unknown")
+        );
+        Ok(())
+    }
+
+    pub fn target_for(&self, tier: Tier) -> u64 {
+        self.config
+            .tier_targets
+            .get(tier.as_str())
+            .copied()
+            .unwrap_or(self.config.matches_per_stratum)
+    }
+
+    pub async fn record_match(&self, region: &str, tier: Option<Tier>, patch: &str) {
+        {
+            let mut current_patch = self.current_patch.write().await;
+            if is_newer_patch(patch, current_patch.as_deref()) {
+                log::info!("Sampling now targets patch {}", patch);
+                *current_patch = Some(patch.to_string());
+            }
+        }
+
+        let mut counts = self.counts.write().await;
+        *counts
+            .entry((region.to_string(), tier, patch.to_string()))
+            .or_insert(0) += 1;
+    }
+
+    /// Fraction of the target reached on the current patch (0.0 = empty, >= 1.0 = saturated)
+    pub async fn fill_ratio(&self, region: &str, tier: Tier) -> f64 {
+        let target = self.target_for(tier);
+        if target == 0 {
+            return f64::INFINITY;
+        }
+
+        let count = match self.current_patch.read().await.as_ref() {
+            Some(patch) => self
+                .counts
+                .read()
+                .await
+                .get(&(region.to_string(), Some(tier), patch.clone()))
+                .copied()
+                .unwrap_or(0),
+            None => 0,
+        };
+
+        count as f64 / target as f64
+    }
+
+    pub async fn is_saturated(&self, region: &str, tier: Option<Tier>) -> bool {
+        match tier {
+            Some(tier) => self.fill_ratio(region, tier).await >= 1.0,
+            None => false,
+        }
+    }
+
+    /// Queue priority for a player of the given tier. Players of unknown tier go to the
+    /// back of the queue since they can't contribute to a stratum target.
+    pub async fn priority_for(&self, region: &str, tier: Option<Tier>) -> SummonerPriority {
+        let Some(tier) = tier else {
+            return SummonerPriority::Low;
+        };
+
+        let ratio = self.fill_ratio(region, tier).await;
+        if ratio < 0.5 {
+            SummonerPriority::High
+        } else if ratio < 1.0 {
+            SummonerPriority::Medium
+        } else {
+            SummonerPriority::Low
+        }
+    }
+
+    /// Tiers below their target in a region, most under-represented first
+    pub async fn under_represented_tiers(&self, region: &str) -> Vec<Tier> {
+        let mut tiers = Vec::new();
+        for tier in Tier::ALL {
+            let ratio = self.fill_ratio(region, tier).await;
+            if ratio < 1.0 {
+                tiers.push((tier, ratio));
+            }
+        }
+
+        tiers.sort_by(|a, b| a.1.total_cmp(&b.1));
+        tiers.into_iter().map(|(tier, _)| tier).collect()
+    }
+
+    /// Progress of every tier on the current patch for the given regions
+    pub async fn progress(&self, regions: &[String]) -> Vec<StratumProgress> {
+        let Some(patch) = self.current_patch.read().await.clone() else {
+            return Vec::new();
+        };
+
+        let counts = self.counts.read().await;
+        let mut progress = Vec::new();
+        for region in regions {
+            for tier in Tier::ALL {
+                progress.push(StratumProgress {
+                    region: region.clone(),
+                    tier,
+                    patch: patch.clone(),
+                    matches: counts
+                        .get(&(region.clone(), Some(tier), patch.clone()))
+                        .copied()
+                        .unwrap_or(0),
+                    target: self.target_for(tier),
+                });
+            }
+        }
+        progress
+    }
+}
+
+/// Reduce a game version such as "14.3.562.1234" to its patch ("14.3")
+pub fn patch_from_game_version(game_version: &str) -> String {
+    game_version
+        .split('.')
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+pub(crate) fn parse_patch(patch: &str) -> Option<(u32, u32)> {
+    let (major, minor) = patch.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+fn is_newer_patch(patch: &str, current: Option<&str>) -> bool {
+    match (parse_patch(patch), current.and_then(parse_patch)) {
+        (Some(candidate), Some(current)) => candidate > current,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> SamplingConfig {
+        let mut config = SamplingConfig {
+            enabled: true,
+            matches_per_stratum: 4,
+            ..Default::default()
+        };
+        config.tier_targets.insert("IRON".to_string(), 2);
+        config
+    }
+
+    #[test]
+    fn test_patch_from_game_version() {
+        assert_eq!(patch_from_game_version("14.3.562.1234"), "14.3");
+        assert_eq!(patch_from_game_version("13.24.1"), "13.24");
+        assert!(is_newer_patch("14.10", Some("14.9")));
+        assert!(!is_newer_patch("13.24", Some("14.1")));
+        assert!(is_newer_patch("14.1", None));
+    }
+
+    #[tokio::test]
+    async fn test_priority_follows_fill_ratio() {
+        let tracker = StratumTracker::new(test_config());
+
+        // Empty strata are the most wanted
+        assert_eq!(
+            tracker.priority_for("na1", Some(Tier::Gold)).await,
+            SummonerPriority::High
+        );
+        assert_eq!(
+            tracker.priority_for("na1", None).await,
+            SummonerPriority::Low
+        );
+
+        for _ in 0..2 {
+            tracker.record_match("na1", Some(Tier::Gold), "14.3").await;
+        }
+        assert_eq!(
+            tracker.priority_for("na1", Some(Tier::Gold)).await,
+            SummonerPriority::Medium
+        );
+
+        for _ in 0..2 {
+            tracker.record_match("na1", Some(Tier::Gold), "14.3").await;
+        }
+        assert_eq!(
+            tracker.priority_for("na1", Some(Tier::Gold)).await,
+            SummonerPriority::Low
+        );
+        assert!(tracker.is_saturated("na1", Some(Tier::Gold)).await);
+
+        // Other regions are separate strata
+        assert!(!tracker.is_saturated("euw1", Some(Tier::Gold)).await);
+    }
+
+    #[tokio::test]
+    async fn test_new_patch_resets_targets() {
+        let tracker = StratumTracker::new(test_config());
+
+        for _ in 0..2 {
+            tracker.record_match("na1", Some(Tier::Iron), "14.3").await;
+        }
+        assert!(tracker.is_saturated("na1", Some(Tier::Iron)).await);
+
+        // A match from a newer patch makes it the current one
+        tracker.record_match("na1", Some(Tier::Gold), "14.4").await;
+        assert!(!tracker.is_saturated("na1", Some(Tier::Iron)).await);
+    }
+
+    #[tokio::test]
+    async fn test_under_represented_tiers_ordering() {
+        let tracker = StratumTracker::new(test_config());
+
+        for _ in 0..3 {
+            tracker.record_match("na1", Some(Tier::Gold), "14.3").await;
+        }
+        tracker
+            .record_match("na1", Some(Tier::Silver), "14.3")
+            .await;
+        for _ in 0..2 {
+            tracker.record_match("na1", Some(Tier::Iron), "14.3").await;
+        }
+
+        let tiers = tracker.under_represented_tiers("na1").await;
+        assert!(!tiers.contains(&Tier::Iron)); // saturated at its override target
+        assert_eq!(tiers.last(), Some(&Tier::Gold));
+        assert!(
+            tiers.iter().position(|t| *t == Tier::Silver)
+                > tiers.iter().position(|t| *t == Tier::Diamond)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_from_database() {
+        let database = crate::database::Database::new(":memory:").unwrap();
+        for i in 0..4 {
+            database
+                .insert_match_stratum(&crate::models::database::DbMatchStratum {
+                    match_id: format!("NA1_{}", i),
+                    region: "na1".to_string(),
+                    tier: Some(Tier::Diamond),
+                    patch: "14.3".to_string(),
+                })
+                .unwrap();
+        }
+
+        let tracker = StratumTracker::new(test_config());
+        tracker.load(&database).await.unwrap();
+        assert!(tracker.is_saturated("na1", Some(Tier::Diamond)).await);
+
+        let progress = tracker.progress(&["na1".to_string()]).await;
+        let diamond = progress.iter().find(|p| p.tier == Tier::Diamond).unwrap();
+        assert_eq!(diamond.matches, 4);
+        assert_eq!(diamond.target, 4);
+    }
+}
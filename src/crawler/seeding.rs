@@ -0,0 +1,125 @@
+use crate::api::RiotApiClient;
+use std::str::FromStr;
+
+/// A player to seed the crawler with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedPlayer {
+    Puuid(String),
+    /// `gameName#tagLine`, resolved to a PUUID through the account API
+    RiotId {
+        game_name: String,
+        tag_line: String,
+    },
+}
+
+impl FromStr for SeedPlayer {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let value = value.trim();
+        match value.rsplit_once('#') {
+            Some((game_name, tag_line)) if !game_name.is_empty() && !tag_line.is_empty() => {
+                Ok(SeedPlayer::RiotId {
+                    game_name: game_name.to_string(),
+                    tag_line: tag_line.to_string(),
+                })
+            }
+            Some(_) => Err(format!("{:?} is not a Riot ID like Name#TAG", value)),
+            None if value.is_empty() => Err("empty player".to_string()),
+            None => Ok(SeedPlayer::Puuid(value.to_string())),
+        }
+    }
+}
+
+impl SeedPlayer {
+    /// The player's PUUID, looking Riot IDs up in `region`'s account cluster
+    pub async fn resolve(&self, api_client: &RiotApiClient, region: &str) -> crate::Result<String> {
+        match self {
+            SeedPlayer::Puuid(puuid) => Ok(puuid.clone()),
+            SeedPlayer::RiotId {
+                game_name,
+                tag_line,
+            } => Ok(api_client
+                .get_account_by_riot_id(region, game_name, tag_line)
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("Could not look up {}#{}: {}", game_name, tag_line, e)
+                })?
+                .puuid),
+        }
+    }
+}
+
+/// Players listed one per line as `<Riot ID or PUUID>[,<region>]`. Blank lines and
+/// lines starting with `#` are skipped; lines without a region use `default_region`.
+pub fn parse_seed_list(
+    contents: &str,
+    default_region: Option<&str>,
+) -> crate::Result<Vec<(SeedPlayer, String)>> {
+    let mut players = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (player, region) = match line.split_once(',') {
+            Some((player, region)) => (player, Some(region.trim())),
+            None => (line, default_region),
+        };
+        let player = player
+            .parse()
+            .map_err(|e| anyhow::anyhow!("line {}: {}", number + 1, e))?;
+        let Some(region) = region.filter(|r| !r.is_empty()) else {
+            anyhow::bail!(
+                "line {}: no region given and no --region default",
+                number + 1
+            );
+        };
+        players.push((player, region.to_lowercase()));
+    }
+    Ok(players)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_seed_player() {
+        assert_eq!(
+            "Faker#KR1".parse::<SeedPlayer>().unwrap(),
+            SeedPlayer::RiotId {
+                game_name: "Faker".to_string(),
+                tag_line: "KR1".to_string()
+            }
+        );
+        assert_eq!(
+            " abc-puuid ".parse::<SeedPlayer>().unwrap(),
+            SeedPlayer::Puuid("abc-puuid".to_string())
+        );
+        assert!("Name#".parse::<SeedPlayer>().is_err());
+        assert!("".parse::<SeedPlayer>().is_err());
+    }
+
+    #[test]
+    fn test_parse_seed_list() {
+        let contents = "# players to follow\n\
+                        Faker#KR1,KR\n\
+                        \n\
+                        some-puuid\n";
+
+        let players = parse_seed_list(contents, Some("euw1")).unwrap();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[0].1, "kr");
+        assert_eq!(
+            players[1],
+            (
+                SeedPlayer::Puuid("some-puuid".to_string()),
+                "euw1".to_string()
+            )
+        );
+
+        let error = parse_seed_list(contents, None).unwrap_err();
+        assert!(error.to_string().contains("line 4"));
+    }
+}
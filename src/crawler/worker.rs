@@ -1,44 +1,173 @@
+use super::failures::TaskFailure;
+use super::hooks::{HookedMatch, MatchHook, MatchHooks};
+use super::sampling::{patch_from_game_version, StratumTracker};
 use crate::api::{queues, RiotApiClient};
-use crate::database::Database;
+use crate::config::{ArchiveConfig, Config};
+use crate::database::{encode_payload, Storage};
+use crate::events::{Event, EventBus, EventKind};
+use crate::metrics::metrics;
 use crate::models::database::{
-    DbBan, DbMatch, DbParticipant, DbSummoner, DbTeam, SummonerPriority, SummonerTask,
+    DbBan, DbMatch, DbMatchBundle, DbMatchStratum, DbParticipant, DbParticipantChallenge,
+    DbParticipantPerk, DbRawPayload, DbSummoner, DbSummonerRank, DbTeam, DbVetoedMatch,
+    FailedTaskType, RawPayloadKind, SummonerPriority, SummonerTask, Tier,
 };
-use chrono::Utc;
-use std::collections::HashSet;
+use crate::models::match_v5::{ChallengesDto, MatchDto, ParticipantDto, PerksDto};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What became of a crawled match
+enum MatchOutcome {
+    /// Stored, with the (puuid, name) pairs of its participants
+    Stored(HashSet<(String, String)>),
+    /// From a queue that is not crawled
+    OtherQueue,
+    /// Declined by the named match hook
+    Vetoed(String),
+}
+
+impl MatchOutcome {
+    /// Participants to crawl next; none unless the match was stored
+    fn into_discovered(self) -> HashSet<(String, String)> {
+        match self {
+            MatchOutcome::Stored(discovered) => discovered,
+            MatchOutcome::OtherQueue | MatchOutcome::Vetoed(_) => HashSet::new(),
+        }
+    }
+}
+
+struct QueueFilter {
+    queue_id: i32,
+    /// Regions storing a different queue than `queue_id`
+    region_queue_ids: HashMap<String, i32>,
+}
 
 pub struct CrawlerWorker {
     api_client: RiotApiClient,
-    database: Database,
+    database: Arc<dyn Storage>,
+    stratum_tracker: Option<Arc<StratumTracker>>,
+    archive: Option<ArchiveConfig>,
+    hooks: MatchHooks,
+    events: Option<Arc<EventBus>>,
+    /// Queues whose matches are stored; others are skipped
+    queue_filter: std::sync::RwLock<QueueFilter>,
+    matches_stored: AtomicU64,
+    matches_stored_by_region: std::sync::Mutex<HashMap<String, u64>>,
 }
 
 impl CrawlerWorker {
-    pub fn new(api_client: RiotApiClient, database: Database) -> Self {
+    pub fn new(api_client: RiotApiClient, database: Arc<dyn Storage>) -> Self {
         Self {
             api_client,
             database,
+            stratum_tracker: None,
+            archive: None,
+            hooks: MatchHooks::default(),
+            events: None,
+            queue_filter: std::sync::RwLock::new(QueueFilter {
+                queue_id: queues::RANKED_SOLO_QUEUE_ID,
+                region_queue_ids: HashMap::new(),
+            }),
+            matches_stored: AtomicU64::new(0),
+            matches_stored_by_region: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
+    /// Ranked matches stored by this worker since it was created
+    pub fn matches_stored(&self) -> u64 {
+        self.matches_stored.load(Ordering::Relaxed)
+    }
+
+    /// Ranked matches stored in `region` by this worker since it was created
+    pub fn matches_stored_in(&self, region: &str) -> u64 {
+        self.matches_stored_by_region
+            .lock()
+            .unwrap()
+            .get(region)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Enable rank-stratified sampling: look up each player's rank, attribute their
+    /// matches to a stratum and prioritise discovered players by stratum deficit
+    pub fn with_stratum_tracker(mut self, tracker: Arc<StratumTracker>) -> Self {
+        self.stratum_tracker = Some(tracker);
+        self
+    }
+
+    /// Keep the original match (and optionally timeline) responses in the raw archive
+    pub fn with_archive(mut self, archive: ArchiveConfig) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Report storage failures on `events`
+    pub fn with_events(mut self, events: Arc<EventBus>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Run `hook` on every crawled match
+    pub fn with_match_hook(mut self, hook: Arc<dyn MatchHook>) -> Self {
+        self.hooks.add(hook);
+        self
+    }
+
+    /// Abandon a hook call that runs longer than `timeout`
+    pub fn with_hook_timeout(mut self, timeout: Duration) -> Self {
+        self.hooks.set_timeout(timeout);
+        self
+    }
+
+    /// Store matches from `queue_id` instead of ranked solo/duo
+    pub fn with_queue_id(mut self, queue_id: i32) -> Self {
+        self.queue_filter.get_mut().unwrap().queue_id = queue_id;
+        self
+    }
+
+    /// Replace the stored queues while the worker runs, as configured by
+    /// `crawler.queue_id` and the `[region.<id>]` sections
+    pub fn set_queue_ids(&self, config: &Config) {
+        *self.queue_filter.write().unwrap() = QueueFilter {
+            queue_id: config.crawler.queue_id,
+            region_queue_ids: config
+                .region_overrides
+                .iter()
+                .filter_map(|(region, overrides)| Some((region.clone(), overrides.queue_id?)))
+                .collect(),
+        };
+    }
+
+    /// Queue whose matches are stored in `region`
+    fn queue_id_for(&self, region: &str) -> i32 {
+        let filter = self.queue_filter.read().unwrap();
+        filter
+            .region_queue_ids
+            .get(region)
+            .copied()
+            .unwrap_or(filter.queue_id)
+    }
+
     pub async fn process_summoner(&self, task: &SummonerTask) -> crate::Result<Vec<SummonerTask>> {
-        log::info!(
-            "Processing summoner: {} ({}) in region: {}",
-            task.summoner_name,
-            task.puuid,
-            task.region
-        );
+        tracing::info!(summoner_name = %task.summoner_name, "Processing summoner");
 
         // First, fetch summoner details and store them
         match self
             .fetch_and_store_summoner(&task.puuid, &task.region)
             .await
         {
-            Ok(_) => log::debug!("Summoner {} stored successfully", task.puuid),
+            Ok(_) => tracing::debug!("Summoner stored"),
             Err(e) => {
-                log::warn!("Failed to fetch summoner {}: {}", task.puuid, e);
+                count_error(&e);
+                tracing::warn!(error = %e, "Failed to fetch summoner");
                 // Continue with match history even if summoner fetch fails
             }
         }
 
+        let tier = self.resolve_tier(task).await;
+
         // Fetch match history
         let match_ids = match self
             .api_client
@@ -52,65 +181,202 @@ impl CrawlerWorker {
         {
             Ok(matches) => matches,
             Err(e) => {
-                log::error!("Failed to fetch match list for {}: {}", task.puuid, e);
-                return Ok(Vec::new());
+                tracing::error!(error = %e, "Failed to fetch match list");
+                let e = e.into();
+                count_error(&e);
+                return Err(e);
             }
         };
 
-        log::debug!(
-            "Found {} matches for summoner {}",
-            match_ids.len(),
-            task.puuid
-        );
+        tracing::debug!(matches = match_ids.len(), "Fetched match list");
 
         let mut new_summoners = HashSet::new();
 
         // Process each match
         for match_id in match_ids {
             // Skip if match already exists
-            if self.database.match_exists(&match_id)? {
-                log::debug!("Match {} already exists, skipping", match_id);
+            if self.database.match_exists(&match_id).await? {
+                tracing::debug!(match_id = %match_id, "Match already stored, skipping");
+                continue;
+            }
+            if self.database.match_vetoed(&match_id).await? {
+                tracing::debug!(match_id = %match_id, "Match vetoed by a hook, skipping");
+                continue;
+            }
+            if self.database.match_pruned(&match_id).await? {
+                tracing::debug!(match_id = %match_id, "Match pruned by retention, skipping");
                 continue;
             }
 
-            match self.fetch_and_store_match(&match_id, &task.region).await {
-                Ok(discovered_summoners) => {
-                    new_summoners.extend(discovered_summoners);
-                    log::debug!("Successfully processed match {}", match_id);
+            match self
+                .fetch_and_store_match(&match_id, &task.region, tier)
+                .await
+            {
+                Ok(outcome) => {
+                    new_summoners.extend(outcome.into_discovered());
+                    tracing::debug!(match_id = %match_id, "Processed match");
+                    if let Err(e) = self
+                        .database
+                        .resolve_failed_task(FailedTaskType::Match, &match_id)
+                        .await
+                    {
+                        tracing::warn!(match_id = %match_id, error = %e, "Failed to clear dead letter");
+                    }
                 }
                 Err(e) => {
-                    log::warn!("Failed to process match {}: {}", match_id, e);
+                    tracing::warn!(match_id = %match_id, error = %e, "Failed to process match");
+                    self.record_failed_match(&match_id, &task.region, &e).await;
                 }
             }
         }
 
-        // Convert discovered summoners to tasks
-        let new_tasks: Vec<SummonerTask> = new_summoners
-            .into_iter()
-            .filter(|(puuid, _)| {
-                // Filter out summoners we already have
-                match self.database.summoner_exists(puuid) {
-                    Ok(exists) => !exists,
-                    Err(_) => true, // Include if we can't check
-                }
-            })
-            .map(|(puuid, summoner_name)| SummonerTask {
+        // New discoveries start as low priority, unless sampling wants more of this
+        // player's stratum: players in a ranked match are close in rank. They are queued
+        // without a tier, since their own rank is looked up when they are processed.
+        let priority = match &self.stratum_tracker {
+            Some(tracker) => tracker.priority_for(&task.region, tier).await,
+            None => SummonerPriority::Low,
+        };
+
+        let new_tasks = self
+            .discovered_to_tasks(new_summoners, &task.region, priority)
+            .await;
+
+        tracing::info!(discovered = new_tasks.len(), "Processed summoner");
+
+        Ok(new_tasks)
+    }
+
+    /// Retry a match from the dead-letter queue on its own, returning tasks for any
+    /// newly discovered summoners. Failures go back into the dead-letter queue.
+    pub async fn retry_failed_match(
+        &self,
+        match_id: &str,
+        region: &str,
+    ) -> crate::Result<Vec<SummonerTask>> {
+        match self.fetch_and_store_match(match_id, region, None).await {
+            Ok(outcome) => {
+                self.database
+                    .resolve_failed_task(FailedTaskType::Match, match_id)
+                    .await?;
+                Ok(self
+                    .discovered_to_tasks(outcome.into_discovered(), region, SummonerPriority::Low)
+                    .await)
+            }
+            Err(e) => {
+                self.record_failed_match(match_id, region, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Download a stored match again and replace its rows, e.g. after `verify` found it
+    /// broken. Fails when the match is gone from the API or no longer qualifies for
+    /// storage, either from another queue or vetoed by a match hook.
+    pub async fn refetch_match(&self, match_id: &str, region: &str) -> crate::Result<()> {
+        match self.fetch_and_store_match(match_id, region, None).await? {
+            MatchOutcome::Stored(_) => Ok(()),
+            MatchOutcome::OtherQueue => anyhow::bail!(
+                "Match {} is not from queue {}",
+                match_id,
+                self.queue_id_for(region)
+            ),
+            MatchOutcome::Vetoed(hook) => {
+                anyhow::bail!("Match {} was vetoed by match hook '{}'", match_id, hook)
+            }
+        }
+    }
+
+    /// Convert discovered (puuid, name) pairs to tasks, skipping summoners we already have
+    async fn discovered_to_tasks(
+        &self,
+        discovered_summoners: HashSet<(String, String)>,
+        region: &str,
+        priority: SummonerPriority,
+    ) -> Vec<SummonerTask> {
+        let mut tasks = Vec::new();
+        for (puuid, summoner_name) in discovered_summoners {
+            // Filter out summoners we already have, but include them if we can't check
+            if self.database.summoner_exists(&puuid).await.unwrap_or(false) {
+                continue;
+            }
+            tasks.push(SummonerTask {
                 puuid,
                 summoner_name,
-                region: task.region.clone(),
-                priority: SummonerPriority::Low, // New discoveries start as low priority
+                region: region.to_string(),
+                priority: priority.clone(),
                 added_at: Utc::now(),
                 retries: 0,
-            })
-            .collect();
+                tier: None,
+                not_before: None,
+                retry_class: None,
+            });
+        }
+        tasks
+    }
 
-        log::info!(
-            "Discovered {} new summoners from processing {}",
-            new_tasks.len(),
-            task.summoner_name
-        );
+    /// Put a match that could not be fetched or stored into the dead-letter queue. The
+    /// API client has already retried transient errors by the time this is reached.
+    async fn record_failed_match(&self, match_id: &str, region: &str, error: &anyhow::Error) {
+        count_error(error);
+        let failure = TaskFailure::from_error(error);
+        // The summoner's task goes on with its other matches, so the engine never sees
+        // this failure
+        if let (Some(events), "Database") = (&self.events, failure.error_kind.as_str()) {
+            events.emit(
+                Event::new(
+                    EventKind::StorageFailure,
+                    format!("Failed to store match {}: {}", match_id, failure.message),
+                )
+                .in_region(region),
+            );
+        }
+        let failed = failure.into_failed_task(FailedTaskType::Match, match_id, region, None, 1);
+        if let Err(e) = self.database.record_failed_task(&failed).await {
+            tracing::error!(match_id, error = %e, "Failed to record dead letter");
+        }
+    }
 
-        Ok(new_tasks)
+    /// Look up the player's current solo/duo tier when sampling is enabled, falling back
+    /// to the tier the task was queued with
+    async fn resolve_tier(&self, task: &SummonerTask) -> Option<Tier> {
+        self.stratum_tracker.as_ref()?;
+
+        let entries = match self
+            .api_client
+            .get_league_entries_by_puuid(&task.region, &task.puuid)
+            .await
+        {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to fetch rank");
+                return task.tier;
+            }
+        };
+
+        let Some(entry) = entries
+            .into_iter()
+            .find(|e| e.queue_type == queues::RANKED_SOLO_5X5)
+        else {
+            tracing::debug!("Summoner is unranked in solo/duo");
+            return None;
+        };
+
+        let rank = DbSummonerRank {
+            puuid: task.puuid.clone(),
+            queue_type: entry.queue_type.clone(),
+            tier: entry.tier.clone(),
+            rank: entry.rank.clone(),
+            league_points: entry.league_points as i32,
+            wins: entry.wins as i32,
+            losses: entry.losses as i32,
+            updated_at: Utc::now(),
+        };
+        if let Err(e) = self.database.upsert_summoner_rank(&rank).await {
+            tracing::warn!(error = %e, "Failed to store rank");
+        }
+
+        entry.tier.parse().ok().or(task.tier)
     }
 
     async fn fetch_and_store_summoner(&self, puuid: &str, region: &str) -> crate::Result<()> {
@@ -120,9 +386,12 @@ impl CrawlerWorker {
             puuid: summoner.puuid.clone(),
             summoner_id: summoner.id.unwrap_or_else(|| "".to_string()),
             account_id: summoner.account_id.unwrap_or_else(|| "".to_string()),
-            summoner_name: summoner
-                .name
-                .unwrap_or_else(|| format!("Player_{}", &summoner.puuid[..8])),
+            summoner_name: summoner.name.unwrap_or_else(|| {
+                format!(
+                    "Player_{}",
+                    summoner.puuid.get(..8).unwrap_or(&summoner.puuid)
+                )
+            }),
             profile_icon_id: summoner.profile_icon_id as i32,
             summoner_level: summoner.summoner_level as i32,
             region: region.to_string(),
@@ -130,142 +399,563 @@ impl CrawlerWorker {
             updated_at: Utc::now(),
         };
 
-        self.database.insert_summoner(&db_summoner)?;
+        let started = Instant::now();
+        self.database.insert_summoner(&db_summoner).await?;
+        observe_write("insert_summoner", started);
         Ok(())
     }
 
+    #[tracing::instrument(name = "match", skip(self, tier))]
     async fn fetch_and_store_match(
         &self,
         match_id: &str,
         region: &str,
-    ) -> crate::Result<HashSet<(String, String)>> {
-        let match_data = self.api_client.get_match_by_id(region, match_id).await?;
+        tier: Option<Tier>,
+    ) -> crate::Result<MatchOutcome> {
+        let (match_data, raw_match) = if self.archive.is_some() {
+            let (match_data, raw) = self.api_client.get_match_payload(region, match_id).await?;
+            (match_data, Some(raw))
+        } else {
+            (
+                self.api_client.get_match_by_id(region, match_id).await?,
+                None,
+            )
+        };
 
-        // Filter to only ranked solo/duo games
-        if match_data.info.queue_id != queues::RANKED_SOLO_QUEUE_ID {
-            log::debug!(
-                "Skipping non-ranked solo/duo match {} with queue_id {}",
-                match_id,
-                match_data.info.queue_id
+        // Filter to only the crawled queue (ranked solo/duo unless overridden)
+        if match_data.info.queue_id != self.queue_id_for(region) {
+            tracing::debug!(
+                queue_id = match_data.info.queue_id,
+                "Skipping match from another queue"
             );
-            return Ok(HashSet::new());
+            return Ok(MatchOutcome::OtherQueue);
         }
 
-        // Store match metadata
-        let db_match = DbMatch {
+        let mut bundle = match_bundle(&match_data, region, Utc::now());
+        bundle.stratum = self.stratum_tracker.as_ref().map(|_| DbMatchStratum {
             match_id: match_data.metadata.match_id.clone(),
-            game_creation: match_data.info.game_creation,
-            game_duration: match_data.info.game_duration as i32,
-            game_end_timestamp: match_data.info.game_end_timestamp,
-            game_id: match_data.info.game_id,
-            game_mode: match_data.info.game_mode.clone(),
-            game_name: match_data.info.game_name.clone(),
-            game_type: match_data.info.game_type.clone(),
-            game_version: match_data.info.game_version.clone(),
-            map_id: match_data.info.map_id,
-            platform_id: match_data.info.platform_id.clone(),
-            queue_id: match_data.info.queue_id,
-            tournament_code: match_data.info.tournament_code.clone(),
             region: region.to_string(),
-            created_at: Utc::now(),
-        };
+            tier,
+            patch: patch_from_game_version(&match_data.info.game_version),
+        });
+        let mut timeline = None;
+        if let (Some(archive), Some(raw_match)) = (&self.archive, raw_match) {
+            let raw_timeline;
+            (bundle.raw_payloads, raw_timeline) = self
+                .archive_payloads(archive, match_id, region, &raw_match)
+                .await?;
+            if !self.hooks.is_empty() {
+                timeline = raw_timeline.and_then(|raw| serde_json::from_str(&raw).ok());
+            }
+        }
 
-        self.database.insert_match(&db_match)?;
-
-        // Store teams
-        for team in &match_data.info.teams {
-            let db_team = DbTeam {
-                id: None,
-                match_id: match_data.metadata.match_id.clone(),
-                team_id: team.team_id,
-                win: team.win,
-                first_baron: team.objectives.baron.first,
-                first_dragon: team.objectives.dragon.first,
-                first_inhibitor: team.objectives.inhibitor.first,
-                first_rift_herald: team.objectives.rift_herald.first,
-                first_tower: team.objectives.tower.first,
-                baron_kills: team.objectives.baron.kills,
-                dragon_kills: team.objectives.dragon.kills,
-                inhibitor_kills: team.objectives.inhibitor.kills,
-                rift_herald_kills: team.objectives.rift_herald.kills,
-                tower_kills: team.objectives.tower.kills,
+        let hooked = HookedMatch {
+            region,
+            match_data: &match_data,
+            timeline: timeline.as_ref(),
+        };
+        if let Some(hook) = self.hooks.declined_by(&hooked).await {
+            // Remember the veto so other match histories listing the match skip it
+            let vetoed = DbVetoedMatch {
+                match_id: match_id.to_string(),
+                region: region.to_string(),
+                hook: hook.clone(),
+                vetoed_at: Utc::now(),
             };
+            if let Err(e) = self.database.record_vetoed_match(&vetoed).await {
+                tracing::warn!(error = %e, "Failed to record vetoed match");
+            }
+            return Ok(MatchOutcome::Vetoed(hook));
+        }
 
-            self.database.insert_team(&db_team)?;
-
-            // Store bans
-            for ban in &team.bans {
-                if ban.champion_id > 0 {
-                    // 0 or -1 indicates no ban
-                    let db_ban = DbBan {
-                        id: None,
-                        match_id: match_data.metadata.match_id.clone(),
-                        team_id: team.team_id,
-                        champion_id: ban.champion_id,
-                        pick_turn: ban.pick_turn,
-                    };
-
-                    self.database.insert_ban(&db_ban)?;
+        // In Match-v5, participant data includes PUUID directly
+        let discovered_summoners = match_data
+            .info
+            .participants
+            .iter()
+            .map(|p| (p.puuid.clone(), p.summoner_name.clone()))
+            .collect();
+
+        // Store the match and all of its rows atomically
+        let started = Instant::now();
+        self.database.store_match_bundle(&bundle).await?;
+        observe_write("store_match_bundle", started);
+        metrics().matches_stored.with_label_values(&[region]).inc();
+        metrics()
+            .participants_stored
+            .with_label_values(&[region])
+            .inc_by(bundle.participants.len() as u64);
+
+        if let (Some(tracker), Some(stratum)) = (&self.stratum_tracker, &bundle.stratum) {
+            tracker
+                .record_match(&stratum.region, stratum.tier, &stratum.patch)
+                .await;
+        }
+
+        self.matches_stored.fetch_add(1, Ordering::Relaxed);
+        *self
+            .matches_stored_by_region
+            .lock()
+            .unwrap()
+            .entry(region.to_string())
+            .or_default() += 1;
+
+        self.hooks
+            .match_stored(&hooked, self.database.as_ref())
+            .await;
+        Ok(MatchOutcome::Stored(discovered_summoners))
+    }
+
+    /// Compressed match payload, plus the timeline when enabled. A timeline that cannot
+    /// be fetched is logged and skipped rather than failing the match. The timeline
+    /// response is returned as well for the match hooks.
+    async fn archive_payloads(
+        &self,
+        archive: &ArchiveConfig,
+        match_id: &str,
+        region: &str,
+        raw_match: &str,
+    ) -> crate::Result<(Vec<DbRawPayload>, Option<String>)> {
+        let level = archive.compression_level;
+        let mut payloads = vec![encode_payload(
+            match_id,
+            RawPayloadKind::Match,
+            region,
+            raw_match,
+            level,
+        )?];
+
+        if archive.timelines {
+            match self
+                .api_client
+                .get_match_timeline_payload(region, match_id)
+                .await
+            {
+                Ok(timeline) => {
+                    payloads.push(encode_payload(
+                        match_id,
+                        RawPayloadKind::Timeline,
+                        region,
+                        &timeline,
+                        level,
+                    )?);
+                    return Ok((payloads, Some(timeline)));
                 }
+                Err(e) => tracing::warn!(error = %e, "Failed to fetch timeline"),
+            }
+        }
+        Ok((payloads, None))
+    }
+}
+
+/// Count a failed summoner or match by `ApiError` variant
+fn count_error(error: &anyhow::Error) {
+    metrics()
+        .worker_errors
+        .with_label_values(&[&TaskFailure::from_error(error).error_kind])
+        .inc();
+}
+
+fn observe_write(operation: &str, started: Instant) {
+    metrics()
+        .db_write_duration
+        .with_label_values(&[operation])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Normalised rows for a match payload. Sampling stratum and raw payloads are left
+/// for the caller to fill in.
+pub(crate) fn match_bundle(
+    match_data: &MatchDto,
+    region: &str,
+    created_at: DateTime<Utc>,
+) -> DbMatchBundle {
+    let db_match = DbMatch {
+        match_id: match_data.metadata.match_id.clone(),
+        game_creation: match_data.info.game_creation,
+        game_duration: match_data.info.game_duration as i32,
+        game_end_timestamp: match_data.info.game_end_timestamp,
+        game_id: match_data.info.game_id,
+        game_mode: match_data.info.game_mode.clone(),
+        game_name: match_data.info.game_name.clone(),
+        game_type: match_data.info.game_type.clone(),
+        game_version: match_data.info.game_version.clone(),
+        map_id: match_data.info.map_id,
+        platform_id: match_data.info.platform_id.clone(),
+        queue_id: match_data.info.queue_id,
+        tournament_code: match_data.info.tournament_code.clone(),
+        region: region.to_string(),
+        created_at,
+    };
+
+    let mut teams = Vec::new();
+    let mut bans = Vec::new();
+    for team in &match_data.info.teams {
+        let db_team = DbTeam {
+            id: None,
+            match_id: match_data.metadata.match_id.clone(),
+            team_id: team.team_id,
+            win: team.win,
+            first_baron: team.objectives.baron.first,
+            first_dragon: team.objectives.dragon.first,
+            first_inhibitor: team.objectives.inhibitor.first,
+            first_rift_herald: team.objectives.rift_herald.first,
+            first_tower: team.objectives.tower.first,
+            baron_kills: team.objectives.baron.kills,
+            dragon_kills: team.objectives.dragon.kills,
+            inhibitor_kills: team.objectives.inhibitor.kills,
+            rift_herald_kills: team.objectives.rift_herald.kills,
+            tower_kills: team.objectives.tower.kills,
+        };
+
+        teams.push(db_team);
+
+        // Collect bans
+        for ban in &team.bans {
+            if ban.champion_id > 0 {
+                // 0 or -1 indicates no ban
+                let db_ban = DbBan {
+                    id: None,
+                    match_id: match_data.metadata.match_id.clone(),
+                    team_id: team.team_id,
+                    champion_id: ban.champion_id,
+                    pick_turn: ban.pick_turn,
+                };
+
+                bans.push(db_ban);
             }
         }
+    }
+
+    // Collect participants with their rune pages and challenges
+    let mut participants = Vec::new();
+    let mut perks = Vec::new();
+    let mut challenges = Vec::new();
+
+    for participant in &match_data.info.participants {
+        let match_id = &match_data.metadata.match_id;
+        participants.push(participant_row(match_id, participant));
+        if let Some(page) = &participant.perks {
+            perks.extend(perk_rows(match_id, &participant.puuid, page));
+        }
+        if let Some(values) = &participant.challenges {
+            challenges.extend(challenge_rows(match_id, &participant.puuid, values));
+        }
+    }
+
+    DbMatchBundle {
+        match_data: db_match,
+        teams,
+        bans,
+        participants,
+        perks,
+        challenges,
+        stratum: None,
+        raw_payloads: Vec::new(),
+    }
+}
+
+/// Participant row with every scalar stat from the match payload
+fn participant_row(match_id: &str, participant: &ParticipantDto) -> DbParticipant {
+    DbParticipant {
+        id: None,
+        match_id: match_id.to_string(),
+        puuid: participant.puuid.clone(),
+        summoner_name: participant.summoner_name.clone(),
+        champion_id: participant.champion_id,
+        champion_name: Some(participant.champion_name.clone()),
+        team_id: participant.team_id,
+        position: Some(participant.lane.clone()),
+        individual_position: Some(participant.individual_position.clone()),
+        kills: participant.kills,
+        deaths: participant.deaths,
+        assists: participant.assists,
+        total_damage_dealt: participant.total_damage_dealt,
+        total_damage_dealt_to_champions: participant.total_damage_dealt_to_champions,
+        total_damage_taken: participant.total_damage_taken,
+        gold_earned: participant.gold_earned,
+        gold_spent: participant.gold_spent,
+        turret_kills: participant.turret_kills,
+        inhibitor_kills: participant.inhibitor_kills,
+        total_minions_killed: participant.total_minions_killed,
+        neutral_minions_killed: participant.neutral_minions_killed,
+        champion_level: participant.champ_level,
+        items_0: participant.item0,
+        items_1: participant.item1,
+        items_2: participant.item2,
+        items_3: participant.item3,
+        items_4: participant.item4,
+        items_5: participant.item5,
+        items_6: participant.item6,
+        summoner_spell_1: participant.summoner1_id,
+        summoner_spell_2: participant.summoner2_id,
+        primary_rune_tree: participant
+            .perks
+            .as_ref()
+            .and_then(|p| p.styles.first().map(|s| s.style)),
+        secondary_rune_tree: participant
+            .perks
+            .as_ref()
+            .and_then(|p| p.styles.get(1).map(|s| s.style)),
+        win: participant.win,
+        first_blood_kill: participant.first_blood_kill,
+        first_tower_kill: participant.first_tower_kill,
+        all_in_pings: participant.all_in_pings,
+        assist_me_pings: participant.assist_me_pings,
+        baron_kills: participant.baron_kills,
+        bounty_level: participant.bounty_level,
+        champ_experience: participant.champ_experience,
+        command_pings: participant.command_pings,
+        champion_transform: participant.champion_transform,
+        consumables_purchased: participant.consumables_purchased,
+        damage_dealt_to_buildings: participant.damage_dealt_to_buildings,
+        damage_dealt_to_objectives: participant.damage_dealt_to_objectives,
+        damage_dealt_to_turrets: participant.damage_dealt_to_turrets,
+        damage_self_mitigated: participant.damage_self_mitigated,
+        detector_wards_placed: participant.detector_wards_placed,
+        double_kills: participant.double_kills,
+        dragon_kills: participant.dragon_kills,
+        eligible_for_progression: participant.eligible_for_progression,
+        enemy_missing_pings: participant.enemy_missing_pings,
+        enemy_vision_pings: participant.enemy_vision_pings,
+        first_blood_assist: participant.first_blood_assist,
+        first_tower_assist: participant.first_tower_assist,
+        game_ended_in_early_surrender: participant.game_ended_in_early_surrender,
+        game_ended_in_surrender: participant.game_ended_in_surrender,
+        hold_pings: participant.hold_pings,
+        get_back_pings: participant.get_back_pings,
+        inhibitor_takedowns: participant.inhibitor_takedowns,
+        inhibitors_lost: participant.inhibitors_lost,
+        items_purchased: participant.items_purchased,
+        killing_sprees: participant.killing_sprees,
+        largest_critical_strike: participant.largest_critical_strike,
+        largest_killing_spree: participant.largest_killing_spree,
+        largest_multi_kill: participant.largest_multi_kill,
+        longest_time_spent_living: participant.longest_time_spent_living,
+        magic_damage_dealt: participant.magic_damage_dealt,
+        magic_damage_dealt_to_champions: participant.magic_damage_dealt_to_champions,
+        magic_damage_taken: participant.magic_damage_taken,
+        need_vision_pings: participant.need_vision_pings,
+        nexus_kills: participant.nexus_kills,
+        nexus_takedowns: participant.nexus_takedowns,
+        nexus_lost: participant.nexus_lost,
+        objectives_stolen: participant.objectives_stolen,
+        objectives_stolen_assists: participant.objectives_stolen_assists,
+        on_my_way_pings: participant.on_my_way_pings,
+        participant_id: participant.participant_id,
+        penta_kills: participant.penta_kills,
+        physical_damage_dealt: participant.physical_damage_dealt,
+        physical_damage_dealt_to_champions: participant.physical_damage_dealt_to_champions,
+        physical_damage_taken: participant.physical_damage_taken,
+        placement: participant.placement,
+        player_augment1: participant.player_augment1,
+        player_augment2: participant.player_augment2,
+        player_augment3: participant.player_augment3,
+        player_augment4: participant.player_augment4,
+        player_subteam_id: participant.player_subteam_id,
+        push_pings: participant.push_pings,
+        profile_icon: participant.profile_icon,
+        quadra_kills: participant.quadra_kills,
+        riot_id_game_name: participant.riot_id_game_name.clone(),
+        riot_id_tagline: participant.riot_id_tagline.clone(),
+        role: participant.role.clone(),
+        sight_wards_bought_in_game: participant.sight_wards_bought_in_game,
+        spell1_casts: participant.spell1_casts,
+        spell2_casts: participant.spell2_casts,
+        spell3_casts: participant.spell3_casts,
+        spell4_casts: participant.spell4_casts,
+        subteam_placement: participant.subteam_placement,
+        summoner1_casts: participant.summoner1_casts,
+        summoner2_casts: participant.summoner2_casts,
+        summoner_id: participant.summoner_id.clone(),
+        summoner_level: participant.summoner_level,
+        team_early_surrendered: participant.team_early_surrendered,
+        team_position: participant.team_position.clone(),
+        time_ccing_others: participant.time_ccing_others,
+        time_played: participant.time_played,
+        total_ally_jungle_minions_killed: participant.total_ally_jungle_minions_killed,
+        total_damage_shielded_on_teammates: participant.total_damage_shielded_on_teammates,
+        total_enemy_jungle_minions_killed: participant.total_enemy_jungle_minions_killed,
+        total_heal: participant.total_heal,
+        total_heals_on_teammates: participant.total_heals_on_teammates,
+        total_time_cc_dealt: participant.total_time_cc_dealt,
+        total_time_spent_dead: participant.total_time_spent_dead,
+        total_units_healed: participant.total_units_healed,
+        triple_kills: participant.triple_kills,
+        true_damage_dealt: participant.true_damage_dealt,
+        true_damage_dealt_to_champions: participant.true_damage_dealt_to_champions,
+        true_damage_taken: participant.true_damage_taken,
+        turret_takedowns: participant.turret_takedowns,
+        turrets_lost: participant.turrets_lost,
+        unreal_kills: participant.unreal_kills,
+        vision_score: participant.vision_score,
+        vision_cleared_pings: participant.vision_cleared_pings,
+        vision_wards_bought_in_game: participant.vision_wards_bought_in_game,
+        wards_killed: participant.wards_killed,
+        wards_placed: participant.wards_placed,
+        missions: participant
+            .missions
+            .as_ref()
+            .and_then(|m| serde_json::to_string(m).ok()),
+    }
+}
 
-        // Store participants and collect summoner info
-        let mut discovered_summoners = HashSet::new();
-
-        for participant in &match_data.info.participants {
-            // In Match-v5, participant data includes PUUID directly
-            discovered_summoners
-                .insert((participant.puuid.clone(), participant.summoner_name.clone()));
-
-            let db_participant = DbParticipant {
-                id: None,
-                match_id: match_data.metadata.match_id.clone(),
-                puuid: participant.puuid.clone(),
-                summoner_name: participant.summoner_name.clone(),
-                champion_id: participant.champion_id,
-                champion_name: Some(participant.champion_name.clone()),
-                team_id: participant.team_id,
-                position: Some(participant.lane.clone()),
-                individual_position: Some(participant.individual_position.clone()),
-                kills: participant.kills,
-                deaths: participant.deaths,
-                assists: participant.assists,
-                total_damage_dealt: participant.total_damage_dealt,
-                total_damage_dealt_to_champions: participant.total_damage_dealt_to_champions,
-                total_damage_taken: participant.total_damage_taken,
-                gold_earned: participant.gold_earned,
-                gold_spent: participant.gold_spent,
-                turret_kills: participant.turret_kills,
-                inhibitor_kills: participant.inhibitor_kills,
-                total_minions_killed: participant.total_minions_killed,
-                neutral_minions_killed: participant.neutral_minions_killed,
-                champion_level: participant.champ_level,
-                items_0: participant.item0,
-                items_1: participant.item1,
-                items_2: participant.item2,
-                items_3: participant.item3,
-                items_4: participant.item4,
-                items_5: participant.item5,
-                items_6: participant.item6,
-                summoner_spell_1: participant.summoner1_id,
-                summoner_spell_2: participant.summoner2_id,
-                primary_rune_tree: participant
-                    .perks
-                    .as_ref()
-                    .and_then(|p| p.styles.first().map(|s| s.style)),
-                secondary_rune_tree: participant
-                    .perks
-                    .as_ref()
-                    .and_then(|p| p.styles.get(1).map(|s| s.style)),
-                win: participant.win,
-                first_blood_kill: participant.first_blood_kill,
-                first_tower_kill: participant.first_tower_kill,
+/// Every rune and stat shard on the participant's rune page
+fn perk_rows(match_id: &str, puuid: &str, perks: &PerksDto) -> Vec<DbParticipantPerk> {
+    let row = |style: &str, style_id: Option<i32>, slot: usize, perk_id: i32| DbParticipantPerk {
+        match_id: match_id.to_string(),
+        puuid: puuid.to_string(),
+        style: style.to_string(),
+        style_id,
+        slot: slot as i32,
+        perk_id,
+        var1: None,
+        var2: None,
+        var3: None,
+    };
+
+    let mut rows = Vec::new();
+    for style in &perks.styles {
+        for (slot, selection) in style.selections.iter().enumerate() {
+            rows.push(DbParticipantPerk {
+                var1: Some(selection.var1),
+                var2: Some(selection.var2),
+                var3: Some(selection.var3),
+                ..row(&style.description, Some(style.style), slot, selection.perk)
+            });
+        }
+    }
+    let shards = &perks.stat_perks;
+    for (slot, perk_id) in [shards.offense, shards.flex, shards.defense]
+        .into_iter()
+        .enumerate()
+    {
+        rows.push(row("statPerks", None, slot, perk_id));
+    }
+    rows
+}
+
+/// Every challenge value reported for the participant
+fn challenge_rows(
+    match_id: &str,
+    puuid: &str,
+    challenges: &ChallengesDto,
+) -> Vec<DbParticipantChallenge> {
+    let named = [
+        ("kda", challenges.kda.map(serde_json::Value::from)),
+        (
+            "killParticipation",
+            challenges.kill_participation.map(serde_json::Value::from),
+        ),
+    ];
+    let other = challenges
+        .other
+        .iter()
+        .map(|(name, value)| (name.as_str(), Some(value.clone())));
+
+    named
+        .into_iter()
+        .chain(other)
+        .filter_map(|(name, value)| {
+            let (value, value_json) = match value? {
+                serde_json::Value::Null => return None,
+                serde_json::Value::Number(n) => (n.as_f64(), None),
+                serde_json::Value::Bool(b) => (Some(if b { 1.0 } else { 0.0 }), None),
+                other => (None, Some(other.to_string())),
             };
+            Some(DbParticipantChallenge {
+                match_id: match_id.to_string(),
+                puuid: puuid.to_string(),
+                name: name.to_string(),
+                value,
+                value_json,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::match_v5::{PerkStatsDto, PerkStyleDto, PerkStyleSelectionDto};
 
-            self.database.insert_participant(&db_participant)?;
+    fn selection(perk: i32) -> PerkStyleSelectionDto {
+        PerkStyleSelectionDto {
+            perk,
+            var1: 100,
+            var2: 20,
+            var3: 0,
         }
+    }
+
+    #[test]
+    fn test_perk_rows_cover_the_full_page() {
+        let perks = PerksDto {
+            stat_perks: PerkStatsDto {
+                defense: 5001,
+                flex: 5008,
+                offense: 5005,
+            },
+            styles: vec![
+                PerkStyleDto {
+                    description: "primaryStyle".to_string(),
+                    selections: vec![
+                        selection(8010),
+                        selection(9111),
+                        selection(9104),
+                        selection(8299),
+                    ],
+                    style: 8000,
+                },
+                PerkStyleDto {
+                    description: "subStyle".to_string(),
+                    selections: vec![selection(8444), selection(8453)],
+                    style: 8400,
+                },
+            ],
+        };
 
-        Ok(discovered_summoners)
+        let rows = perk_rows("NA1_1", "p1", &perks);
+        assert_eq!(rows.len(), 9);
+        assert_eq!(rows[0].style, "primaryStyle");
+        assert_eq!(rows[0].style_id, Some(8000));
+        assert_eq!(rows[0].perk_id, 8010);
+        assert_eq!(rows[0].var1, Some(100));
+        assert_eq!(rows[5].style, "subStyle");
+        assert_eq!(rows[5].slot, 1);
+
+        let shards: Vec<(i32, i32)> = rows[6..].iter().map(|r| (r.slot, r.perk_id)).collect();
+        assert_eq!(shards, vec![(0, 5005), (1, 5008), (2, 5001)]);
+        assert!(rows[6..]
+            .iter()
+            .all(|r| r.style == "statPerks" && r.style_id.is_none() && r.var1.is_none()));
+    }
+
+    #[test]
+    fn test_challenge_rows_keep_every_value() {
+        let challenges: ChallengesDto = serde_json::from_value(serde_json::json!({
+            "kda": 4.5,
+            "killParticipation": 0.62,
+            "visionScorePerMinute": 1.25,
+            "controlWardsPlaced": 3,
+            "playedChampSelectPosition": true,
+            "legendaryItemUsed": [3031, 6672],
+            "fasterSupportQuestCompletion": null
+        }))
+        .unwrap();
+
+        let rows = challenge_rows("NA1_1", "p1", &challenges);
+        let get = |name: &str| rows.iter().find(|r| r.name == name).unwrap();
+
+        assert_eq!(rows.len(), 6);
+        assert_eq!(get("kda").value, Some(4.5));
+        assert_eq!(get("killParticipation").value, Some(0.62));
+        assert_eq!(get("controlWardsPlaced").value, Some(3.0));
+        assert_eq!(get("playedChampSelectPosition").value, Some(1.0));
+        assert_eq!(get("legendaryItemUsed").value, None);
+        assert_eq!(
+            get("legendaryItemUsed").value_json.as_deref(),
+            Some("[3031,6672]")
+        );
     }
 }
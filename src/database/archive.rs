@@ -0,0 +1,52 @@
+use crate::models::database::{DbRawPayload, RawPayloadKind};
+use crate::Result;
+use chrono::Utc;
+
+/// Compress an API response for the raw payload archive
+pub fn encode_payload(
+    match_id: &str,
+    kind: RawPayloadKind,
+    region: &str,
+    json: &str,
+    level: i32,
+) -> Result<DbRawPayload> {
+    Ok(DbRawPayload {
+        match_id: match_id.to_string(),
+        kind,
+        region: region.to_string(),
+        payload: zstd::encode_all(json.as_bytes(), level)?,
+        raw_size: json.len() as i64,
+        fetched_at: Utc::now(),
+    })
+}
+
+/// The original JSON of an archived payload
+pub fn decode_payload(payload: &DbRawPayload) -> Result<String> {
+    let bytes = zstd::decode_all(payload.payload.as_slice())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_round_trip() {
+        let json = format!(
+            r#"{{"metadata":{{"matchId":"NA1_1"}},"pad":"{}"}}"#,
+            "x".repeat(4096)
+        );
+        let payload = encode_payload("NA1_1", RawPayloadKind::Match, "na1", &json, 3).unwrap();
+
+        assert_eq!(payload.raw_size, json.len() as i64);
+        assert!(payload.payload.len() < json.len());
+        assert_eq!(decode_payload(&payload).unwrap(), json);
+    }
+
+    #[test]
+    fn test_corrupt_payload_is_an_error() {
+        let mut payload = encode_payload("NA1_1", RawPayloadKind::Match, "na1", "{}", 3).unwrap();
+        payload.payload = b"not zstd".to_vec();
+        assert!(decode_payload(&payload).is_err());
+    }
+}
@@ -1,49 +1,233 @@
+use super::migrations::{self, MigrationStatus};
 use super::schema::Schema;
+use super::StorageError;
 use crate::Result;
-use rusqlite::{Connection, Result as SqliteResult};
+use rusqlite::types::{ToSqlOutput, Value};
+use rusqlite::{Connection, OpenFlags, Result as SqliteResult, Transaction};
+use std::ops::Deref;
 use std::path::Path;
-use std::sync::{Arc, Mutex};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::oneshot;
 
+/// Read connections opened alongside the writer
+const READ_POOL_SIZE: usize = 4;
+
+type WriteJob = Box<dyn FnOnce(&mut Connection) + Send>;
+
+/// SQLite database with a pool of read connections and a single writer thread.
+///
+/// Writes are sent over a channel to the writer, which applies them in order on its own
+/// connection; reads check out a pooled connection. Through `Storage`, writes await the
+/// writer's reply and reads run on tokio's blocking pool, so neither holds up the
+/// runtime. The synchronous methods wait inside `block_in_place` on the multi-threaded
+/// runtime.
 #[derive(Clone)]
 pub struct Database {
-    connection: Arc<Mutex<Connection>>,
+    inner: Arc<Inner>,
 }
 
-impl Database {
-    pub fn new(database_url: &str) -> Result<Self> {
-        // Ensure the parent directory exists
-        if let Some(parent) = Path::new(database_url).parent() {
-            std::fs::create_dir_all(parent)?;
+struct Inner {
+    readers: ReaderPool,
+    writer: Option<mpsc::Sender<WriteJob>>,
+    writer_thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Closing the channel stops the writer once queued writes are applied
+        self.writer.take();
+        if let Some(handle) = self.writer_thread.take() {
+            let _ = handle.join();
         }
+    }
+}
 
-        let conn = Connection::open(database_url)?;
-        let database = Database {
-            connection: Arc::new(Mutex::new(conn)),
-        };
+impl Database {
+    pub fn new(database_url: &str) -> Result<Self> {
+        let database = Self::open(database_url)?;
 
-        // Initialize schema
+        // Initialize schema and apply pending migrations
         database.initialize_schema()?;
 
         Ok(database)
     }
 
+    /// Open a database without creating or migrating its schema
+    pub fn open(database_url: &str) -> Result<Self> {
+        let shared_memory = database_url == ":memory:";
+        let target = if shared_memory {
+            // Every plain ":memory:" connection is a separate database, so the pool and
+            // the writer share a named in-memory database instead
+            format!(
+                "file:memdb-{}?mode=memory&cache=shared",
+                uuid::Uuid::new_v4()
+            )
+        } else {
+            // Ensure the parent directory exists
+            if let Some(parent) = Path::new(database_url).parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            database_url.to_string()
+        };
+
+        let writer_conn = Connection::open_with_flags(&target, OpenFlags::default())?;
+        configure_writer(&writer_conn)?;
+
+        let mut readers = Vec::with_capacity(READ_POOL_SIZE);
+        for _ in 0..READ_POOL_SIZE {
+            let conn = Connection::open_with_flags(&target, OpenFlags::default())?;
+            configure_reader(&conn, shared_memory)?;
+            readers.push(conn);
+        }
+
+        let (sender, receiver) = mpsc::channel::<WriteJob>();
+        let writer_thread = std::thread::Builder::new()
+            .name("sqlite-writer".to_string())
+            .spawn(move || {
+                let mut conn = writer_conn;
+                for job in receiver {
+                    job(&mut conn);
+                }
+            })?;
+
+        Ok(Database {
+            inner: Arc::new(Inner {
+                readers: ReaderPool {
+                    idle: Mutex::new(readers),
+                    available: Condvar::new(),
+                },
+                writer: Some(sender),
+                writer_thread: Some(writer_thread),
+            }),
+        })
+    }
+
     pub fn execute(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<usize> {
-        let conn = self.connection.lock().unwrap();
-        Ok(conn.execute(sql, params)?)
+        let sql = sql.to_string();
+        let values = owned_params(params)?;
+        self.write(move |conn| {
+            conn.prepare_cached(&sql)?
+                .execute(rusqlite::params_from_iter(values))
+        })
     }
 
     fn initialize_schema(&self) -> Result<()> {
-        let conn = self.connection.lock().unwrap();
-        Schema::initialize(&conn)?;
-        Ok(())
+        self.write(|conn| Schema::initialize(conn))
+    }
+
+    /// Run `f` on the writer thread and wait for its result
+    pub(crate) fn write<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> SqliteResult<T> + Send + 'static,
+    {
+        let (reply, result) = mpsc::sync_channel(1);
+        self.submit(Box::new(move |conn| {
+            let _ = reply.send(f(conn));
+        }))?;
+        match blocking(|| result.recv()) {
+            Ok(result) => Ok(result.map_err(StorageError::from)?),
+            Err(_) => Err(StorageError::WriterStopped.into()),
+        }
+    }
+
+    /// Run `f` on the writer thread and await its result without blocking the runtime
+    pub(crate) async fn write_async<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> SqliteResult<T> + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        self.submit(Box::new(move |conn| {
+            let _ = reply.send(f(conn));
+        }))?;
+        match result.await {
+            Ok(result) => Ok(result.map_err(StorageError::from)?),
+            Err(_) => Err(StorageError::WriterStopped.into()),
+        }
+    }
+
+    fn submit(&self, job: WriteJob) -> Result<()> {
+        let sender = self.inner.writer.as_ref().expect("writer runs until drop");
+        sender
+            .send(job)
+            .map_err(|_| StorageError::WriterStopped.into())
+    }
+
+    /// Run `f` in a transaction on the writer thread, committing if it succeeds and
+    /// rolling back otherwise
+    pub(crate) fn transaction<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Transaction) -> SqliteResult<T> + Send + 'static,
+    {
+        self.write(in_transaction(f))
+    }
+
+    /// `transaction` for async callers
+    pub(crate) async fn transaction_async<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Transaction) -> SqliteResult<T> + Send + 'static,
+    {
+        self.write_async(in_transaction(f)).await
+    }
+
+    /// Run `f` on a pooled read connection
+    pub(crate) fn read<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Connection) -> SqliteResult<T>,
+    {
+        blocking(|| {
+            let conn = self.inner.readers.get();
+            Ok(f(&conn).map_err(StorageError::from)?)
+        })
+    }
+
+    /// Run the synchronous reads in `f` on tokio's blocking pool, where waiting for a
+    /// pooled connection or for SQLite I/O does not hold up the runtime
+    pub(crate) async fn read_async<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+    {
+        let database = self.clone();
+        tokio::task::spawn_blocking(move || f(&database)).await?
+    }
+
+    /// Baseline and embedded migrations with the time each was applied
+    pub fn migration_status(&self) -> Result<Vec<MigrationStatus>> {
+        self.read(migrations::status)
+    }
+
+    /// Bring the schema up to date, returning the versions that were applied
+    pub fn migrate(&self) -> Result<Vec<MigrationStatus>> {
+        self.write(|conn| {
+            if !Schema::needs_migration(conn)? {
+                return Ok(Vec::new());
+            }
+
+            let pending: Vec<i32> = migrations::status(conn)?
+                .into_iter()
+                .filter(|s| s.applied_at.is_none())
+                .map(|s| s.version)
+                .collect();
+            Schema::initialize(conn)?;
+
+            Ok(migrations::status(conn)?
+                .into_iter()
+                .filter(|s| pending.contains(&s.version))
+                .collect())
+        })
     }
 
     pub fn query_row<T, F>(&self, sql: &str, params: &[&dyn rusqlite::ToSql], f: F) -> Result<T>
     where
         F: FnOnce(&rusqlite::Row) -> SqliteResult<T>,
     {
-        let conn = self.connection.lock().unwrap();
-        Ok(conn.query_row(sql, params, f)?)
+        self.read(|conn| conn.prepare_cached(sql)?.query_row(params, f))
     }
 
     pub fn query_map<T, F>(
@@ -55,15 +239,233 @@ impl Database {
     where
         F: FnMut(&rusqlite::Row) -> SqliteResult<T>,
     {
-        let conn = self.connection.lock().unwrap();
-        let mut stmt = conn.prepare(sql)?;
-        let rows = stmt.query_map(params, &mut f)?;
+        self.read(|conn| {
+            let mut stmt = conn.prepare_cached(sql)?;
+            let rows = stmt.query_map(params, &mut f)?;
+            rows.collect()
+        })
+    }
+}
+
+/// Idle read connections; `get` waits while all of them are checked out
+struct ReaderPool {
+    idle: Mutex<Vec<Connection>>,
+    available: Condvar,
+}
+
+impl ReaderPool {
+    fn get(&self) -> PooledConnection<'_> {
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(conn) = idle.pop() {
+                return PooledConnection {
+                    pool: self,
+                    conn: Some(conn),
+                };
+            }
+            idle = self.available.wait(idle).unwrap();
+        }
+    }
+}
+
+/// A read connection that goes back to the pool when dropped
+struct PooledConnection<'a> {
+    pool: &'a ReaderPool,
+    conn: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection is held until drop")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.idle.lock().unwrap().push(conn);
+            self.pool.available.notify_one();
+        }
+    }
+}
+
+/// Wrap `f` in a transaction that commits if it succeeds and rolls back otherwise
+fn in_transaction<T, F>(f: F) -> impl FnOnce(&mut Connection) -> SqliteResult<T>
+where
+    F: FnOnce(&Transaction) -> SqliteResult<T>,
+{
+    move |conn| {
+        let tx = conn.transaction()?;
+        let result = f(&tx)?;
+        tx.commit()?;
+        Ok(result)
+    }
+}
+
+/// Run blocking SQLite work without stalling the tokio runtime it is called from
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
 
-        let mut results = Vec::new();
-        for row in rows {
-            results.push(row?);
+/// Copy borrowed parameters so a statement can be sent to the writer thread
+fn owned_params(params: &[&dyn rusqlite::ToSql]) -> SqliteResult<Vec<Value>> {
+    params
+        .iter()
+        .map(|param| match param.to_sql()? {
+            ToSqlOutput::Borrowed(value) => Ok(value.into()),
+            ToSqlOutput::Owned(value) => Ok(value),
+            _ => Err(rusqlite::Error::ToSqlConversionFailure(
+                "unsupported parameter type".into(),
+            )),
+        })
+        .collect()
+}
+
+fn configure_connection(conn: &Connection) -> SqliteResult<()> {
+    conn.pragma_update(None, "temp_store", "MEMORY")?;
+    // Negative cache size is in KiB: 64 MiB of page cache
+    conn.pragma_update(None, "cache_size", -65536)?;
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.set_prepared_statement_cache_capacity(64);
+    Ok(())
+}
+
+/// Pragmas tuned for a single writer ingesting many small transactions
+fn configure_writer(conn: &Connection) -> SqliteResult<()> {
+    // Lets retention hand freed pages back to the file system; only takes effect on a
+    // database that has no tables yet
+    conn.pragma_update(None, "auto_vacuum", "INCREMENTAL")?;
+    // WAL lets readers run alongside the writer; NORMAL sync is durable in WAL mode
+    // except for the last transactions before a power loss
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    configure_connection(conn)
+}
+
+fn configure_reader(conn: &Connection, shared_memory: bool) -> SqliteResult<()> {
+    configure_connection(conn)?;
+    conn.pragma_update(None, "query_only", true)?;
+    if shared_memory {
+        // Shared-cache databases lock whole tables; read without waiting on the writer
+        conn.pragma_update(None, "read_uncommitted", true)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(db: &Database) -> i64 {
+        db.query_row("SELECT COUNT(*) FROM items", &[], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_memory_databases_are_isolated() {
+        let first = Database::open(":memory:").unwrap();
+        let second = Database::open(":memory:").unwrap();
+
+        first
+            .execute("CREATE TABLE items (id INTEGER)", &[])
+            .unwrap();
+        first
+            .execute("INSERT INTO items (id) VALUES (?1)", &[&1])
+            .unwrap();
+
+        // Pooled readers see the writer's data; the other database does not
+        assert_eq!(count(&first), 1);
+        assert!(second
+            .query_row("SELECT COUNT(*) FROM items", &[], |row| row
+                .get::<_, i64>(0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_readers_cannot_write() {
+        let db = Database::open(":memory:").unwrap();
+        db.execute("CREATE TABLE items (id INTEGER)", &[]).unwrap();
+
+        let result = db.read(|conn| conn.execute("INSERT INTO items (id) VALUES (1)", []));
+        assert!(result.is_err());
+        assert_eq!(count(&db), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_reads_and_writes() {
+        let db = Database::open(":memory:").unwrap();
+        db.execute("CREATE TABLE items (id INTEGER)", &[]).unwrap();
+
+        let mut handles = Vec::new();
+        for task in 0..8i64 {
+            let db = db.clone();
+            handles.push(tokio::spawn(async move {
+                for i in 0..25i64 {
+                    db.execute("INSERT INTO items (id) VALUES (?1)", &[&(task * 100 + i)])
+                        .unwrap();
+                    assert!(count(&db) > 0);
+                }
+            }));
         }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(count(&db), 200);
+    }
+
+    #[tokio::test]
+    async fn test_storage_calls_do_not_block_a_current_thread_runtime() {
+        use crate::database::Storage;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let db = Database::new(":memory:").unwrap();
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        });
+
+        // Keep the writer and every reader busy while the storage calls wait for them
+        let (busy, started) = mpsc::channel();
+        let (writer_busy, readers_busy) = (busy.clone(), busy);
+        db.submit(Box::new(move |_| {
+            let _ = writer_busy.send(());
+            std::thread::sleep(Duration::from_millis(300));
+        }))
+        .unwrap();
+        let pool = db.clone();
+        std::thread::spawn(move || {
+            let held: Vec<_> = (0..READ_POOL_SIZE)
+                .map(|_| pool.inner.readers.get())
+                .collect();
+            let _ = readers_busy.send(());
+            std::thread::sleep(Duration::from_millis(300));
+            drop(held);
+        });
+        started.recv().unwrap();
+        started.recv().unwrap();
 
-        Ok(results)
+        let before = ticks.load(Ordering::SeqCst);
+        let (stored, counted) = tokio::join!(
+            Storage::save_pending_tasks(&db, &[]),
+            Storage::get_matches_count(&db)
+        );
+        stored.unwrap();
+        assert_eq!(counted.unwrap(), 0);
+        assert!(ticks.load(Ordering::SeqCst) - before >= 10);
+        ticker.abort();
     }
 }
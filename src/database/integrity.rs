@@ -0,0 +1,347 @@
+use super::retention::delete_match_rows;
+use super::{Database, CHILD_TABLES};
+use crate::Result;
+use std::collections::BTreeMap;
+
+/// What is wrong with a stored match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// Rows whose match does not exist
+    Orphaned,
+    /// Wrong number of participants or teams
+    Incomplete,
+    /// Participants on a team the match has no row for, or disagreeing with it
+    TeamMismatch,
+    /// The same player or participant id twice in one match
+    Duplicate,
+    /// Values no real game can produce
+    ImpossibleValue,
+}
+
+impl IssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueKind::Orphaned => "orphaned",
+            IssueKind::Incomplete => "incomplete",
+            IssueKind::TeamMismatch => "team_mismatch",
+            IssueKind::Duplicate => "duplicate",
+            IssueKind::ImpossibleValue => "impossible_value",
+        }
+    }
+}
+
+impl std::fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single problem found by [`Database::verify_integrity`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityIssue {
+    pub kind: IssueKind,
+    pub match_id: String,
+    /// Region of the match; `None` for orphaned rows
+    pub region: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityReport {
+    pub matches_checked: i64,
+    pub issues: Vec<IntegrityIssue>,
+    /// Rows, across all tables, whose match is not stored
+    pub orphaned_rows: i64,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Distinct (match_id, region) of stored matches with at least one issue
+    pub fn broken_matches(&self) -> Vec<(String, String)> {
+        let broken: BTreeMap<&str, &str> = self
+            .issues
+            .iter()
+            .filter_map(|issue| Some((issue.match_id.as_str(), issue.region.as_deref()?)))
+            .collect();
+        broken
+            .into_iter()
+            .map(|(id, region)| (id.to_string(), region.to_string()))
+            .collect()
+    }
+
+    pub fn has_orphans(&self) -> bool {
+        self.issues.iter().any(|i| i.kind == IssueKind::Orphaned)
+    }
+}
+
+impl Database {
+    /// Scan every stored match for orphaned rows, missing participants or teams, team
+    /// mismatches, duplicates and impossible values
+    pub fn verify_integrity(&self) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport {
+            matches_checked: self
+                .query_row("SELECT COUNT(*) FROM matches", &[], |row| row.get(0))?,
+            issues: Vec::new(),
+            orphaned_rows: 0,
+        };
+
+        for table in CHILD_TABLES
+            .iter()
+            .chain(&["match_strata", "match_hook_rows"])
+        {
+            let orphans = self.query_map(
+                &format!(
+                    "SELECT match_id, COUNT(*) FROM {} c
+                     WHERE NOT EXISTS (SELECT 1 FROM matches m WHERE m.match_id = c.match_id)
+                     GROUP BY match_id",
+                    table
+                ),
+                &[],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+            )?;
+            for (match_id, count) in orphans {
+                report.orphaned_rows += count;
+                report.issues.push(IntegrityIssue {
+                    kind: IssueKind::Orphaned,
+                    match_id,
+                    region: None,
+                    detail: format!("{} {} rows without a match", count, table),
+                });
+            }
+        }
+
+        // Arena (game mode CHERRY) is played by 16 players in subteams rather than two
+        // teams of five, so only its participant count is checked
+        let checks: &[(IssueKind, &str)] = &[
+            (
+                IssueKind::Incomplete,
+                "SELECT m.match_id, m.region, COUNT(p.id) || ' participants'
+                 FROM matches m LEFT JOIN participants p ON p.match_id = m.match_id
+                 GROUP BY m.match_id
+                 HAVING COUNT(p.id) != CASE WHEN m.game_mode = 'CHERRY' THEN 16 ELSE 10 END",
+            ),
+            (
+                IssueKind::Incomplete,
+                "SELECT m.match_id, m.region, COUNT(t.id) || ' teams'
+                 FROM matches m LEFT JOIN teams t ON t.match_id = m.match_id
+                 WHERE m.game_mode != 'CHERRY'
+                 GROUP BY m.match_id
+                 HAVING COUNT(t.id) != 2",
+            ),
+            (
+                IssueKind::TeamMismatch,
+                "SELECT m.match_id, m.region, 'participant ' || p.puuid || ' on missing team ' || p.team_id
+                 FROM participants p JOIN matches m ON m.match_id = p.match_id
+                 WHERE m.game_mode != 'CHERRY'
+                   AND NOT EXISTS (SELECT 1 FROM teams t
+                                   WHERE t.match_id = p.match_id AND t.team_id = p.team_id)",
+            ),
+            (
+                IssueKind::TeamMismatch,
+                "SELECT m.match_id, m.region, 'participant ' || p.puuid || ' win disagrees with team ' || p.team_id
+                 FROM participants p
+                 JOIN matches m ON m.match_id = p.match_id
+                 JOIN teams t ON t.match_id = p.match_id AND t.team_id = p.team_id
+                 WHERE m.game_mode != 'CHERRY' AND p.win != t.win",
+            ),
+            (
+                IssueKind::TeamMismatch,
+                "SELECT m.match_id, m.region, SUM(t.win) || ' winning teams'
+                 FROM matches m JOIN teams t ON t.match_id = m.match_id
+                 WHERE m.game_mode != 'CHERRY'
+                 GROUP BY m.match_id
+                 HAVING COUNT(t.id) = 2 AND SUM(t.win) != 1",
+            ),
+            (
+                IssueKind::Duplicate,
+                "SELECT m.match_id, m.region, 'puuid ' || p.puuid || ' appears ' || COUNT(*) || ' times'
+                 FROM participants p JOIN matches m ON m.match_id = p.match_id
+                 GROUP BY p.match_id, p.puuid
+                 HAVING COUNT(*) > 1",
+            ),
+            (
+                IssueKind::Duplicate,
+                "SELECT m.match_id, m.region, 'participant id ' || p.participant_id || ' appears ' || COUNT(*) || ' times'
+                 FROM participants p JOIN matches m ON m.match_id = p.match_id
+                 WHERE p.participant_id > 0
+                 GROUP BY p.match_id, p.participant_id
+                 HAVING COUNT(*) > 1",
+            ),
+            (
+                IssueKind::ImpossibleValue,
+                "SELECT match_id, region, 'game_duration ' || game_duration
+                 FROM matches
+                 WHERE game_duration <= 0 OR game_creation <= 0",
+            ),
+            (
+                IssueKind::ImpossibleValue,
+                "SELECT m.match_id, m.region, 'participant ' || p.puuid || ' has negative stats or champion level ' || p.champion_level
+                 FROM participants p JOIN matches m ON m.match_id = p.match_id
+                 WHERE p.kills < 0 OR p.deaths < 0 OR p.assists < 0 OR p.gold_earned < 0
+                    OR p.total_minions_killed < 0 OR p.total_damage_dealt < 0
+                    OR p.champion_level NOT BETWEEN 1 AND 18",
+            ),
+        ];
+
+        for (kind, sql) in checks {
+            let issues = self.query_map(sql, &[], |row| {
+                Ok(IntegrityIssue {
+                    kind: *kind,
+                    match_id: row.get(0)?,
+                    region: row.get(1)?,
+                    detail: row.get(2)?,
+                })
+            })?;
+            report.issues.extend(issues);
+        }
+
+        report
+            .issues
+            .sort_by(|a, b| (a.kind, &a.match_id).cmp(&(b.kind, &b.match_id)));
+        Ok(report)
+    }
+
+    /// Remove a match with all of its rows and strata. Archived payloads are kept, so
+    /// `reprocess` can still rebuild it.
+    pub fn delete_match(&self, match_id: &str) -> Result<()> {
+        let match_id = match_id.to_string();
+        self.transaction(move |tx| delete_match_rows(tx, &match_id, true))
+    }
+
+    /// Remove rows whose match does not exist, returning how many were deleted
+    pub fn delete_orphaned_rows(&self) -> Result<usize> {
+        self.transaction(|tx| {
+            let mut deleted = 0;
+            for table in CHILD_TABLES
+                .iter()
+                .chain(&["match_strata", "match_hook_rows"])
+            {
+                deleted += tx.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE NOT EXISTS
+                         (SELECT 1 FROM matches m WHERE m.match_id = {}.match_id)",
+                        table, table
+                    ),
+                    [],
+                )?;
+            }
+            Ok(deleted)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::database::DbParticipant;
+    use crate::models::fixtures::bundle;
+
+    fn kinds(report: &IntegrityReport, match_id: &str) -> Vec<IssueKind> {
+        report
+            .issues
+            .iter()
+            .filter(|i| i.match_id == match_id)
+            .map(|i| i.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_well_formed_matches_pass() {
+        let db = Database::new(":memory:").unwrap();
+        db.store_match_bundle(&bundle("NA1_OK")).unwrap();
+
+        let report = db.verify_integrity().unwrap();
+        assert_eq!(report.matches_checked, 1);
+        assert!(report.is_clean(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn test_broken_matches_are_reported() {
+        let db = Database::new(":memory:").unwrap();
+
+        let mut missing_player = bundle("NA1_MISSING");
+        missing_player.participants.pop();
+        db.store_match_bundle(&missing_player).unwrap();
+
+        let mut wrong_team = bundle("NA1_TEAM");
+        wrong_team.participants[0].team_id = 300;
+        wrong_team.teams[1].win = true;
+        for participant in &mut wrong_team.participants[5..] {
+            participant.win = true;
+        }
+        db.store_match_bundle(&wrong_team).unwrap();
+
+        let mut duplicate = bundle("NA1_DUP");
+        duplicate.participants[1].participant_id = 1;
+        db.store_match_bundle(&duplicate).unwrap();
+
+        let mut impossible = bundle("NA1_BAD");
+        impossible.participants[2].kills = -3;
+        impossible.match_data.game_duration = 0;
+        db.store_match_bundle(&impossible).unwrap();
+
+        let report = db.verify_integrity().unwrap();
+        assert_eq!(kinds(&report, "NA1_MISSING"), vec![IssueKind::Incomplete]);
+        assert_eq!(
+            kinds(&report, "NA1_TEAM"),
+            vec![IssueKind::TeamMismatch, IssueKind::TeamMismatch]
+        );
+        assert_eq!(kinds(&report, "NA1_DUP"), vec![IssueKind::Duplicate]);
+        assert_eq!(
+            kinds(&report, "NA1_BAD"),
+            vec![IssueKind::ImpossibleValue, IssueKind::ImpossibleValue]
+        );
+        assert_eq!(
+            report.broken_matches(),
+            ["NA1_BAD", "NA1_DUP", "NA1_MISSING", "NA1_TEAM"]
+                .iter()
+                .map(|id| (id.to_string(), "na1".to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_arena_matches_have_sixteen_players() {
+        let db = Database::new(":memory:").unwrap();
+        let mut arena = bundle("NA1_ARENA");
+        arena.match_data.game_mode = "CHERRY".to_string();
+        arena.teams.clear();
+        arena.participants = (0..16)
+            .map(|i| DbParticipant {
+                match_id: "NA1_ARENA".to_string(),
+                puuid: format!("arena-{}", i),
+                participant_id: i + 1,
+                team_id: 0,
+                champion_level: 18,
+                ..Default::default()
+            })
+            .collect();
+        db.store_match_bundle(&arena).unwrap();
+
+        assert!(db.verify_integrity().unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_repairs_remove_broken_matches_and_orphans() {
+        let db = Database::new(":memory:").unwrap();
+        db.store_match_bundle(&bundle("NA1_KEEP")).unwrap();
+        db.store_match_bundle(&bundle("NA1_DROP")).unwrap();
+        db.execute("DELETE FROM matches WHERE match_id = 'NA1_DROP'", &[])
+            .unwrap();
+
+        let report = db.verify_integrity().unwrap();
+        assert!(report.has_orphans());
+        assert_eq!(report.orphaned_rows, 12);
+        assert!(report.broken_matches().is_empty());
+        assert_eq!(db.delete_orphaned_rows().unwrap(), 12);
+
+        db.delete_match("NA1_KEEP").unwrap();
+        let report = db.verify_integrity().unwrap();
+        assert_eq!(report.matches_checked, 0);
+        assert!(report.is_clean());
+        assert_eq!(db.get_participants_count().unwrap(), 0);
+    }
+}
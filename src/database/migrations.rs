@@ -0,0 +1,254 @@
+use super::schema::{Schema, BASELINE_VERSION};
+use chrono::Utc;
+use rusqlite::{params, Connection, Result as SqliteResult};
+
+/// A schema change applied on top of the baseline created by `Schema::initialize`
+#[derive(Debug)]
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Embedded migrations, in the order they are applied. Never edit a migration
+/// that has shipped; add a new one with the next version instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        name: "summoner_ranks_and_match_strata",
+        sql: include_str!("../../migrations/0002_summoner_ranks_and_match_strata.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "failed_tasks",
+        sql: include_str!("../../migrations/0003_failed_tasks.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "pending_tasks",
+        sql: include_str!("../../migrations/0004_pending_tasks.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "participant_details",
+        sql: include_str!("../../migrations/0005_participant_details.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "raw_payloads",
+        sql: include_str!("../../migrations/0006_raw_payloads.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "match_stored_seq",
+        sql: include_str!("../../migrations/0007_match_stored_seq.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "api_call_rollups",
+        sql: include_str!("../../migrations/0008_api_call_rollups.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "match_hook_rows",
+        sql: include_str!("../../migrations/0009_match_hook_rows.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "vetoed_matches",
+        sql: include_str!("../../migrations/0010_vetoed_matches.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "pruned_matches",
+        sql: include_str!("../../migrations/0011_pruned_matches.sql"),
+    },
+];
+
+/// Applied or pending state of a single schema version
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i32,
+    pub name: String,
+    /// `None` while the migration is pending
+    pub applied_at: Option<String>,
+}
+
+/// Create the table that records applied schema versions
+pub fn create_schema_version_table(conn: &Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Record a schema version as applied
+pub fn record_version(conn: &Connection, version: i32, name: &str) -> SqliteResult<()> {
+    conn.execute(
+        "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+        params![version, name, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+/// Migrations newer than the database's current version
+pub fn pending(conn: &Connection) -> SqliteResult<Vec<&'static Migration>> {
+    let current = Schema::get_version(conn)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > current).collect())
+}
+
+/// Apply one migration and record it, all in a single transaction
+pub fn apply(conn: &Connection, migration: &Migration) -> SqliteResult<()> {
+    log::info!(
+        "Applying schema migration {} ({})",
+        migration.version,
+        migration.name
+    );
+    let tx = conn.unchecked_transaction()?;
+    tx.execute_batch(migration.sql)?;
+    record_version(&tx, migration.version, migration.name)?;
+    tx.commit()
+}
+
+/// Apply every pending migration in order, stopping at the first failure
+pub fn apply_pending(conn: &Connection) -> SqliteResult<Vec<&'static Migration>> {
+    let pending = pending(conn)?;
+    for migration in &pending {
+        apply(conn, migration)?;
+    }
+    Ok(pending)
+}
+
+/// Baseline plus every embedded migration, with the time each was applied
+pub fn status(conn: &Connection) -> SqliteResult<Vec<MigrationStatus>> {
+    let applied: Vec<(i32, String)> = if has_schema_version_table(conn)? {
+        let mut stmt = conn.prepare("SELECT version, applied_at FROM schema_version")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<SqliteResult<_>>()?
+    } else {
+        Vec::new()
+    };
+    let applied_at = |version: i32| {
+        applied
+            .iter()
+            .find(|(v, _)| *v == version)
+            .map(|(_, at)| at.clone())
+    };
+
+    let mut statuses = vec![MigrationStatus {
+        version: BASELINE_VERSION,
+        name: "baseline".to_string(),
+        applied_at: applied_at(BASELINE_VERSION),
+    }];
+    statuses.extend(MIGRATIONS.iter().map(|m| MigrationStatus {
+        version: m.version,
+        name: m.name.to_string(),
+        applied_at: applied_at(m.version),
+    }));
+    Ok(statuses)
+}
+
+/// Databases created before schema versioning have no schema_version table
+pub fn has_schema_version_table(conn: &Connection) -> SqliteResult<bool> {
+    let count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='schema_version'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::SCHEMA_VERSION;
+
+    fn table_exists(conn: &Connection, name: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name=?1",
+            [name],
+            |row| row.get::<_, i32>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        let mut previous = BASELINE_VERSION;
+        for migration in MIGRATIONS {
+            assert_eq!(migration.version, previous + 1, "{}", migration.name);
+            previous = migration.version;
+        }
+        assert_eq!(previous, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_fresh_database_is_fully_migrated() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+
+        assert_eq!(Schema::get_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(pending(&conn).unwrap().is_empty());
+        assert!(status(&conn)
+            .unwrap()
+            .iter()
+            .all(|s| s.applied_at.is_some()));
+
+        // Initializing again is a no-op
+        Schema::initialize(&conn).unwrap();
+        let rows: i32 = conn
+            .query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows as usize, MIGRATIONS.len() + 1);
+    }
+
+    #[test]
+    fn test_unversioned_database_is_upgraded() {
+        // A database created before versioning has the baseline tables but no
+        // schema_version table
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::create_baseline(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO summoners (puuid, summoner_name) VALUES ('p1', 'Existing')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(Schema::get_version(&conn).unwrap(), 0);
+        assert!(status(&conn)
+            .unwrap()
+            .iter()
+            .all(|s| s.applied_at.is_none()));
+
+        Schema::initialize(&conn).unwrap();
+        assert_eq!(Schema::get_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(table_exists(&conn, "failed_tasks"));
+        assert!(table_exists(&conn, "pending_tasks"));
+        assert!(table_exists(&conn, "participant_perks"));
+        let name: String = conn
+            .query_row("SELECT summoner_name FROM summoners", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "Existing");
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        Schema::initialize(&conn).unwrap();
+
+        let broken = Migration {
+            version: SCHEMA_VERSION + 1,
+            name: "broken",
+            sql: "CREATE TABLE half_done (id INTEGER); INSERT INTO no_such_table VALUES (1);",
+        };
+        assert!(apply(&conn, &broken).is_err());
+        assert!(!table_exists(&conn, "half_done"));
+        assert_eq!(Schema::get_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+}
@@ -1,5 +1,42 @@
+mod archive;
 mod connection;
+mod integrity;
+mod migrations;
 mod operations;
+mod postgres;
+mod retention;
 mod schema;
+mod storage;
 
+pub use archive::{decode_payload, encode_payload};
 pub use connection::Database;
+pub use integrity::{IntegrityIssue, IntegrityReport, IssueKind};
+pub use migrations::MigrationStatus;
+pub use postgres::PostgresStorage;
+pub use retention::endpoint_template;
+pub use storage::{connect, is_postgres_url, redact_url, Storage};
+
+/// Failure of the storage backend itself, as opposed to an API error. Both backends
+/// report their errors through it, so a failed task can be recognised as a storage
+/// failure whichever database is in use.
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Postgres(#[from] tokio_postgres::Error),
+    #[error("Database writer has stopped")]
+    WriterStopped,
+    /// A stored value the crawler cannot read back
+    #[error("Invalid stored value: {0}")]
+    InvalidValue(String),
+}
+
+/// Tables holding rows that belong to a match, replaced whenever the match is stored
+pub(crate) const CHILD_TABLES: &[&str] = &[
+    "teams",
+    "bans",
+    "participants",
+    "participant_perks",
+    "participant_challenges",
+];
@@ -1,141 +1,73 @@
-use super::Database;
+use super::{Database, CHILD_TABLES};
 use crate::models::database::*;
 use crate::Result;
+use chrono::Utc;
+use rusqlite::{params, Connection, Result as SqliteResult};
 
 impl Database {
     pub fn insert_summoner(&self, summoner: &DbSummoner) -> Result<()> {
-        self.execute(
-            "INSERT OR REPLACE INTO summoners 
-             (puuid, summoner_id, account_id, summoner_name, profile_icon_id, summoner_level, region, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            &[
-                &summoner.puuid,
-                &summoner.summoner_id,
-                &summoner.account_id,
-                &summoner.summoner_name,
-                &summoner.profile_icon_id,
-                &summoner.summoner_level,
-                &summoner.region,
-                &summoner.created_at.to_rfc3339(),
-                &summoner.updated_at.to_rfc3339(),
-            ],
-        )?;
-        Ok(())
+        let summoner = summoner.clone();
+        self.write(move |conn| write_summoner(conn, &summoner))
     }
 
     pub fn insert_match(&self, match_data: &DbMatch) -> Result<()> {
-        self.execute(
-            "INSERT OR REPLACE INTO matches 
-             (match_id, game_creation, game_duration, game_end_timestamp, game_id, game_mode, game_name, game_type, game_version, map_id, platform_id, queue_id, tournament_code, region, created_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
-            &[
-                &match_data.match_id,
-                &match_data.game_creation,
-                &match_data.game_duration,
-                &match_data.game_end_timestamp,
-                &match_data.game_id,
-                &match_data.game_mode,
-                &match_data.game_name,
-                &match_data.game_type,
-                &match_data.game_version,
-                &match_data.map_id,
-                &match_data.platform_id,
-                &match_data.queue_id,
-                &match_data.tournament_code,
-                &match_data.region,
-                &match_data.created_at.to_rfc3339(),
-            ],
-        )?;
-        Ok(())
+        let match_data = match_data.clone();
+        self.write(move |conn| write_match(conn, &match_data))
     }
 
     pub fn insert_participant(&self, participant: &DbParticipant) -> Result<()> {
-        self.execute(
-            "INSERT OR REPLACE INTO participants 
-             (match_id, puuid, summoner_name, champion_id, champion_name, team_id, position, individual_position, 
-              kills, deaths, assists, total_damage_dealt, total_damage_dealt_to_champions, total_damage_taken, 
-              gold_earned, gold_spent, turret_kills, inhibitor_kills, total_minions_killed, neutral_minions_killed, 
-              champion_level, items_0, items_1, items_2, items_3, items_4, items_5, items_6, 
-              summoner_spell_1, summoner_spell_2, primary_rune_tree, secondary_rune_tree, 
-              win, first_blood_kill, first_tower_kill) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35)",
-            &[
-                &participant.match_id,
-                &participant.puuid,
-                &participant.summoner_name,
-                &participant.champion_id,
-                &participant.champion_name,
-                &participant.team_id,
-                &participant.position,
-                &participant.individual_position,
-                &participant.kills,
-                &participant.deaths,
-                &participant.assists,
-                &participant.total_damage_dealt,
-                &participant.total_damage_dealt_to_champions,
-                &participant.total_damage_taken,
-                &participant.gold_earned,
-                &participant.gold_spent,
-                &participant.turret_kills,
-                &participant.inhibitor_kills,
-                &participant.total_minions_killed,
-                &participant.neutral_minions_killed,
-                &participant.champion_level,
-                &participant.items_0,
-                &participant.items_1,
-                &participant.items_2,
-                &participant.items_3,
-                &participant.items_4,
-                &participant.items_5,
-                &participant.items_6,
-                &participant.summoner_spell_1,
-                &participant.summoner_spell_2,
-                &participant.primary_rune_tree,
-                &participant.secondary_rune_tree,
-                &participant.win,
-                &participant.first_blood_kill,
-                &participant.first_tower_kill,
-            ],
-        )?;
-        Ok(())
+        let participant = participant.clone();
+        self.write(move |conn| write_participant(conn, &participant))
     }
 
     pub fn insert_team(&self, team: &DbTeam) -> Result<()> {
-        self.execute(
-            "INSERT OR REPLACE INTO teams 
-             (match_id, team_id, win, first_baron, first_dragon, first_inhibitor, first_rift_herald, first_tower, 
-              baron_kills, dragon_kills, inhibitor_kills, rift_herald_kills, tower_kills) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
-            &[
-                &team.match_id,
-                &team.team_id,
-                &team.win,
-                &team.first_baron,
-                &team.first_dragon,
-                &team.first_inhibitor,
-                &team.first_rift_herald,
-                &team.first_tower,
-                &team.baron_kills,
-                &team.dragon_kills,
-                &team.inhibitor_kills,
-                &team.rift_herald_kills,
-                &team.tower_kills,
-            ],
-        )?;
-        Ok(())
+        let team = team.clone();
+        self.write(move |conn| write_team(conn, &team))
     }
 
     pub fn insert_ban(&self, ban: &DbBan) -> Result<()> {
-        self.execute(
-            "INSERT INTO bans (match_id, team_id, champion_id, pick_turn) VALUES (?1, ?2, ?3, ?4)",
-            &[
-                &ban.match_id,
-                &ban.team_id,
-                &ban.champion_id,
-                &ban.pick_turn,
-            ],
+        let ban = ban.clone();
+        self.write(move |conn| write_ban(conn, &ban))
+    }
+
+    /// Store a match with its teams, bans, participants and stratum in one transaction.
+    /// Child rows already stored for the match are replaced, so storing it again is safe.
+    pub fn store_match_bundle(&self, bundle: &DbMatchBundle) -> Result<()> {
+        let bundle = bundle.clone();
+        self.transaction(move |tx| write_match_bundle(tx, &bundle))
+    }
+
+    pub fn record_vetoed_match(&self, vetoed: &DbVetoedMatch) -> Result<()> {
+        let vetoed = vetoed.clone();
+        self.write(move |conn| write_vetoed_match(conn, &vetoed))
+    }
+
+    pub fn match_vetoed(&self, match_id: &str) -> Result<bool> {
+        let count: i64 = self.query_row(
+            "SELECT COUNT(*) FROM vetoed_matches WHERE match_id = ?1",
+            &[&match_id],
+            |row| row.get(0),
         )?;
-        Ok(())
+        Ok(count > 0)
+    }
+
+    pub fn match_pruned(&self, match_id: &str) -> Result<bool> {
+        let count: i64 = self.query_row(
+            "SELECT COUNT(*) FROM pruned_matches WHERE match_id = ?1",
+            &[&match_id],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn store_hook_rows(
+        &self,
+        match_id: &str,
+        hook: &str,
+        rows: &[DbMatchHookRow],
+    ) -> Result<()> {
+        let (match_id, hook, rows) = (match_id.to_string(), hook.to_string(), rows.to_vec());
+        self.transaction(move |tx| write_hook_rows(tx, &match_id, &hook, &rows))
     }
 
     pub fn insert_active_game(&self, game: &DbActiveGame) -> Result<()> {
@@ -159,35 +91,13 @@ impl Database {
     }
 
     pub fn log_api_call(&self, call: &DbApiCall) -> Result<()> {
-        self.execute(
-            "INSERT INTO api_calls (endpoint, region, timestamp, response_code, rate_limit_remaining) 
-             VALUES (?1, ?2, ?3, ?4, ?5)",
-            &[
-                &call.endpoint,
-                &call.region,
-                &call.timestamp.to_rfc3339(),
-                &call.response_code,
-                &call.rate_limit_remaining,
-            ],
-        )?;
-        Ok(())
+        let call = call.clone();
+        self.write(move |conn| write_api_call(conn, &call))
     }
 
     pub fn update_crawler_state(&self, state: &DbCrawlerState) -> Result<()> {
-        self.execute(
-            "UPDATE crawler_state SET 
-             last_processed_summoner = ?1, total_summoners_processed = ?2, total_matches_processed = ?3, 
-             queue_size = ?4, last_update = ?5 
-             WHERE id = 1",
-            &[
-                &state.last_processed_summoner,
-                &state.total_summoners_processed,
-                &state.total_matches_processed,
-                &state.queue_size,
-                &state.last_update.to_rfc3339(),
-            ],
-        )?;
-        Ok(())
+        let state = state.clone();
+        self.write(move |conn| write_crawler_state(conn, &state))
     }
 
     pub fn get_crawler_state(&self) -> Result<Option<DbCrawlerState>> {
@@ -280,6 +190,771 @@ impl Database {
             self.query_row("SELECT COUNT(*) FROM participants", &[], |row| row.get(0))?;
         Ok(count)
     }
+
+    pub fn upsert_summoner_rank(&self, rank: &DbSummonerRank) -> Result<()> {
+        let rank = rank.clone();
+        self.write(move |conn| write_summoner_rank(conn, &rank))
+    }
+
+    pub fn get_summoner_tier(&self, puuid: &str, queue_type: &str) -> Result<Option<Tier>> {
+        let tiers = self.query_map(
+            "SELECT tier FROM summoner_ranks WHERE puuid = ?1 AND queue_type = ?2",
+            &[&puuid, &queue_type],
+            |row| row.get::<_, String>(0),
+        )?;
+        Ok(tiers.first().and_then(|tier| tier.parse().ok()))
+    }
+
+    /// Like `get_existing_summoners_for_update`, but with the known solo/duo tier of each player
+    pub fn get_existing_summoners_with_tier(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<(String, String, Option<Tier>)>> {
+        let summoners = self.query_map(
+            "SELECT s.puuid, s.region, r.tier FROM summoners s 
+             LEFT JOIN summoner_ranks r ON r.puuid = s.puuid AND r.queue_type = 'RANKED_SOLO_5x5' 
+             ORDER BY s.updated_at ASC 
+             LIMIT ?1",
+            &[&limit],
+            |row| {
+                let tier: Option<String> = row.get(2)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    tier.and_then(|t| t.parse().ok()),
+                ))
+            },
+        )?;
+        Ok(summoners)
+    }
+
+    pub fn insert_match_stratum(&self, stratum: &DbMatchStratum) -> Result<()> {
+        let stratum = stratum.clone();
+        self.write(move |conn| write_match_stratum(conn, &stratum))
+    }
+
+    /// Number of stored matches per (region, tier, patch) stratum
+    pub fn get_stratum_counts(&self) -> Result<Vec<StratumCount>> {
+        let counts = self.query_map(
+            "SELECT region, tier, patch, COUNT(*) FROM match_strata GROUP BY region, tier, patch",
+            &[],
+            |row| {
+                let tier: Option<String> = row.get(1)?;
+                Ok((
+                    row.get::<_, String>(0)?,
+                    tier.and_then(|t| t.parse().ok()),
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )?;
+        Ok(counts)
+    }
+
+    /// Record a failed task in the dead-letter queue. Repeated failures of the same
+    /// summoner or match accumulate attempts and keep the latest error.
+    pub fn record_failed_task(&self, task: &DbFailedTask) -> Result<()> {
+        let task = task.clone();
+        self.write(move |conn| write_failed_task(conn, &task))
+    }
+
+    /// Remove a task from the dead-letter queue once it has been processed successfully
+    pub fn resolve_failed_task(&self, task_type: FailedTaskType, target_id: &str) -> Result<bool> {
+        let target_id = target_id.to_string();
+        self.write(move |conn| delete_failed_task(conn, task_type, &target_id))
+    }
+
+    pub fn get_failed_tasks(
+        &self,
+        filter: &FailedTaskFilter,
+        status: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<DbFailedTask>> {
+        let tasks = self.query_map(
+            "SELECT id, task_type, target_id, region, summoner_name, error_kind, http_status, attempts,
+                    last_error, status, first_failed_at, last_failed_at
+             FROM failed_tasks
+             WHERE (?1 IS NULL OR id = ?1) AND (?2 IS NULL OR task_type = ?2)
+               AND (?3 IS NULL OR error_kind = ?3) AND (?4 IS NULL OR status = ?4)
+             ORDER BY last_failed_at DESC
+             LIMIT ?5",
+            &[
+                &filter.id,
+                &filter.task_type.map(|t| t.as_str()),
+                &filter.error_kind,
+                &status,
+                &limit,
+            ],
+            |row| {
+                let task_type: String = row.get(1)?;
+                let first_failed_at: String = row.get(10)?;
+                let last_failed_at: String = row.get(11)?;
+                Ok(DbFailedTask {
+                    id: row.get(0)?,
+                    task_type: task_type.parse().map_err(|_| {
+                        rusqlite::Error::InvalidColumnType(
+                            1,
+                            "TEXT".to_string(),
+                            rusqlite::types::Type::Text,
+                        )
+                    })?,
+                    target_id: row.get(2)?,
+                    region: row.get(3)?,
+                    summoner_name: row.get(4)?,
+                    error_kind: row.get(5)?,
+                    http_status: row.get(6)?,
+                    attempts: row.get(7)?,
+                    last_error: row.get(8)?,
+                    status: row.get(9)?,
+                    first_failed_at: first_failed_at.parse().unwrap_or_else(|_| Utc::now()),
+                    last_failed_at: last_failed_at.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        )?;
+        Ok(tasks)
+    }
+
+    /// Dead-letter counts per (task_type, error_kind, status)
+    pub fn get_failed_task_summary(&self) -> Result<Vec<(String, String, String, i64)>> {
+        let summary = self.query_map(
+            "SELECT task_type, error_kind, status, COUNT(*) FROM failed_tasks
+             GROUP BY task_type, error_kind, status ORDER BY task_type, error_kind, status",
+            &[],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        Ok(summary)
+    }
+
+    /// Mark failed tasks matching the filter for requeueing; the crawler picks them up on
+    /// its next start. Returns the number of tasks marked.
+    pub fn requeue_failed_tasks(&self, filter: &FailedTaskFilter) -> Result<usize> {
+        let filter = filter.clone();
+        self.write(move |conn| requeue_failed(conn, &filter))
+    }
+
+    /// Persist the crawl queue, replacing whatever was saved before
+    pub fn save_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        let tasks = tasks.to_vec();
+        self.transaction(move |tx| write_pending_tasks(tx, &tasks, true))
+    }
+
+    /// Add tasks to the persisted crawl queue, keeping what was saved before
+    pub fn append_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        let tasks = tasks.to_vec();
+        self.transaction(move |tx| write_pending_tasks(tx, &tasks, false))
+    }
+
+    pub fn get_pending_tasks_count(&self) -> Result<i64> {
+        let count: i64 =
+            self.query_row("SELECT COUNT(*) FROM pending_tasks", &[], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Load and clear the persisted crawl queue
+    pub fn take_pending_tasks(&self) -> Result<Vec<SummonerTask>> {
+        self.transaction(|tx| take_pending(tx))
+    }
+
+    /// An archived API response, if the match was crawled with the raw archive enabled
+    pub fn get_raw_payload(
+        &self,
+        match_id: &str,
+        kind: RawPayloadKind,
+    ) -> Result<Option<DbRawPayload>> {
+        let payloads = self.query_map(
+            "SELECT match_id, region, payload, raw_size, fetched_at FROM raw_payloads
+             WHERE match_id = ?1 AND kind = ?2",
+            &[&match_id, &kind.as_str()],
+            |row| {
+                let fetched_at: String = row.get(4)?;
+                Ok(DbRawPayload {
+                    match_id: row.get(0)?,
+                    kind,
+                    region: row.get(1)?,
+                    payload: row.get(2)?,
+                    raw_size: row.get(3)?,
+                    fetched_at: fetched_at.parse().unwrap_or_else(|_| Utc::now()),
+                })
+            },
+        )?;
+        Ok(payloads.into_iter().next())
+    }
+
+    /// Match IDs with an archived match payload, in order, starting after `after`
+    pub fn get_archived_match_ids(&self, after: Option<&str>, limit: i32) -> Result<Vec<String>> {
+        let ids = self.query_map(
+            "SELECT match_id FROM raw_payloads
+             WHERE kind = 'match' AND (?1 IS NULL OR match_id > ?1)
+             ORDER BY match_id LIMIT ?2",
+            &[&after, &limit],
+            |row| row.get(0),
+        )?;
+        Ok(ids)
+    }
+}
+
+pub(super) fn write_summoner(conn: &Connection, summoner: &DbSummoner) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO summoners 
+         (puuid, summoner_id, account_id, summoner_name, profile_icon_id, summoner_level, region, created_at, updated_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?
+    .execute(params![
+        summoner.puuid,
+        summoner.summoner_id,
+        summoner.account_id,
+        summoner.summoner_name,
+        summoner.profile_icon_id,
+        summoner.summoner_level,
+        summoner.region,
+        summoner.created_at.to_rfc3339(),
+        summoner.updated_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+pub(super) fn write_match_bundle(conn: &Connection, bundle: &DbMatchBundle) -> SqliteResult<()> {
+    let match_id = &bundle.match_data.match_id;
+    for table in CHILD_TABLES {
+        conn.prepare_cached(&format!("DELETE FROM {} WHERE match_id = ?1", table))?
+            .execute([match_id])?;
+    }
+
+    write_match(conn, &bundle.match_data)?;
+    for team in &bundle.teams {
+        write_team(conn, team)?;
+    }
+    for ban in &bundle.bans {
+        write_ban(conn, ban)?;
+    }
+    for participant in &bundle.participants {
+        write_participant(conn, participant)?;
+    }
+    for perk in &bundle.perks {
+        write_participant_perk(conn, perk)?;
+    }
+    for challenge in &bundle.challenges {
+        write_participant_challenge(conn, challenge)?;
+    }
+    if let Some(stratum) = &bundle.stratum {
+        write_match_stratum(conn, stratum)?;
+    }
+    for payload in &bundle.raw_payloads {
+        write_raw_payload(conn, payload)?;
+    }
+    Ok(())
+}
+
+pub(super) fn write_vetoed_match(conn: &Connection, vetoed: &DbVetoedMatch) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO vetoed_matches (match_id, region, hook, vetoed_at)
+         VALUES (?1, ?2, ?3, ?4)",
+    )?
+    .execute(params![
+        vetoed.match_id,
+        vetoed.region,
+        vetoed.hook,
+        vetoed.vetoed_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+/// Replace the rows `hook` stored for a match
+pub(super) fn write_hook_rows(
+    conn: &Connection,
+    match_id: &str,
+    hook: &str,
+    rows: &[DbMatchHookRow],
+) -> SqliteResult<()> {
+    conn.prepare_cached("DELETE FROM match_hook_rows WHERE match_id = ?1 AND hook = ?2")?
+        .execute([match_id, hook])?;
+    for row in rows {
+        conn.prepare_cached(
+            "INSERT INTO match_hook_rows (match_id, hook, kind, data, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?
+        .execute(params![
+            row.match_id,
+            row.hook,
+            row.kind,
+            row.data.to_string(),
+            row.created_at.to_rfc3339(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub(super) fn write_api_call(conn: &Connection, call: &DbApiCall) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO api_calls (endpoint, region, timestamp, response_code, rate_limit_remaining) 
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![
+        call.endpoint,
+        call.region,
+        call.timestamp.to_rfc3339(),
+        call.response_code,
+        call.rate_limit_remaining,
+    ])?;
+    Ok(())
+}
+
+pub(super) fn write_crawler_state(conn: &Connection, state: &DbCrawlerState) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "UPDATE crawler_state SET 
+         last_processed_summoner = ?1, total_summoners_processed = ?2, total_matches_processed = ?3, 
+         queue_size = ?4, last_update = ?5 
+         WHERE id = 1",
+    )?
+    .execute(params![
+        state.last_processed_summoner,
+        state.total_summoners_processed,
+        state.total_matches_processed,
+        state.queue_size,
+        state.last_update.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+pub(super) fn write_summoner_rank(conn: &Connection, rank: &DbSummonerRank) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO summoner_ranks 
+         (puuid, queue_type, tier, rank, league_points, wins, losses, updated_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?
+    .execute(params![
+        rank.puuid,
+        rank.queue_type,
+        rank.tier,
+        rank.rank,
+        rank.league_points,
+        rank.wins,
+        rank.losses,
+        rank.updated_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+pub(super) fn write_failed_task(conn: &Connection, task: &DbFailedTask) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO failed_tasks
+         (task_type, target_id, region, summoner_name, error_kind, http_status, attempts, last_error, status, first_failed_at, last_failed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'failed', ?9, ?10)
+         ON CONFLICT(task_type, target_id) DO UPDATE SET
+         error_kind = excluded.error_kind, http_status = excluded.http_status,
+         attempts = failed_tasks.attempts + excluded.attempts, last_error = excluded.last_error,
+         status = 'failed', last_failed_at = excluded.last_failed_at",
+    )?
+    .execute(params![
+        task.task_type.as_str(),
+        task.target_id,
+        task.region,
+        task.summoner_name,
+        task.error_kind,
+        task.http_status,
+        task.attempts,
+        task.last_error,
+        task.first_failed_at.to_rfc3339(),
+        task.last_failed_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+/// Whether a failed task was removed
+pub(super) fn delete_failed_task(
+    conn: &Connection,
+    task_type: FailedTaskType,
+    target_id: &str,
+) -> SqliteResult<bool> {
+    let removed = conn
+        .prepare_cached("DELETE FROM failed_tasks WHERE task_type = ?1 AND target_id = ?2")?
+        .execute([task_type.as_str(), target_id])?;
+    Ok(removed > 0)
+}
+
+pub(super) fn requeue_failed(conn: &Connection, filter: &FailedTaskFilter) -> SqliteResult<usize> {
+    conn.prepare_cached(
+        "UPDATE failed_tasks SET status = 'requeued'
+         WHERE status = 'failed' AND (?1 IS NULL OR id = ?1) AND (?2 IS NULL OR task_type = ?2)
+           AND (?3 IS NULL OR error_kind = ?3)",
+    )?
+    .execute(params![
+        filter.id,
+        filter.task_type.map(|t| t.as_str()),
+        filter.error_kind,
+    ])
+}
+
+/// Insert tasks into the persisted queue, first clearing it when `replace` is set
+pub(super) fn write_pending_tasks(
+    conn: &Connection,
+    tasks: &[SummonerTask],
+    replace: bool,
+) -> SqliteResult<()> {
+    if replace {
+        conn.execute("DELETE FROM pending_tasks", [])?;
+    }
+    for task in tasks {
+        write_pending_task(conn, task)?;
+    }
+    Ok(())
+}
+
+/// Load and clear the persisted queue
+pub(super) fn take_pending(conn: &Connection) -> SqliteResult<Vec<SummonerTask>> {
+    let tasks = conn
+        .prepare_cached(
+            "SELECT puuid, summoner_name, region, priority, tier, retries, retry_class, not_before, added_at
+             FROM pending_tasks",
+        )?
+        .query_map([], |row| {
+            let priority: String = row.get(3)?;
+            let tier: Option<String> = row.get(4)?;
+            let retry_class: Option<String> = row.get(6)?;
+            let not_before: Option<String> = row.get(7)?;
+            let added_at: String = row.get(8)?;
+            Ok(SummonerTask {
+                puuid: row.get(0)?,
+                summoner_name: row.get(1)?,
+                region: row.get(2)?,
+                priority: priority.parse().unwrap_or(SummonerPriority::Low),
+                added_at: added_at.parse().unwrap_or_else(|_| Utc::now()),
+                retries: row.get(5)?,
+                tier: tier.and_then(|t| t.parse().ok()),
+                not_before: not_before.and_then(|t| t.parse().ok()),
+                retry_class: retry_class.and_then(|c| c.parse().ok()),
+            })
+        })?
+        .collect::<SqliteResult<Vec<_>>>()?;
+    conn.execute("DELETE FROM pending_tasks", [])?;
+    Ok(tasks)
+}
+
+fn write_match(conn: &Connection, match_data: &DbMatch) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO matches 
+         (match_id, game_creation, game_duration, game_end_timestamp, game_id, game_mode, game_name, game_type, game_version, map_id, platform_id, queue_id, tournament_code, region, created_at, stored_seq) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                 (SELECT COALESCE(MAX(stored_seq), 0) + 1 FROM matches))",
+    )?
+    .execute(params![
+        match_data.match_id,
+        match_data.game_creation,
+        match_data.game_duration,
+        match_data.game_end_timestamp,
+        match_data.game_id,
+        match_data.game_mode,
+        match_data.game_name,
+        match_data.game_type,
+        match_data.game_version,
+        match_data.map_id,
+        match_data.platform_id,
+        match_data.queue_id,
+        match_data.tournament_code,
+        match_data.region,
+        match_data.created_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+fn write_participant(conn: &Connection, participant: &DbParticipant) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO participants
+         (match_id, puuid, summoner_name, champion_id, champion_name, team_id, position,
+          individual_position, kills, deaths, assists, total_damage_dealt,
+          total_damage_dealt_to_champions, total_damage_taken, gold_earned, gold_spent,
+          turret_kills, inhibitor_kills, total_minions_killed, neutral_minions_killed,
+          champion_level, items_0, items_1, items_2, items_3, items_4, items_5, items_6,
+          summoner_spell_1, summoner_spell_2, primary_rune_tree, secondary_rune_tree, win,
+          first_blood_kill, first_tower_kill, all_in_pings, assist_me_pings, baron_kills,
+          bounty_level, champ_experience, command_pings, champion_transform, consumables_purchased,
+          damage_dealt_to_buildings, damage_dealt_to_objectives, damage_dealt_to_turrets,
+          damage_self_mitigated, detector_wards_placed, double_kills, dragon_kills,
+          eligible_for_progression, enemy_missing_pings, enemy_vision_pings, first_blood_assist,
+          first_tower_assist, game_ended_in_early_surrender, game_ended_in_surrender, hold_pings,
+          get_back_pings, inhibitor_takedowns, inhibitors_lost, items_purchased, killing_sprees,
+          largest_critical_strike, largest_killing_spree, largest_multi_kill,
+          longest_time_spent_living, magic_damage_dealt, magic_damage_dealt_to_champions,
+          magic_damage_taken, need_vision_pings, nexus_kills, nexus_takedowns, nexus_lost,
+          objectives_stolen, objectives_stolen_assists, on_my_way_pings, participant_id,
+          penta_kills, physical_damage_dealt, physical_damage_dealt_to_champions,
+          physical_damage_taken, placement, player_augment1, player_augment2, player_augment3,
+          player_augment4, player_subteam_id, push_pings, profile_icon, quadra_kills,
+          riot_id_game_name, riot_id_tagline, role, sight_wards_bought_in_game, spell1_casts,
+          spell2_casts, spell3_casts, spell4_casts, subteam_placement, summoner1_casts,
+          summoner2_casts, summoner_id, summoner_level, team_early_surrendered, team_position,
+          time_ccing_others, time_played, total_ally_jungle_minions_killed,
+          total_damage_shielded_on_teammates, total_enemy_jungle_minions_killed, total_heal,
+          total_heals_on_teammates, total_time_cc_dealt, total_time_spent_dead, total_units_healed,
+          triple_kills, true_damage_dealt, true_damage_dealt_to_champions, true_damage_taken,
+          turret_takedowns, turrets_lost, unreal_kills, vision_score, vision_cleared_pings,
+          vision_wards_bought_in_game, wards_killed, wards_placed, missions)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+         ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38,
+         ?39, ?40, ?41, ?42, ?43, ?44, ?45, ?46, ?47, ?48, ?49, ?50, ?51, ?52, ?53, ?54, ?55, ?56,
+         ?57, ?58, ?59, ?60, ?61, ?62, ?63, ?64, ?65, ?66, ?67, ?68, ?69, ?70, ?71, ?72, ?73, ?74,
+         ?75, ?76, ?77, ?78, ?79, ?80, ?81, ?82, ?83, ?84, ?85, ?86, ?87, ?88, ?89, ?90, ?91, ?92,
+         ?93, ?94, ?95, ?96, ?97, ?98, ?99, ?100, ?101, ?102, ?103, ?104, ?105, ?106, ?107, ?108,
+         ?109, ?110, ?111, ?112, ?113, ?114, ?115, ?116, ?117, ?118, ?119, ?120, ?121, ?122, ?123,
+         ?124, ?125, ?126, ?127, ?128, ?129)",
+    )?
+    .execute(params![
+        participant.match_id,
+        participant.puuid,
+        participant.summoner_name,
+        participant.champion_id,
+        participant.champion_name,
+        participant.team_id,
+        participant.position,
+        participant.individual_position,
+        participant.kills,
+        participant.deaths,
+        participant.assists,
+        participant.total_damage_dealt,
+        participant.total_damage_dealt_to_champions,
+        participant.total_damage_taken,
+        participant.gold_earned,
+        participant.gold_spent,
+        participant.turret_kills,
+        participant.inhibitor_kills,
+        participant.total_minions_killed,
+        participant.neutral_minions_killed,
+        participant.champion_level,
+        participant.items_0,
+        participant.items_1,
+        participant.items_2,
+        participant.items_3,
+        participant.items_4,
+        participant.items_5,
+        participant.items_6,
+        participant.summoner_spell_1,
+        participant.summoner_spell_2,
+        participant.primary_rune_tree,
+        participant.secondary_rune_tree,
+        participant.win,
+        participant.first_blood_kill,
+        participant.first_tower_kill,
+        participant.all_in_pings,
+        participant.assist_me_pings,
+        participant.baron_kills,
+        participant.bounty_level,
+        participant.champ_experience,
+        participant.command_pings,
+        participant.champion_transform,
+        participant.consumables_purchased,
+        participant.damage_dealt_to_buildings,
+        participant.damage_dealt_to_objectives,
+        participant.damage_dealt_to_turrets,
+        participant.damage_self_mitigated,
+        participant.detector_wards_placed,
+        participant.double_kills,
+        participant.dragon_kills,
+        participant.eligible_for_progression,
+        participant.enemy_missing_pings,
+        participant.enemy_vision_pings,
+        participant.first_blood_assist,
+        participant.first_tower_assist,
+        participant.game_ended_in_early_surrender,
+        participant.game_ended_in_surrender,
+        participant.hold_pings,
+        participant.get_back_pings,
+        participant.inhibitor_takedowns,
+        participant.inhibitors_lost,
+        participant.items_purchased,
+        participant.killing_sprees,
+        participant.largest_critical_strike,
+        participant.largest_killing_spree,
+        participant.largest_multi_kill,
+        participant.longest_time_spent_living,
+        participant.magic_damage_dealt,
+        participant.magic_damage_dealt_to_champions,
+        participant.magic_damage_taken,
+        participant.need_vision_pings,
+        participant.nexus_kills,
+        participant.nexus_takedowns,
+        participant.nexus_lost,
+        participant.objectives_stolen,
+        participant.objectives_stolen_assists,
+        participant.on_my_way_pings,
+        participant.participant_id,
+        participant.penta_kills,
+        participant.physical_damage_dealt,
+        participant.physical_damage_dealt_to_champions,
+        participant.physical_damage_taken,
+        participant.placement,
+        participant.player_augment1,
+        participant.player_augment2,
+        participant.player_augment3,
+        participant.player_augment4,
+        participant.player_subteam_id,
+        participant.push_pings,
+        participant.profile_icon,
+        participant.quadra_kills,
+        participant.riot_id_game_name,
+        participant.riot_id_tagline,
+        participant.role,
+        participant.sight_wards_bought_in_game,
+        participant.spell1_casts,
+        participant.spell2_casts,
+        participant.spell3_casts,
+        participant.spell4_casts,
+        participant.subteam_placement,
+        participant.summoner1_casts,
+        participant.summoner2_casts,
+        participant.summoner_id,
+        participant.summoner_level,
+        participant.team_early_surrendered,
+        participant.team_position,
+        participant.time_ccing_others,
+        participant.time_played,
+        participant.total_ally_jungle_minions_killed,
+        participant.total_damage_shielded_on_teammates,
+        participant.total_enemy_jungle_minions_killed,
+        participant.total_heal,
+        participant.total_heals_on_teammates,
+        participant.total_time_cc_dealt,
+        participant.total_time_spent_dead,
+        participant.total_units_healed,
+        participant.triple_kills,
+        participant.true_damage_dealt,
+        participant.true_damage_dealt_to_champions,
+        participant.true_damage_taken,
+        participant.turret_takedowns,
+        participant.turrets_lost,
+        participant.unreal_kills,
+        participant.vision_score,
+        participant.vision_cleared_pings,
+        participant.vision_wards_bought_in_game,
+        participant.wards_killed,
+        participant.wards_placed,
+        participant.missions,
+    ])?;
+    Ok(())
+}
+
+fn write_participant_perk(conn: &Connection, perk: &DbParticipantPerk) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO participant_perks
+         (match_id, puuid, style, style_id, slot, perk_id, var1, var2, var3)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?
+    .execute(params![
+        perk.match_id,
+        perk.puuid,
+        perk.style,
+        perk.style_id,
+        perk.slot,
+        perk.perk_id,
+        perk.var1,
+        perk.var2,
+        perk.var3,
+    ])?;
+    Ok(())
+}
+
+fn write_participant_challenge(
+    conn: &Connection,
+    challenge: &DbParticipantChallenge,
+) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO participant_challenges (match_id, puuid, name, value, value_json)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?
+    .execute(params![
+        challenge.match_id,
+        challenge.puuid,
+        challenge.name,
+        challenge.value,
+        challenge.value_json,
+    ])?;
+    Ok(())
+}
+
+fn write_team(conn: &Connection, team: &DbTeam) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO teams 
+         (match_id, team_id, win, first_baron, first_dragon, first_inhibitor, first_rift_herald, first_tower, 
+          baron_kills, dragon_kills, inhibitor_kills, rift_herald_kills, tower_kills) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )?
+    .execute(params![
+        team.match_id,
+        team.team_id,
+        team.win,
+        team.first_baron,
+        team.first_dragon,
+        team.first_inhibitor,
+        team.first_rift_herald,
+        team.first_tower,
+        team.baron_kills,
+        team.dragon_kills,
+        team.inhibitor_kills,
+        team.rift_herald_kills,
+        team.tower_kills,
+    ])?;
+    Ok(())
+}
+
+fn write_ban(conn: &Connection, ban: &DbBan) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT INTO bans (match_id, team_id, champion_id, pick_turn) VALUES (?1, ?2, ?3, ?4)",
+    )?
+    .execute(params![
+        ban.match_id,
+        ban.team_id,
+        ban.champion_id,
+        ban.pick_turn,
+    ])?;
+    Ok(())
+}
+
+fn write_match_stratum(conn: &Connection, stratum: &DbMatchStratum) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO match_strata (match_id, region, tier, patch) VALUES (?1, ?2, ?3, ?4)",
+    )?
+    .execute(params![
+        stratum.match_id,
+        stratum.region,
+        stratum.tier.map(|t| t.as_str()),
+        stratum.patch,
+    ])?;
+    Ok(())
+}
+
+fn write_pending_task(conn: &Connection, task: &SummonerTask) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO pending_tasks
+         (puuid, summoner_name, region, priority, tier, retries, retry_class, not_before, added_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+    )?
+    .execute(params![
+        task.puuid,
+        task.summoner_name,
+        task.region,
+        task.priority.as_str(),
+        task.tier.map(|t| t.as_str()),
+        task.retries,
+        task.retry_class.map(|c| c.as_str()),
+        task.not_before.map(|t| t.to_rfc3339()),
+        task.added_at.to_rfc3339(),
+    ])?;
+    Ok(())
+}
+
+fn write_raw_payload(conn: &Connection, payload: &DbRawPayload) -> SqliteResult<()> {
+    conn.prepare_cached(
+        "INSERT OR REPLACE INTO raw_payloads (match_id, kind, region, payload, raw_size, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?
+    .execute(params![
+        payload.match_id,
+        payload.kind.as_str(),
+        payload.region,
+        payload.payload,
+        payload.raw_size,
+        payload.fetched_at.to_rfc3339(),
+    ])?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -309,6 +984,7 @@ mod tests {
         }
     }
 
+    #[allow(clippy::unnecessary_cast)]
     fn test_match() -> DbMatch {
         let now = Utc::now();
         let unique_id = now.timestamp_nanos_opt().unwrap_or(0);
@@ -369,6 +1045,11 @@ mod tests {
             win: true,
             first_blood_kill: false,
             first_tower_kill: true,
+            vision_score: 42,
+            wards_placed: 12,
+            damage_dealt_to_objectives: 8000,
+            team_position: "MIDDLE".to_string(),
+            ..Default::default()
         }
     }
 
@@ -834,4 +1515,322 @@ mod tests {
         let no_summoners = db.get_existing_summoners_for_update(0).unwrap();
         assert_eq!(no_summoners.len(), 0);
     }
+
+    #[test]
+    fn test_summoner_rank_operations() {
+        let db = create_test_database();
+        let summoner = test_summoner();
+        assert!(db.insert_summoner(&summoner).is_ok());
+
+        assert_eq!(
+            db.get_summoner_tier(&summoner.puuid, "RANKED_SOLO_5x5")
+                .unwrap(),
+            None
+        );
+
+        let rank = DbSummonerRank {
+            puuid: summoner.puuid.clone(),
+            queue_type: "RANKED_SOLO_5x5".to_string(),
+            tier: "GOLD".to_string(),
+            rank: "II".to_string(),
+            league_points: 42,
+            wins: 30,
+            losses: 28,
+            updated_at: Utc::now(),
+        };
+        assert!(db.upsert_summoner_rank(&rank).is_ok());
+        assert_eq!(
+            db.get_summoner_tier(&summoner.puuid, "RANKED_SOLO_5x5")
+                .unwrap(),
+            Some(Tier::Gold)
+        );
+
+        // Rank updates replace the previous standing
+        let mut promoted = rank.clone();
+        promoted.tier = "PLATINUM".to_string();
+        assert!(db.upsert_summoner_rank(&promoted).is_ok());
+
+        let summoners = db.get_existing_summoners_with_tier(10).unwrap();
+        assert_eq!(summoners.len(), 1);
+        assert_eq!(summoners[0].2, Some(Tier::Platinum));
+    }
+
+    #[test]
+    fn test_match_stratum_counts() {
+        let db = create_test_database();
+
+        for (i, tier) in [Some(Tier::Gold), Some(Tier::Gold), None]
+            .iter()
+            .enumerate()
+        {
+            let stratum = DbMatchStratum {
+                match_id: format!("NA1_{}", i),
+                region: "na1".to_string(),
+                tier: *tier,
+                patch: "14.3".to_string(),
+            };
+            assert!(db.insert_match_stratum(&stratum).is_ok());
+        }
+
+        let mut counts = db.get_stratum_counts().unwrap();
+        counts.sort_by_key(|(_, _, _, count)| *count);
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[0], ("na1".to_string(), None, "14.3".to_string(), 1));
+        assert_eq!(
+            counts[1],
+            ("na1".to_string(), Some(Tier::Gold), "14.3".to_string(), 2)
+        );
+    }
+
+    fn test_failed_task(
+        task_type: FailedTaskType,
+        target_id: &str,
+        error_kind: &str,
+    ) -> DbFailedTask {
+        DbFailedTask {
+            id: None,
+            task_type,
+            target_id: target_id.to_string(),
+            region: "na1".to_string(),
+            summoner_name: None,
+            error_kind: error_kind.to_string(),
+            http_status: Some(404),
+            attempts: 1,
+            last_error: "Resource not found".to_string(),
+            status: "failed".to_string(),
+            first_failed_at: Utc::now(),
+            last_failed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_failed_task_operations() {
+        let db = create_test_database();
+
+        let failed = test_failed_task(FailedTaskType::Match, "NA1_1", "NotFound");
+        assert!(db.record_failed_task(&failed).is_ok());
+        // A second failure accumulates attempts and keeps the latest error
+        let mut again = failed.clone();
+        again.error_kind = "Json".to_string();
+        again.http_status = None;
+        again.last_error = "JSON parsing error: missing field `info`".to_string();
+        assert!(db.record_failed_task(&again).is_ok());
+        assert!(db
+            .record_failed_task(&test_failed_task(
+                FailedTaskType::Summoner,
+                "puuid-1",
+                "Authentication"
+            ))
+            .is_ok());
+
+        let all = db
+            .get_failed_tasks(&FailedTaskFilter::default(), None, 10)
+            .unwrap();
+        assert_eq!(all.len(), 2);
+
+        let filter = FailedTaskFilter {
+            task_type: Some(FailedTaskType::Match),
+            ..Default::default()
+        };
+        let matches = db.get_failed_tasks(&filter, None, 10).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].attempts, 2);
+        assert_eq!(matches[0].error_kind, "Json");
+        assert_eq!(matches[0].http_status, None);
+
+        let summary = db.get_failed_task_summary().unwrap();
+        assert_eq!(summary.len(), 2);
+
+        // Requeue only the auth failures
+        let filter = FailedTaskFilter {
+            error_kind: Some("Authentication".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(db.requeue_failed_tasks(&filter).unwrap(), 1);
+        assert_eq!(db.requeue_failed_tasks(&filter).unwrap(), 0);
+        let requeued = db
+            .get_failed_tasks(&FailedTaskFilter::default(), Some("requeued"), 10)
+            .unwrap();
+        assert_eq!(requeued.len(), 1);
+        assert_eq!(requeued[0].task_type, FailedTaskType::Summoner);
+
+        // Success removes the entry
+        assert!(db
+            .resolve_failed_task(FailedTaskType::Summoner, "puuid-1")
+            .unwrap());
+        assert!(!db
+            .resolve_failed_task(FailedTaskType::Summoner, "puuid-1")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_pending_tasks_round_trip() {
+        let db = create_test_database();
+
+        let retry_at = Utc::now() + chrono::Duration::seconds(120);
+        let tasks = vec![
+            SummonerTask {
+                puuid: "puuid-1".to_string(),
+                summoner_name: "Player1".to_string(),
+                region: "na1".to_string(),
+                priority: SummonerPriority::High,
+                added_at: Utc::now(),
+                retries: 0,
+                tier: Some(Tier::Gold),
+                not_before: None,
+                retry_class: None,
+            },
+            SummonerTask {
+                puuid: "puuid-2".to_string(),
+                summoner_name: "Player2".to_string(),
+                region: "euw1".to_string(),
+                priority: SummonerPriority::Medium,
+                added_at: Utc::now(),
+                retries: 2,
+                tier: None,
+                not_before: Some(retry_at),
+                retry_class: Some(RetryClass::ServiceUnavailable),
+            },
+        ];
+        assert!(db.save_pending_tasks(&tasks[..1]).is_ok());
+        assert!(db.append_pending_tasks(&tasks[1..]).is_ok());
+        assert_eq!(db.get_pending_tasks_count().unwrap(), 2);
+
+        let mut restored = db.take_pending_tasks().unwrap();
+        restored.sort_by(|a, b| a.puuid.cmp(&b.puuid));
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].priority, SummonerPriority::High);
+        assert_eq!(restored[0].tier, Some(Tier::Gold));
+        assert_eq!(restored[1].retries, 2);
+        assert_eq!(
+            restored[1].retry_class,
+            Some(RetryClass::ServiceUnavailable)
+        );
+        assert_eq!(
+            restored[1].not_before.map(|t| t.timestamp()),
+            Some(retry_at.timestamp())
+        );
+
+        // Taking the queue clears it
+        assert!(db.take_pending_tasks().unwrap().is_empty());
+
+        // Saving replaces the queue
+        db.save_pending_tasks(&tasks).unwrap();
+        db.save_pending_tasks(&tasks[1..]).unwrap();
+        assert_eq!(db.get_pending_tasks_count().unwrap(), 1);
+    }
+
+    fn test_bundle() -> DbMatchBundle {
+        let match_data = test_match();
+        let match_id = match_data.match_id.clone();
+        DbMatchBundle {
+            teams: vec![test_team_for_match(&match_id)],
+            bans: vec![test_ban_for_match(&match_id), test_ban_for_match(&match_id)],
+            participants: (0..10)
+                .map(|i| test_participant_for_match(&match_id, &format!("puuid-{}", i)))
+                .collect(),
+            perks: (0..3)
+                .map(|slot| DbParticipantPerk {
+                    match_id: match_id.clone(),
+                    puuid: "puuid-0".to_string(),
+                    style: "statPerks".to_string(),
+                    style_id: None,
+                    slot,
+                    perk_id: 5001 + slot,
+                    var1: None,
+                    var2: None,
+                    var3: None,
+                })
+                .collect(),
+            challenges: vec![DbParticipantChallenge {
+                match_id: match_id.clone(),
+                puuid: "puuid-0".to_string(),
+                name: "visionScorePerMinute".to_string(),
+                value: Some(1.4),
+                value_json: None,
+            }],
+            stratum: Some(DbMatchStratum {
+                match_id: match_id.clone(),
+                region: "na1".to_string(),
+                tier: Some(Tier::Diamond),
+                patch: "14.1".to_string(),
+            }),
+            raw_payloads: Vec::new(),
+            match_data,
+        }
+    }
+
+    fn count_rows(db: &Database, table: &str) -> i64 {
+        db.query_row(&format!("SELECT COUNT(*) FROM {}", table), &[], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_store_match_bundle() {
+        let db = create_test_database();
+        let bundle = test_bundle();
+
+        db.store_match_bundle(&bundle).unwrap();
+        assert!(db.match_exists(&bundle.match_data.match_id).unwrap());
+        assert_eq!(db.get_participants_count().unwrap(), 10);
+        assert_eq!(count_rows(&db, "teams"), 1);
+        assert_eq!(count_rows(&db, "bans"), 2);
+        assert_eq!(count_rows(&db, "match_strata"), 1);
+        assert_eq!(count_rows(&db, "participant_perks"), 3);
+        assert_eq!(count_rows(&db, "participant_challenges"), 1);
+
+        let (vision_score, team_position): (i32, String) = db
+            .query_row(
+                "SELECT vision_score, team_position FROM participants WHERE puuid = 'puuid-0'",
+                &[],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(vision_score, 42);
+        assert_eq!(team_position, "MIDDLE");
+
+        // Storing the same match again replaces its rows instead of duplicating them
+        db.store_match_bundle(&bundle).unwrap();
+        assert_eq!(db.get_matches_count().unwrap(), 1);
+        assert_eq!(db.get_participants_count().unwrap(), 10);
+        assert_eq!(count_rows(&db, "bans"), 2);
+        assert_eq!(count_rows(&db, "participant_perks"), 3);
+        assert_eq!(count_rows(&db, "participant_challenges"), 1);
+    }
+
+    #[test]
+    fn test_store_match_bundle_rolls_back_on_error() {
+        let db = create_test_database();
+        let bundle = test_bundle();
+
+        // Make the last write of the bundle fail
+        db.execute("DROP TABLE match_strata", &[]).unwrap();
+        assert!(db.store_match_bundle(&bundle).is_err());
+
+        assert!(!db.match_exists(&bundle.match_data.match_id).unwrap());
+        assert_eq!(db.get_participants_count().unwrap(), 0);
+        assert_eq!(count_rows(&db, "bans"), 0);
+        assert_eq!(count_rows(&db, "participant_perks"), 0);
+    }
+
+    #[test]
+    fn test_file_database_uses_wal() {
+        let path = std::env::temp_dir().join(format!(
+            "lol-crawler-wal-{}.db",
+            Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ));
+        let db = Database::new(path.to_str().unwrap()).unwrap();
+
+        let mode: String = db
+            .query_row("PRAGMA journal_mode", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
 }
@@ -0,0 +1,1112 @@
+use super::migrations::Migration;
+use super::retention::aggregate_api_calls;
+use super::storage::Storage;
+use super::{StorageError, CHILD_TABLES};
+use crate::models::database::*;
+use crate::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, GenericClient, NoTls, Row};
+
+/// Postgres schema versions, applied in order on connect. Like the SQLite migrations,
+/// a shipped migration is never edited; schema changes add a new file.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../../migrations/postgres/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "participant_details",
+        sql: include_str!("../../migrations/postgres/0002_participant_details.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "raw_payloads",
+        sql: include_str!("../../migrations/postgres/0003_raw_payloads.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "match_stored_seq",
+        sql: include_str!("../../migrations/postgres/0004_match_stored_seq.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "api_call_rollups",
+        sql: include_str!("../../migrations/postgres/0005_api_call_rollups.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "match_hook_rows",
+        sql: include_str!("../../migrations/postgres/0006_match_hook_rows.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "vetoed_matches",
+        sql: include_str!("../../migrations/postgres/0007_vetoed_matches.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "pruned_matches",
+        sql: include_str!("../../migrations/postgres/0008_pruned_matches.sql"),
+    },
+];
+
+/// Advisory lock key that keeps two crawlers from migrating the same database at once
+const MIGRATION_LOCK_ID: i64 = 0x6c6f_6c63_7261_776c;
+
+/// Storage backend for PostgreSQL, selected by a `postgres://` `DATABASE_URL`
+pub struct PostgresStorage {
+    /// Transactions need exclusive use of the client, so requests take turns
+    client: Mutex<Client>,
+}
+
+impl PostgresStorage {
+    /// Connect and bring the schema up to date. TLS is not supported; connect to a
+    /// local or tunnelled server.
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let (mut client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                log::error!("Postgres connection error: {}", e);
+            }
+        });
+
+        migrate(&mut client).await?;
+
+        Ok(Self {
+            client: Mutex::new(client),
+        })
+    }
+
+    async fn count(&self, sql: &str) -> Result<i64> {
+        let client = self.client.lock().await;
+        Ok(client.query_one(sql, &[]).await?.get(0))
+    }
+
+    /// Insert tasks into the persisted queue in one transaction, first clearing it
+    /// when `replace` is set
+    async fn write_pending_tasks(&self, tasks: &[SummonerTask], replace: bool) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        if replace {
+            tx.execute("DELETE FROM pending_tasks", &[]).await?;
+        }
+        let insert = tx
+            .prepare(
+                "INSERT INTO pending_tasks
+                 (puuid, summoner_name, region, priority, tier, retries, retry_class, not_before, added_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (puuid) DO UPDATE SET
+                 summoner_name = excluded.summoner_name, region = excluded.region,
+                 priority = excluded.priority, tier = excluded.tier, retries = excluded.retries,
+                 retry_class = excluded.retry_class, not_before = excluded.not_before,
+                 added_at = excluded.added_at",
+            )
+            .await?;
+        for task in tasks {
+            tx.execute(
+                &insert,
+                &[
+                    &task.puuid,
+                    &task.summoner_name,
+                    &task.region,
+                    &task.priority.as_str(),
+                    &task.tier.map(|t| t.as_str()),
+                    &i64::from(task.retries),
+                    &task.retry_class.map(|c| c.as_str()),
+                    &task.not_before,
+                    &task.added_at,
+                ],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+async fn migrate(client: &mut Client) -> Result<()> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .await?;
+
+    for migration in MIGRATIONS {
+        let tx = client.transaction().await?;
+        tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK_ID])
+            .await?;
+        let applied = tx
+            .query_opt(
+                "SELECT 1 FROM schema_version WHERE version = $1",
+                &[&migration.version],
+            )
+            .await?
+            .is_some();
+        if applied {
+            continue;
+        }
+
+        log::info!(
+            "Applying Postgres schema migration {} ({})",
+            migration.version,
+            migration.name
+        );
+        tx.batch_execute(migration.sql).await?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+async fn write_match_bundle<C: GenericClient>(client: &C, bundle: &DbMatchBundle) -> Result<()> {
+    let m = &bundle.match_data;
+    for table in CHILD_TABLES {
+        client
+            .execute(
+                &format!("DELETE FROM {} WHERE match_id = $1", table),
+                &[&m.match_id],
+            )
+            .await?;
+    }
+
+    client
+        .execute(
+            "INSERT INTO matches
+             (match_id, game_creation, game_duration, game_end_timestamp, game_id, game_mode, game_name, game_type,
+              game_version, map_id, platform_id, queue_id, tournament_code, region, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+             ON CONFLICT (match_id) DO UPDATE SET
+             game_creation = excluded.game_creation, game_duration = excluded.game_duration,
+             game_end_timestamp = excluded.game_end_timestamp, game_id = excluded.game_id,
+             game_mode = excluded.game_mode, game_name = excluded.game_name, game_type = excluded.game_type,
+             game_version = excluded.game_version, map_id = excluded.map_id, platform_id = excluded.platform_id,
+             queue_id = excluded.queue_id, tournament_code = excluded.tournament_code,
+             region = excluded.region, created_at = excluded.created_at,
+             stored_seq = nextval('matches_stored_seq')",
+            &[
+                &m.match_id,
+                &m.game_creation,
+                &m.game_duration,
+                &m.game_end_timestamp,
+                &m.game_id,
+                &m.game_mode,
+                &m.game_name,
+                &m.game_type,
+                &m.game_version,
+                &m.map_id,
+                &m.platform_id,
+                &m.queue_id,
+                &m.tournament_code,
+                &m.region,
+                &m.created_at,
+            ],
+        )
+        .await?;
+
+    let insert_team = client
+        .prepare(
+            "INSERT INTO teams
+             (match_id, team_id, win, first_baron, first_dragon, first_inhibitor, first_rift_herald, first_tower,
+              baron_kills, dragon_kills, inhibitor_kills, rift_herald_kills, tower_kills)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+        )
+        .await?;
+    for team in &bundle.teams {
+        client
+            .execute(
+                &insert_team,
+                &[
+                    &team.match_id,
+                    &team.team_id,
+                    &team.win,
+                    &team.first_baron,
+                    &team.first_dragon,
+                    &team.first_inhibitor,
+                    &team.first_rift_herald,
+                    &team.first_tower,
+                    &team.baron_kills,
+                    &team.dragon_kills,
+                    &team.inhibitor_kills,
+                    &team.rift_herald_kills,
+                    &team.tower_kills,
+                ],
+            )
+            .await?;
+    }
+
+    let insert_ban = client
+        .prepare(
+            "INSERT INTO bans (match_id, team_id, champion_id, pick_turn) VALUES ($1, $2, $3, $4)",
+        )
+        .await?;
+    for ban in &bundle.bans {
+        client
+            .execute(
+                &insert_ban,
+                &[
+                    &ban.match_id,
+                    &ban.team_id,
+                    &ban.champion_id,
+                    &ban.pick_turn,
+                ],
+            )
+            .await?;
+    }
+
+    let insert_participant = client
+        .prepare(
+            "INSERT INTO participants
+             (match_id, puuid, summoner_name, champion_id, champion_name, team_id, position,
+              individual_position, kills, deaths, assists, total_damage_dealt,
+              total_damage_dealt_to_champions, total_damage_taken, gold_earned, gold_spent,
+              turret_kills, inhibitor_kills, total_minions_killed, neutral_minions_killed,
+              champion_level, items_0, items_1, items_2, items_3, items_4, items_5, items_6,
+              summoner_spell_1, summoner_spell_2, primary_rune_tree, secondary_rune_tree, win,
+              first_blood_kill, first_tower_kill, all_in_pings, assist_me_pings, baron_kills,
+              bounty_level, champ_experience, command_pings, champion_transform,
+              consumables_purchased, damage_dealt_to_buildings, damage_dealt_to_objectives,
+              damage_dealt_to_turrets, damage_self_mitigated, detector_wards_placed, double_kills,
+              dragon_kills, eligible_for_progression, enemy_missing_pings, enemy_vision_pings,
+              first_blood_assist, first_tower_assist, game_ended_in_early_surrender,
+              game_ended_in_surrender, hold_pings, get_back_pings, inhibitor_takedowns,
+              inhibitors_lost, items_purchased, killing_sprees, largest_critical_strike,
+              largest_killing_spree, largest_multi_kill, longest_time_spent_living,
+              magic_damage_dealt, magic_damage_dealt_to_champions, magic_damage_taken,
+              need_vision_pings, nexus_kills, nexus_takedowns, nexus_lost, objectives_stolen,
+              objectives_stolen_assists, on_my_way_pings, participant_id, penta_kills,
+              physical_damage_dealt, physical_damage_dealt_to_champions, physical_damage_taken,
+              placement, player_augment1, player_augment2, player_augment3, player_augment4,
+              player_subteam_id, push_pings, profile_icon, quadra_kills, riot_id_game_name,
+              riot_id_tagline, role, sight_wards_bought_in_game, spell1_casts, spell2_casts,
+              spell3_casts, spell4_casts, subteam_placement, summoner1_casts, summoner2_casts,
+              summoner_id, summoner_level, team_early_surrendered, team_position, time_ccing_others,
+              time_played, total_ally_jungle_minions_killed, total_damage_shielded_on_teammates,
+              total_enemy_jungle_minions_killed, total_heal, total_heals_on_teammates,
+              total_time_cc_dealt, total_time_spent_dead, total_units_healed, triple_kills,
+              true_damage_dealt, true_damage_dealt_to_champions, true_damage_taken,
+              turret_takedowns, turrets_lost, unreal_kills, vision_score, vision_cleared_pings,
+              vision_wards_bought_in_game, wards_killed, wards_placed, missions)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                     $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28, $29, $30, $31, $32, $33,
+                     $34, $35, $36, $37, $38, $39, $40, $41, $42, $43, $44, $45, $46, $47, $48, $49,
+                     $50, $51, $52, $53, $54, $55, $56, $57, $58, $59, $60, $61, $62, $63, $64, $65,
+                     $66, $67, $68, $69, $70, $71, $72, $73, $74, $75, $76, $77, $78, $79, $80, $81,
+                     $82, $83, $84, $85, $86, $87, $88, $89, $90, $91, $92, $93, $94, $95, $96, $97,
+                     $98, $99, $100, $101, $102, $103, $104, $105, $106, $107, $108, $109, $110,
+                     $111, $112, $113, $114, $115, $116, $117, $118, $119, $120, $121, $122, $123,
+                     $124, $125, $126, $127, $128, $129)",
+        )
+        .await?;
+    for p in &bundle.participants {
+        client
+            .execute(
+                &insert_participant,
+                &[
+                    &p.match_id,
+                    &p.puuid,
+                    &p.summoner_name,
+                    &p.champion_id,
+                    &p.champion_name,
+                    &p.team_id,
+                    &p.position,
+                    &p.individual_position,
+                    &p.kills,
+                    &p.deaths,
+                    &p.assists,
+                    &p.total_damage_dealt,
+                    &p.total_damage_dealt_to_champions,
+                    &p.total_damage_taken,
+                    &p.gold_earned,
+                    &p.gold_spent,
+                    &p.turret_kills,
+                    &p.inhibitor_kills,
+                    &p.total_minions_killed,
+                    &p.neutral_minions_killed,
+                    &p.champion_level,
+                    &p.items_0,
+                    &p.items_1,
+                    &p.items_2,
+                    &p.items_3,
+                    &p.items_4,
+                    &p.items_5,
+                    &p.items_6,
+                    &p.summoner_spell_1,
+                    &p.summoner_spell_2,
+                    &p.primary_rune_tree,
+                    &p.secondary_rune_tree,
+                    &p.win,
+                    &p.first_blood_kill,
+                    &p.first_tower_kill,
+                    &p.all_in_pings,
+                    &p.assist_me_pings,
+                    &p.baron_kills,
+                    &p.bounty_level,
+                    &p.champ_experience,
+                    &p.command_pings,
+                    &p.champion_transform,
+                    &p.consumables_purchased,
+                    &p.damage_dealt_to_buildings,
+                    &p.damage_dealt_to_objectives,
+                    &p.damage_dealt_to_turrets,
+                    &p.damage_self_mitigated,
+                    &p.detector_wards_placed,
+                    &p.double_kills,
+                    &p.dragon_kills,
+                    &p.eligible_for_progression,
+                    &p.enemy_missing_pings,
+                    &p.enemy_vision_pings,
+                    &p.first_blood_assist,
+                    &p.first_tower_assist,
+                    &p.game_ended_in_early_surrender,
+                    &p.game_ended_in_surrender,
+                    &p.hold_pings,
+                    &p.get_back_pings,
+                    &p.inhibitor_takedowns,
+                    &p.inhibitors_lost,
+                    &p.items_purchased,
+                    &p.killing_sprees,
+                    &p.largest_critical_strike,
+                    &p.largest_killing_spree,
+                    &p.largest_multi_kill,
+                    &p.longest_time_spent_living,
+                    &p.magic_damage_dealt,
+                    &p.magic_damage_dealt_to_champions,
+                    &p.magic_damage_taken,
+                    &p.need_vision_pings,
+                    &p.nexus_kills,
+                    &p.nexus_takedowns,
+                    &p.nexus_lost,
+                    &p.objectives_stolen,
+                    &p.objectives_stolen_assists,
+                    &p.on_my_way_pings,
+                    &p.participant_id,
+                    &p.penta_kills,
+                    &p.physical_damage_dealt,
+                    &p.physical_damage_dealt_to_champions,
+                    &p.physical_damage_taken,
+                    &p.placement,
+                    &p.player_augment1,
+                    &p.player_augment2,
+                    &p.player_augment3,
+                    &p.player_augment4,
+                    &p.player_subteam_id,
+                    &p.push_pings,
+                    &p.profile_icon,
+                    &p.quadra_kills,
+                    &p.riot_id_game_name,
+                    &p.riot_id_tagline,
+                    &p.role,
+                    &p.sight_wards_bought_in_game,
+                    &p.spell1_casts,
+                    &p.spell2_casts,
+                    &p.spell3_casts,
+                    &p.spell4_casts,
+                    &p.subteam_placement,
+                    &p.summoner1_casts,
+                    &p.summoner2_casts,
+                    &p.summoner_id,
+                    &p.summoner_level,
+                    &p.team_early_surrendered,
+                    &p.team_position,
+                    &p.time_ccing_others,
+                    &p.time_played,
+                    &p.total_ally_jungle_minions_killed,
+                    &p.total_damage_shielded_on_teammates,
+                    &p.total_enemy_jungle_minions_killed,
+                    &p.total_heal,
+                    &p.total_heals_on_teammates,
+                    &p.total_time_cc_dealt,
+                    &p.total_time_spent_dead,
+                    &p.total_units_healed,
+                    &p.triple_kills,
+                    &p.true_damage_dealt,
+                    &p.true_damage_dealt_to_champions,
+                    &p.true_damage_taken,
+                    &p.turret_takedowns,
+                    &p.turrets_lost,
+                    &p.unreal_kills,
+                    &p.vision_score,
+                    &p.vision_cleared_pings,
+                    &p.vision_wards_bought_in_game,
+                    &p.wards_killed,
+                    &p.wards_placed,
+                    &p.missions,
+                ],
+            )
+            .await?;
+    }
+
+    let insert_perk = client
+        .prepare(
+            "INSERT INTO participant_perks
+             (match_id, puuid, style, style_id, slot, perk_id, var1, var2, var3)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .await?;
+    for perk in &bundle.perks {
+        client
+            .execute(
+                &insert_perk,
+                &[
+                    &perk.match_id,
+                    &perk.puuid,
+                    &perk.style,
+                    &perk.style_id,
+                    &perk.slot,
+                    &perk.perk_id,
+                    &perk.var1,
+                    &perk.var2,
+                    &perk.var3,
+                ],
+            )
+            .await?;
+    }
+
+    let insert_challenge = client
+        .prepare(
+            "INSERT INTO participant_challenges (match_id, puuid, name, value, value_json)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .await?;
+    for challenge in &bundle.challenges {
+        client
+            .execute(
+                &insert_challenge,
+                &[
+                    &challenge.match_id,
+                    &challenge.puuid,
+                    &challenge.name,
+                    &challenge.value,
+                    &challenge.value_json,
+                ],
+            )
+            .await?;
+    }
+
+    if let Some(stratum) = &bundle.stratum {
+        client
+            .execute(
+                "INSERT INTO match_strata (match_id, region, tier, patch) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (match_id) DO UPDATE SET
+                 region = excluded.region, tier = excluded.tier, patch = excluded.patch",
+                &[
+                    &stratum.match_id,
+                    &stratum.region,
+                    &stratum.tier.map(|t| t.as_str()),
+                    &stratum.patch,
+                ],
+            )
+            .await?;
+    }
+    for payload in &bundle.raw_payloads {
+        client
+            .execute(
+                "INSERT INTO raw_payloads (match_id, kind, region, payload, raw_size, fetched_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (match_id, kind) DO UPDATE SET
+                 region = excluded.region, payload = excluded.payload,
+                 raw_size = excluded.raw_size, fetched_at = excluded.fetched_at",
+                &[
+                    &payload.match_id,
+                    &payload.kind.as_str(),
+                    &payload.region,
+                    &payload.payload,
+                    &payload.raw_size,
+                    &payload.fetched_at,
+                ],
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+fn failed_task_from_row(row: &Row) -> Result<DbFailedTask> {
+    let task_type: String = row.get(1);
+    let http_status: Option<i32> = row.get(6);
+    Ok(DbFailedTask {
+        id: Some(row.get(0)),
+        task_type: task_type.parse().map_err(StorageError::InvalidValue)?,
+        target_id: row.get(2),
+        region: row.get(3),
+        summoner_name: row.get(4),
+        error_kind: row.get(5),
+        http_status: http_status.and_then(|s| u16::try_from(s).ok()),
+        attempts: row.get(7),
+        last_error: row.get(8),
+        status: row.get(9),
+        first_failed_at: row.get(10),
+        last_failed_at: row.get(11),
+    })
+}
+
+fn pending_task_from_row(row: &Row) -> SummonerTask {
+    let priority: String = row.get(3);
+    let tier: Option<String> = row.get(4);
+    let retries: i64 = row.get(5);
+    let retry_class: Option<String> = row.get(6);
+    SummonerTask {
+        puuid: row.get(0),
+        summoner_name: row.get(1),
+        region: row.get(2),
+        priority: priority.parse().unwrap_or(SummonerPriority::Low),
+        added_at: row.get(8),
+        retries: u32::try_from(retries).unwrap_or(0),
+        tier: tier.and_then(|t| t.parse().ok()),
+        not_before: row.get::<_, Option<DateTime<Utc>>>(7),
+        retry_class: retry_class.and_then(|c| c.parse().ok()),
+    }
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn insert_summoner(&self, summoner: &DbSummoner) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "INSERT INTO summoners
+                 (puuid, summoner_id, account_id, summoner_name, profile_icon_id, summoner_level, region, created_at, updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                 ON CONFLICT (puuid) DO UPDATE SET
+                 summoner_id = excluded.summoner_id, account_id = excluded.account_id,
+                 summoner_name = excluded.summoner_name, profile_icon_id = excluded.profile_icon_id,
+                 summoner_level = excluded.summoner_level, region = excluded.region,
+                 updated_at = excluded.updated_at",
+                &[
+                    &summoner.puuid,
+                    &summoner.summoner_id,
+                    &summoner.account_id,
+                    &summoner.summoner_name,
+                    &summoner.profile_icon_id,
+                    &summoner.summoner_level,
+                    &summoner.region,
+                    &summoner.created_at,
+                    &summoner.updated_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn summoner_exists(&self, puuid: &str) -> Result<bool> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt("SELECT 1 FROM summoners WHERE puuid = $1", &[&puuid])
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn match_exists(&self, match_id: &str) -> Result<bool> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt("SELECT 1 FROM matches WHERE match_id = $1", &[&match_id])
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn record_vetoed_match(&self, vetoed: &DbVetoedMatch) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "INSERT INTO vetoed_matches (match_id, region, hook, vetoed_at)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (match_id) DO UPDATE SET
+                 region = excluded.region, hook = excluded.hook, vetoed_at = excluded.vetoed_at",
+                &[
+                    &vetoed.match_id,
+                    &vetoed.region,
+                    &vetoed.hook,
+                    &vetoed.vetoed_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn match_vetoed(&self, match_id: &str) -> Result<bool> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt(
+                "SELECT 1 FROM vetoed_matches WHERE match_id = $1",
+                &[&match_id],
+            )
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn match_pruned(&self, match_id: &str) -> Result<bool> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt(
+                "SELECT 1 FROM pruned_matches WHERE match_id = $1",
+                &[&match_id],
+            )
+            .await?;
+        Ok(row.is_some())
+    }
+
+    async fn store_match_bundle(&self, bundle: &DbMatchBundle) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        write_match_bundle(&tx, bundle).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn store_hook_rows(
+        &self,
+        match_id: &str,
+        hook: &str,
+        rows: &[DbMatchHookRow],
+    ) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        tx.execute(
+            "DELETE FROM match_hook_rows WHERE match_id = $1 AND hook = $2",
+            &[&match_id, &hook],
+        )
+        .await?;
+        for row in rows {
+            tx.execute(
+                "INSERT INTO match_hook_rows (match_id, hook, kind, data, created_at)
+                 VALUES ($1, $2, $3, $4::TEXT::JSONB, $5)",
+                &[
+                    &row.match_id,
+                    &row.hook,
+                    &row.kind,
+                    &row.data.to_string(),
+                    &row.created_at,
+                ],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn upsert_summoner_rank(&self, rank: &DbSummonerRank) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "INSERT INTO summoner_ranks
+                 (puuid, queue_type, tier, rank, league_points, wins, losses, updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (puuid, queue_type) DO UPDATE SET
+                 tier = excluded.tier, rank = excluded.rank, league_points = excluded.league_points,
+                 wins = excluded.wins, losses = excluded.losses, updated_at = excluded.updated_at",
+                &[
+                    &rank.puuid,
+                    &rank.queue_type,
+                    &rank.tier,
+                    &rank.rank,
+                    &rank.league_points,
+                    &rank.wins,
+                    &rank.losses,
+                    &rank.updated_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_existing_summoners_with_tier(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<(String, String, Option<Tier>)>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT s.puuid, s.region, r.tier FROM summoners s
+                 LEFT JOIN summoner_ranks r ON r.puuid = s.puuid AND r.queue_type = 'RANKED_SOLO_5x5'
+                 ORDER BY s.updated_at ASC
+                 LIMIT $1",
+                &[&i64::from(limit)],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let tier: Option<String> = row.get(2);
+                (row.get(0), row.get(1), tier.and_then(|t| t.parse().ok()))
+            })
+            .collect())
+    }
+
+    async fn get_stratum_counts(&self) -> Result<Vec<StratumCount>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT region, tier, patch, COUNT(*) FROM match_strata GROUP BY region, tier, patch",
+                &[],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let tier: Option<String> = row.get(1);
+                (
+                    row.get(0),
+                    tier.and_then(|t| t.parse().ok()),
+                    row.get(2),
+                    row.get(3),
+                )
+            })
+            .collect())
+    }
+
+    async fn log_api_call(&self, call: &DbApiCall) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "INSERT INTO api_calls (endpoint, region, timestamp, response_code, rate_limit_remaining)
+                 VALUES ($1, $2, $3, $4, $5)",
+                &[
+                    &call.endpoint,
+                    &call.region,
+                    &call.timestamp,
+                    &call.response_code,
+                    &call.rate_limit_remaining,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn record_failed_task(&self, task: &DbFailedTask) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "INSERT INTO failed_tasks
+                 (task_type, target_id, region, summoner_name, error_kind, http_status, attempts, last_error, status, first_failed_at, last_failed_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'failed', $9, $10)
+                 ON CONFLICT (task_type, target_id) DO UPDATE SET
+                 error_kind = excluded.error_kind, http_status = excluded.http_status,
+                 attempts = failed_tasks.attempts + excluded.attempts, last_error = excluded.last_error,
+                 status = 'failed', last_failed_at = excluded.last_failed_at",
+                &[
+                    &task.task_type.as_str(),
+                    &task.target_id,
+                    &task.region,
+                    &task.summoner_name,
+                    &task.error_kind,
+                    &task.http_status.map(i32::from),
+                    &task.attempts,
+                    &task.last_error,
+                    &task.first_failed_at,
+                    &task.last_failed_at,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn resolve_failed_task(
+        &self,
+        task_type: FailedTaskType,
+        target_id: &str,
+    ) -> Result<bool> {
+        let client = self.client.lock().await;
+        let removed = client
+            .execute(
+                "DELETE FROM failed_tasks WHERE task_type = $1 AND target_id = $2",
+                &[&task_type.as_str(), &target_id],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    async fn get_failed_tasks(
+        &self,
+        filter: &FailedTaskFilter,
+        status: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<DbFailedTask>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT id, task_type, target_id, region, summoner_name, error_kind, http_status, attempts,
+                        last_error, status, first_failed_at, last_failed_at
+                 FROM failed_tasks
+                 WHERE ($1::BIGINT IS NULL OR id = $1) AND ($2::TEXT IS NULL OR task_type = $2)
+                   AND ($3::TEXT IS NULL OR error_kind = $3) AND ($4::TEXT IS NULL OR status = $4)
+                 ORDER BY last_failed_at DESC
+                 LIMIT $5",
+                &[
+                    &filter.id,
+                    &filter.task_type.map(|t| t.as_str()),
+                    &filter.error_kind,
+                    &status,
+                    &i64::from(limit),
+                ],
+            )
+            .await?;
+        rows.iter().map(failed_task_from_row).collect()
+    }
+
+    async fn get_failed_task_summary(&self) -> Result<Vec<(String, String, String, i64)>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT task_type, error_kind, status, COUNT(*) FROM failed_tasks
+                 GROUP BY task_type, error_kind, status ORDER BY task_type, error_kind, status",
+                &[],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2), row.get(3)))
+            .collect())
+    }
+
+    async fn requeue_failed_tasks(&self, filter: &FailedTaskFilter) -> Result<usize> {
+        let client = self.client.lock().await;
+        let requeued = client
+            .execute(
+                "UPDATE failed_tasks SET status = 'requeued'
+                 WHERE status = 'failed' AND ($1::BIGINT IS NULL OR id = $1)
+                   AND ($2::TEXT IS NULL OR task_type = $2) AND ($3::TEXT IS NULL OR error_kind = $3)",
+                &[
+                    &filter.id,
+                    &filter.task_type.map(|t| t.as_str()),
+                    &filter.error_kind,
+                ],
+            )
+            .await?;
+        Ok(requeued as usize)
+    }
+
+    async fn save_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        self.write_pending_tasks(tasks, true).await
+    }
+
+    async fn append_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        self.write_pending_tasks(tasks, false).await
+    }
+
+    async fn take_pending_tasks(&self) -> Result<Vec<SummonerTask>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "DELETE FROM pending_tasks
+                 RETURNING puuid, summoner_name, region, priority, tier, retries, retry_class, not_before, added_at",
+                &[],
+            )
+            .await?;
+        Ok(rows.iter().map(pending_task_from_row).collect())
+    }
+
+    async fn get_pending_tasks_count(&self) -> Result<i64> {
+        self.count("SELECT COUNT(*) FROM pending_tasks").await
+    }
+
+    async fn get_raw_payload(
+        &self,
+        match_id: &str,
+        kind: RawPayloadKind,
+    ) -> Result<Option<DbRawPayload>> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt(
+                "SELECT match_id, region, payload, raw_size, fetched_at FROM raw_payloads
+                 WHERE match_id = $1 AND kind = $2",
+                &[&match_id, &kind.as_str()],
+            )
+            .await?;
+        Ok(row.map(|row| DbRawPayload {
+            match_id: row.get(0),
+            kind,
+            region: row.get(1),
+            payload: row.get(2),
+            raw_size: row.get(3),
+            fetched_at: row.get(4),
+        }))
+    }
+
+    async fn get_archived_match_ids(&self, after: Option<&str>, limit: i32) -> Result<Vec<String>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT match_id FROM raw_payloads
+                 WHERE kind = 'match' AND ($1::TEXT IS NULL OR match_id > $1)
+                 ORDER BY match_id LIMIT $2",
+                &[&after, &(limit as i64)],
+            )
+            .await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn rollup_api_calls(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        let calls: Vec<DbApiCall> = tx
+            .query(
+                "SELECT id, endpoint, region, timestamp, response_code, rate_limit_remaining
+                 FROM api_calls WHERE timestamp < $1 ORDER BY id LIMIT $2",
+                &[&before, &limit],
+            )
+            .await?
+            .iter()
+            .map(|row| DbApiCall {
+                id: row.get(0),
+                endpoint: row.get::<_, Option<String>>(1).unwrap_or_default(),
+                region: row.get::<_, Option<String>>(2).unwrap_or_default(),
+                timestamp: row.get(3),
+                response_code: row.get::<_, Option<i32>>(4).unwrap_or_default(),
+                rate_limit_remaining: row.get(5),
+            })
+            .collect();
+
+        for rollup in aggregate_api_calls(&calls) {
+            tx.execute(
+                "INSERT INTO api_call_rollups (hour, endpoint, region, calls, errors, rate_limited)
+                 VALUES ($1, $2, $3, $4, $5, $6)
+                 ON CONFLICT (hour, endpoint, region) DO UPDATE SET
+                 calls = api_call_rollups.calls + excluded.calls,
+                 errors = api_call_rollups.errors + excluded.errors,
+                 rate_limited = api_call_rollups.rate_limited + excluded.rate_limited",
+                &[
+                    &rollup.hour,
+                    &rollup.endpoint,
+                    &rollup.region,
+                    &rollup.calls,
+                    &rollup.errors,
+                    &rollup.rate_limited,
+                ],
+            )
+            .await?;
+        }
+        let ids: Vec<i64> = calls.iter().filter_map(|call| call.id).collect();
+        tx.execute("DELETE FROM api_calls WHERE id = ANY($1)", &[&ids])
+            .await?;
+        tx.commit().await?;
+        Ok(calls.len() as u64)
+    }
+
+    async fn get_game_versions(&self) -> Result<Vec<String>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT DISTINCT game_version FROM matches WHERE game_version IS NOT NULL",
+                &[],
+            )
+            .await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    async fn prune_matches(
+        &self,
+        game_versions: &[String],
+        keep_raw_payloads: bool,
+        limit: i64,
+    ) -> Result<u64> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        let pruned: Vec<(String, Option<String>)> = tx
+            .query(
+                "SELECT match_id, game_version FROM matches WHERE game_version = ANY($1) LIMIT $2",
+                &[&game_versions, &limit],
+            )
+            .await?
+            .iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+        let match_ids: Vec<&String> = pruned.iter().map(|(match_id, _)| match_id).collect();
+
+        let pruned_at = Utc::now();
+        for (match_id, game_version) in &pruned {
+            tx.execute(
+                "INSERT INTO pruned_matches (match_id, game_version, pruned_at)
+                 VALUES ($1, $2, $3)
+                 ON CONFLICT (match_id) DO UPDATE SET
+                 game_version = excluded.game_version, pruned_at = excluded.pruned_at",
+                &[match_id, game_version, &pruned_at],
+            )
+            .await?;
+        }
+
+        let mut tables: Vec<&str> = CHILD_TABLES.to_vec();
+        tables.extend(["match_strata", "match_hook_rows", "matches"]);
+        if !keep_raw_payloads {
+            tables.push("raw_payloads");
+        }
+        for table in tables {
+            tx.execute(
+                &format!("DELETE FROM {} WHERE match_id = ANY($1)", table),
+                &[&match_ids],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(match_ids.len() as u64)
+    }
+
+    async fn reclaim_space(&self) -> Result<()> {
+        // Autovacuum makes deleted rows reusable; returning space to the OS would need
+        // VACUUM FULL, which locks the tables
+        Ok(())
+    }
+
+    async fn get_crawler_state(&self) -> Result<Option<DbCrawlerState>> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt(
+                "SELECT id, last_processed_summoner, total_summoners_processed, total_matches_processed,
+                        queue_size, last_update
+                 FROM crawler_state WHERE id = 1",
+                &[],
+            )
+            .await?;
+        Ok(row.map(|row| DbCrawlerState {
+            id: row.get(0),
+            last_processed_summoner: row.get(1),
+            total_summoners_processed: row.get(2),
+            total_matches_processed: row.get(3),
+            queue_size: row.get(4),
+            last_update: row.get(5),
+        }))
+    }
+
+    async fn update_crawler_state(&self, state: &DbCrawlerState) -> Result<()> {
+        let client = self.client.lock().await;
+        client
+            .execute(
+                "UPDATE crawler_state SET
+                 last_processed_summoner = $1, total_summoners_processed = $2, total_matches_processed = $3,
+                 queue_size = $4, last_update = $5
+                 WHERE id = 1",
+                &[
+                    &state.last_processed_summoner,
+                    &state.total_summoners_processed,
+                    &state.total_matches_processed,
+                    &state.queue_size,
+                    &state.last_update,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn get_matches_count(&self) -> Result<i64> {
+        self.count("SELECT COUNT(*) FROM matches").await
+    }
+
+    async fn get_summoners_count(&self) -> Result<i64> {
+        self.count("SELECT COUNT(*) FROM summoners").await
+    }
+
+    async fn get_participants_count(&self) -> Result<i64> {
+        self.count("SELECT COUNT(*) FROM participants").await
+    }
+}
@@ -0,0 +1,360 @@
+use super::{Database, CHILD_TABLES};
+use crate::models::database::{DbApiCall, DbApiCallRollup};
+use crate::Result;
+use chrono::{DateTime, DurationRound, NaiveDateTime, TimeDelta, Utc};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+
+/// Path segments after the resource that are part of an endpoint rather than an id
+const FIXED_SEGMENTS: &[&str] = &[
+    "by-name",
+    "by-puuid",
+    "by-queue",
+    "by-riot-id",
+    "by-summoner",
+    "ids",
+    "timeline",
+];
+
+/// Endpoint path with ids replaced by `{}` so calls to the same endpoint roll up
+/// together. The game, service, version and resource segments are kept, as are the
+/// known fixed segments after them (`by-puuid`, `ids`); anything else may be an id or
+/// a player's name.
+pub fn endpoint_template(endpoint: &str) -> String {
+    let path = endpoint.split('?').next().unwrap_or_default();
+    path.split('/')
+        .enumerate()
+        .map(|(i, segment)| {
+            if i <= 4 || FIXED_SEGMENTS.contains(&segment) {
+                segment
+            } else {
+                "{}"
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Hourly per-endpoint aggregates of `calls`
+pub(crate) fn aggregate_api_calls(calls: &[DbApiCall]) -> Vec<DbApiCallRollup> {
+    let mut rollups: BTreeMap<(DateTime<Utc>, String, String), DbApiCallRollup> = BTreeMap::new();
+    for call in calls {
+        let hour = call
+            .timestamp
+            .duration_trunc(TimeDelta::hours(1))
+            .unwrap_or(call.timestamp);
+        let endpoint = endpoint_template(&call.endpoint);
+        let rollup = rollups
+            .entry((hour, endpoint.clone(), call.region.clone()))
+            .or_insert_with(|| DbApiCallRollup {
+                hour,
+                endpoint,
+                region: call.region.clone(),
+                calls: 0,
+                errors: 0,
+                rate_limited: 0,
+            });
+        rollup.calls += 1;
+        if call.response_code >= 400 {
+            rollup.errors += 1;
+        }
+        if call.response_code == 429 {
+            rollup.rate_limited += 1;
+        }
+    }
+    rollups.into_values().collect()
+}
+
+/// Delete a match's rows, stratum and hook rows, and its archived payloads unless
+/// `keep_raw_payloads`
+pub(crate) fn delete_match_rows(
+    conn: &Connection,
+    match_id: &str,
+    keep_raw_payloads: bool,
+) -> rusqlite::Result<()> {
+    let mut tables: Vec<&str> = CHILD_TABLES.to_vec();
+    tables.extend(["match_strata", "match_hook_rows", "matches"]);
+    if !keep_raw_payloads {
+        tables.push("raw_payloads");
+    }
+    for table in tables {
+        conn.prepare_cached(&format!("DELETE FROM {} WHERE match_id = ?1", table))?
+            .execute([match_id])?;
+    }
+    Ok(())
+}
+
+/// Timestamps are written as RFC 3339, but the column default is SQLite's
+/// `YYYY-MM-DD HH:MM:SS`
+fn parse_timestamp(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").map(|t| t.and_utc()))
+        .unwrap_or_default()
+}
+
+impl Database {
+    /// Fold up to `limit` `api_calls` rows older than `before` into hourly rollups and
+    /// delete them, returning how many rows were rolled up
+    pub fn rollup_api_calls(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        self.transaction(move |tx| rollup_api_calls(tx, before, limit))
+    }
+
+    pub fn get_api_call_rollups(&self) -> Result<Vec<DbApiCallRollup>> {
+        self.query_map(
+            "SELECT hour, endpoint, region, calls, errors, rate_limited
+             FROM api_call_rollups ORDER BY hour, endpoint, region",
+            &[],
+            |row| {
+                Ok(DbApiCallRollup {
+                    hour: parse_timestamp(&row.get::<_, String>(0)?),
+                    endpoint: row.get(1)?,
+                    region: row.get(2)?,
+                    calls: row.get(3)?,
+                    errors: row.get(4)?,
+                    rate_limited: row.get(5)?,
+                })
+            },
+        )
+    }
+
+    /// Distinct game versions of stored matches
+    pub fn get_game_versions(&self) -> Result<Vec<String>> {
+        self.query_map(
+            "SELECT DISTINCT game_version FROM matches WHERE game_version IS NOT NULL",
+            &[],
+            |row| row.get(0),
+        )
+    }
+
+    /// Delete up to `limit` matches played on one of `game_versions`, returning how many
+    /// were deleted. Archived payloads are kept when `keep_raw_payloads` is set.
+    pub fn prune_matches(
+        &self,
+        game_versions: &[String],
+        keep_raw_payloads: bool,
+        limit: i64,
+    ) -> Result<u64> {
+        if game_versions.is_empty() {
+            return Ok(0);
+        }
+        let game_versions = game_versions.to_vec();
+        self.transaction(move |tx| prune_matches(tx, &game_versions, keep_raw_payloads, limit))
+    }
+
+    /// Return free pages to the file system. Only databases created with incremental
+    /// auto-vacuum can do this without a full `VACUUM`.
+    pub fn reclaim_space(&self) -> Result<()> {
+        self.write(|conn| reclaim_space(conn))
+    }
+}
+
+/// Run an incremental vacuum, if the database was created to allow one
+pub(super) fn reclaim_space(conn: &Connection) -> rusqlite::Result<()> {
+    let mode: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+    // 2 is INCREMENTAL
+    if mode != 2 {
+        log::warn!(
+            "Database was created without incremental auto-vacuum; run \
+             `sqlite3 <db> 'PRAGMA auto_vacuum = INCREMENTAL; VACUUM;'` once while \
+             the crawler is stopped to let retention shrink the file"
+        );
+        return Ok(());
+    }
+    conn.execute_batch("PRAGMA incremental_vacuum")
+}
+
+/// Fold up to `limit` `api_calls` rows older than `before` into hourly rollups
+pub(super) fn rollup_api_calls(
+    conn: &Connection,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> rusqlite::Result<u64> {
+    let calls = conn
+        .prepare_cached(
+            "SELECT id, endpoint, region, timestamp, response_code, rate_limit_remaining
+             FROM api_calls WHERE timestamp < ?1 ORDER BY id LIMIT ?2",
+        )?
+        .query_map(params![before.to_rfc3339(), limit], |row| {
+            Ok(DbApiCall {
+                id: row.get(0)?,
+                endpoint: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                region: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                timestamp: parse_timestamp(&row.get::<_, String>(3)?),
+                response_code: row.get::<_, Option<i32>>(4)?.unwrap_or_default(),
+                rate_limit_remaining: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for rollup in aggregate_api_calls(&calls) {
+        conn.prepare_cached(
+            "INSERT INTO api_call_rollups (hour, endpoint, region, calls, errors, rate_limited)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (hour, endpoint, region) DO UPDATE SET
+             calls = calls + excluded.calls, errors = errors + excluded.errors,
+             rate_limited = rate_limited + excluded.rate_limited",
+        )?
+        .execute(params![
+            rollup.hour.to_rfc3339(),
+            rollup.endpoint,
+            rollup.region,
+            rollup.calls,
+            rollup.errors,
+            rollup.rate_limited,
+        ])?;
+    }
+    for call in &calls {
+        conn.prepare_cached("DELETE FROM api_calls WHERE id = ?1")?
+            .execute([call.id])?;
+    }
+    Ok(calls.len() as u64)
+}
+
+/// Delete up to `limit` matches played on one of `game_versions`
+pub(super) fn prune_matches(
+    conn: &Connection,
+    game_versions: &[String],
+    keep_raw_payloads: bool,
+    limit: i64,
+) -> rusqlite::Result<u64> {
+    let placeholders = (2..game_versions.len() + 2)
+        .map(|i| format!("?{}", i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut params: Vec<&dyn rusqlite::ToSql> = vec![&limit];
+    params.extend(game_versions.iter().map(|v| v as &dyn rusqlite::ToSql));
+    let pruned = conn
+        .prepare(&format!(
+            "SELECT match_id, game_version FROM matches WHERE game_version IN ({}) LIMIT ?1",
+            placeholders
+        ))?
+        .query_map(params.as_slice(), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let pruned_at = Utc::now().to_rfc3339();
+    for (match_id, game_version) in &pruned {
+        delete_match_rows(conn, match_id, keep_raw_payloads)?;
+        conn.prepare_cached(
+            "INSERT OR REPLACE INTO pruned_matches (match_id, game_version, pruned_at)
+             VALUES (?1, ?2, ?3)",
+        )?
+        .execute(params![match_id, game_version, pruned_at])?;
+    }
+    Ok(pruned.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(endpoint: &str, timestamp: &str, response_code: i32) -> DbApiCall {
+        DbApiCall {
+            id: None,
+            endpoint: endpoint.to_string(),
+            region: "na1".to_string(),
+            timestamp: parse_timestamp(timestamp),
+            response_code,
+            rate_limit_remaining: None,
+        }
+    }
+
+    #[test]
+    fn test_endpoint_templates() {
+        assert_eq!(
+            endpoint_template("/lol/match/v5/matches/NA1_4567"),
+            "/lol/match/v5/matches/{}"
+        );
+        assert_eq!(
+            endpoint_template("/lol/match/v5/matches/by-puuid/Ab3-x_Y/ids?start=0&count=20"),
+            "/lol/match/v5/matches/by-puuid/{}/ids"
+        );
+        assert_eq!(
+            endpoint_template("/lol/league/v4/entries/RANKED_SOLO_5x5/GOLD/I?page=2"),
+            "/lol/league/v4/entries/{}/{}/{}"
+        );
+        assert_eq!(
+            endpoint_template("/lol/league/v4/challengerleagues/by-queue/RANKED_SOLO_5x5"),
+            "/lol/league/v4/challengerleagues/by-queue/{}"
+        );
+        assert_eq!(
+            endpoint_template("/lol/match/v5/matches/NA1_4567/timeline"),
+            "/lol/match/v5/matches/{}/timeline"
+        );
+        // Lowercase names are ids too
+        assert_eq!(
+            endpoint_template("/riot/account/v1/accounts/by-riot-id/faker/kr1"),
+            "/riot/account/v1/accounts/by-riot-id/{}/{}"
+        );
+        assert_eq!(
+            endpoint_template("/lol/summoner/v4/summoners/by-name/some-player"),
+            "/lol/summoner/v4/summoners/by-name/{}"
+        );
+    }
+
+    #[test]
+    fn test_calls_aggregate_by_hour_and_endpoint() {
+        let rollups = aggregate_api_calls(&[
+            call(
+                "/lol/match/v5/matches/NA1_1",
+                "2024-01-01T10:05:00+00:00",
+                200,
+            ),
+            call(
+                "/lol/match/v5/matches/NA1_2",
+                "2024-01-01T10:55:00+00:00",
+                429,
+            ),
+            call(
+                "/lol/match/v5/matches/NA1_3",
+                "2024-01-01T11:00:00+00:00",
+                404,
+            ),
+        ]);
+
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(
+            rollups[0].hour,
+            parse_timestamp("2024-01-01T10:00:00+00:00")
+        );
+        assert_eq!(rollups[0].endpoint, "/lol/match/v5/matches/{}");
+        assert_eq!(
+            (rollups[0].calls, rollups[0].errors, rollups[0].rate_limited),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            (rollups[1].calls, rollups[1].errors, rollups[1].rate_limited),
+            (1, 1, 0)
+        );
+    }
+
+    #[test]
+    fn test_rollup_moves_old_calls_into_aggregates() {
+        let db = Database::new(":memory:").unwrap();
+        for (timestamp, code) in [
+            ("2024-01-01T10:05:00+00:00", 200),
+            ("2024-01-01T10:35:00+00:00", 200),
+            ("2024-01-01T11:05:00+00:00", 500),
+            ("2024-03-01T00:00:00+00:00", 200),
+        ] {
+            db.log_api_call(&call("/lol/match/v5/matches/NA1_1", timestamp, code))
+                .unwrap();
+        }
+
+        let cutoff = parse_timestamp("2024-02-01T00:00:00+00:00");
+        assert_eq!(db.rollup_api_calls(cutoff, 2).unwrap(), 2);
+        assert_eq!(db.rollup_api_calls(cutoff, 2).unwrap(), 1);
+        assert_eq!(db.rollup_api_calls(cutoff, 2).unwrap(), 0);
+
+        let rollups = db.get_api_call_rollups().unwrap();
+        assert_eq!(rollups.len(), 2);
+        assert_eq!(rollups[0].calls, 2);
+        assert_eq!(rollups[1].errors, 1);
+        let remaining: i64 = db
+            .query_row("SELECT COUNT(*) FROM api_calls", &[], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+}
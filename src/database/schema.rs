@@ -1,16 +1,48 @@
+use super::migrations;
 use rusqlite::{Connection, Result as SqliteResult};
 
-/// Current database schema version
-pub const SCHEMA_VERSION: i32 = 1;
+/// Current database schema version: the baseline plus every embedded migration
+pub const SCHEMA_VERSION: i32 = 11;
+
+/// Version of the tables created directly by `Schema::initialize`. The baseline is
+/// frozen; schema changes go into a new file under `migrations/`.
+pub const BASELINE_VERSION: i32 = 1;
 
 /// Database schema management for League of Legends crawler
 pub struct Schema;
 
 impl Schema {
-    /// Initialize the complete database schema
+    /// Initialize the baseline schema if needed and apply any pending migrations
     pub fn initialize(conn: &Connection) -> SqliteResult<()> {
-        log::info!("Initializing database schema version {}", SCHEMA_VERSION);
+        migrations::create_schema_version_table(conn)?;
+
+        if Self::get_version(conn)? < BASELINE_VERSION {
+            // New database, or one created before schema versions were recorded
+            log::info!("Initializing baseline database schema");
+            let tx = conn.unchecked_transaction()?;
+            Self::create_baseline(&tx)?;
+            migrations::record_version(&tx, BASELINE_VERSION, "baseline")?;
+            tx.commit()?;
+        }
+
+        let applied = migrations::apply_pending(conn)?;
+        if !applied.is_empty() {
+            log::info!(
+                "Applied {} schema migrations, now at version {}",
+                applied.len(),
+                SCHEMA_VERSION
+            );
+        }
+
+        // Initialize default data
+        Self::initialize_default_data(conn)?;
+
+        log::info!("Database schema initialized successfully");
+        Ok(())
+    }
 
+    /// Create the version 1 tables and indexes
+    pub(crate) fn create_baseline(conn: &Connection) -> SqliteResult<()> {
         // Create all tables
         Self::create_summoners_table(conn)?;
         Self::create_matches_table(conn)?;
@@ -23,13 +55,7 @@ impl Schema {
         Self::create_active_games_table(conn)?;
 
         // Create indexes for performance
-        Self::create_indexes(conn)?;
-
-        // Initialize default data
-        Self::initialize_default_data(conn)?;
-
-        log::info!("Database schema initialized successfully");
-        Ok(())
+        Self::create_indexes(conn)
     }
 
     /// Create summoners table - stores player profile information
@@ -288,15 +314,19 @@ impl Schema {
         Ok(())
     }
 
-    /// Get the current schema version from the database
-    #[allow(dead_code)]
-    pub fn get_version(_conn: &Connection) -> SqliteResult<i32> {
-        // For now, we assume version 1. In future versions, we'd store this in a schema_info table
-        Ok(SCHEMA_VERSION)
+    /// Get the current schema version from the database (0 if none is recorded)
+    pub fn get_version(conn: &Connection) -> SqliteResult<i32> {
+        if !migrations::has_schema_version_table(conn)? {
+            return Ok(0);
+        }
+        conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )
     }
 
     /// Check if the database needs migration
-    #[allow(dead_code)]
     pub fn needs_migration(conn: &Connection) -> SqliteResult<bool> {
         let current_version = Self::get_version(conn)?;
         Ok(current_version < SCHEMA_VERSION)
@@ -328,6 +358,12 @@ mod tests {
 
     #[test]
     fn test_schema_version() {
-        assert_eq!(SCHEMA_VERSION, 1);
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(Schema::get_version(&conn).unwrap(), 0);
+        assert!(Schema::needs_migration(&conn).unwrap());
+
+        Schema::initialize(&conn).unwrap();
+        assert_eq!(Schema::get_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(!Schema::needs_migration(&conn).unwrap());
     }
 }
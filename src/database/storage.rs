@@ -0,0 +1,351 @@
+use super::postgres::PostgresStorage;
+use super::{operations, retention, Database};
+use crate::models::database::*;
+use crate::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+/// Persistence used by the crawler engine, worker and API client.
+///
+/// SQLite (`Database`) is the default backend; a `postgres://` or `postgresql://`
+/// `DATABASE_URL` selects `PostgresStorage`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn insert_summoner(&self, summoner: &DbSummoner) -> Result<()>;
+    async fn summoner_exists(&self, puuid: &str) -> Result<bool>;
+    async fn match_exists(&self, match_id: &str) -> Result<bool>;
+    /// Remember a match a hook declined, so it is not downloaded again
+    async fn record_vetoed_match(&self, vetoed: &DbVetoedMatch) -> Result<()>;
+    async fn match_vetoed(&self, match_id: &str) -> Result<bool>;
+    /// Whether retention deleted the match, so it is not downloaded again
+    async fn match_pruned(&self, match_id: &str) -> Result<bool>;
+    /// Store a match with its teams, bans, participants and stratum atomically
+    async fn store_match_bundle(&self, bundle: &DbMatchBundle) -> Result<()>;
+    /// Replace the rows `hook` produced for `match_id`
+    async fn store_hook_rows(
+        &self,
+        match_id: &str,
+        hook: &str,
+        rows: &[DbMatchHookRow],
+    ) -> Result<()>;
+    async fn upsert_summoner_rank(&self, rank: &DbSummonerRank) -> Result<()>;
+    async fn get_existing_summoners_with_tier(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<(String, String, Option<Tier>)>>;
+    async fn get_stratum_counts(&self) -> Result<Vec<StratumCount>>;
+    async fn log_api_call(&self, call: &DbApiCall) -> Result<()>;
+
+    async fn record_failed_task(&self, task: &DbFailedTask) -> Result<()>;
+    async fn resolve_failed_task(&self, task_type: FailedTaskType, target_id: &str)
+        -> Result<bool>;
+    async fn get_failed_tasks(
+        &self,
+        filter: &FailedTaskFilter,
+        status: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<DbFailedTask>>;
+    async fn get_failed_task_summary(&self) -> Result<Vec<(String, String, String, i64)>>;
+    async fn requeue_failed_tasks(&self, filter: &FailedTaskFilter) -> Result<usize>;
+
+    async fn save_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()>;
+    /// Add tasks to the persisted queue, keeping what was saved before
+    async fn append_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()>;
+    async fn take_pending_tasks(&self) -> Result<Vec<SummonerTask>>;
+    async fn get_pending_tasks_count(&self) -> Result<i64>;
+
+    async fn get_raw_payload(
+        &self,
+        match_id: &str,
+        kind: RawPayloadKind,
+    ) -> Result<Option<DbRawPayload>>;
+    async fn get_archived_match_ids(&self, after: Option<&str>, limit: i32) -> Result<Vec<String>>;
+
+    /// Fold up to `limit` `api_calls` rows older than `before` into hourly rollups,
+    /// returning how many rows were removed
+    async fn rollup_api_calls(&self, before: DateTime<Utc>, limit: i64) -> Result<u64>;
+    async fn get_game_versions(&self) -> Result<Vec<String>>;
+    /// Delete up to `limit` matches played on one of `game_versions`, returning how many
+    /// were deleted
+    async fn prune_matches(
+        &self,
+        game_versions: &[String],
+        keep_raw_payloads: bool,
+        limit: i64,
+    ) -> Result<u64>;
+    /// Give space freed by pruning back to the file system where the backend needs it
+    async fn reclaim_space(&self) -> Result<()>;
+
+    async fn get_crawler_state(&self) -> Result<Option<DbCrawlerState>>;
+    async fn update_crawler_state(&self, state: &DbCrawlerState) -> Result<()>;
+    async fn get_matches_count(&self) -> Result<i64>;
+    async fn get_summoners_count(&self) -> Result<i64>;
+    async fn get_participants_count(&self) -> Result<i64>;
+}
+
+/// Whether the URL selects the Postgres backend rather than a SQLite file
+pub fn is_postgres_url(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+/// The URL with any password replaced, for logging
+pub fn redact_url(database_url: &str) -> String {
+    let Some((scheme, rest)) = database_url.split_once("://") else {
+        return database_url.to_string();
+    };
+    match rest.split_once('@') {
+        Some((credentials, host)) if credentials.contains(':') => {
+            let user = credentials.split(':').next().unwrap_or_default();
+            format!("{}://{}:***@{}", scheme, user, host)
+        }
+        _ => database_url.to_string(),
+    }
+}
+
+/// Open the storage backend selected by the scheme of `database_url`, creating or
+/// migrating its schema
+pub async fn connect(database_url: &str) -> Result<Arc<dyn Storage>> {
+    if is_postgres_url(database_url) {
+        Ok(Arc::new(PostgresStorage::connect(database_url).await?))
+    } else {
+        Ok(Arc::new(Database::new(database_url)?))
+    }
+}
+
+// Writes await the writer thread and reads run on the blocking pool, so these never
+// block the runtime
+#[async_trait]
+impl Storage for Database {
+    async fn insert_summoner(&self, summoner: &DbSummoner) -> Result<()> {
+        let summoner = summoner.clone();
+        self.write_async(move |conn| operations::write_summoner(conn, &summoner))
+            .await
+    }
+
+    async fn summoner_exists(&self, puuid: &str) -> Result<bool> {
+        let puuid = puuid.to_string();
+        self.read_async(move |db| db.summoner_exists(&puuid)).await
+    }
+
+    async fn match_exists(&self, match_id: &str) -> Result<bool> {
+        let match_id = match_id.to_string();
+        self.read_async(move |db| db.match_exists(&match_id)).await
+    }
+
+    async fn record_vetoed_match(&self, vetoed: &DbVetoedMatch) -> Result<()> {
+        let vetoed = vetoed.clone();
+        self.write_async(move |conn| operations::write_vetoed_match(conn, &vetoed))
+            .await
+    }
+
+    async fn match_vetoed(&self, match_id: &str) -> Result<bool> {
+        let match_id = match_id.to_string();
+        self.read_async(move |db| db.match_vetoed(&match_id)).await
+    }
+
+    async fn match_pruned(&self, match_id: &str) -> Result<bool> {
+        let match_id = match_id.to_string();
+        self.read_async(move |db| db.match_pruned(&match_id)).await
+    }
+
+    async fn store_match_bundle(&self, bundle: &DbMatchBundle) -> Result<()> {
+        let bundle = bundle.clone();
+        self.transaction_async(move |tx| operations::write_match_bundle(tx, &bundle))
+            .await
+    }
+
+    async fn store_hook_rows(
+        &self,
+        match_id: &str,
+        hook: &str,
+        rows: &[DbMatchHookRow],
+    ) -> Result<()> {
+        let (match_id, hook, rows) = (match_id.to_string(), hook.to_string(), rows.to_vec());
+        self.transaction_async(move |tx| operations::write_hook_rows(tx, &match_id, &hook, &rows))
+            .await
+    }
+
+    async fn upsert_summoner_rank(&self, rank: &DbSummonerRank) -> Result<()> {
+        let rank = rank.clone();
+        self.write_async(move |conn| operations::write_summoner_rank(conn, &rank))
+            .await
+    }
+
+    async fn get_existing_summoners_with_tier(
+        &self,
+        limit: i32,
+    ) -> Result<Vec<(String, String, Option<Tier>)>> {
+        self.read_async(move |db| db.get_existing_summoners_with_tier(limit))
+            .await
+    }
+
+    async fn get_stratum_counts(&self) -> Result<Vec<StratumCount>> {
+        self.read_async(|db| db.get_stratum_counts()).await
+    }
+
+    async fn log_api_call(&self, call: &DbApiCall) -> Result<()> {
+        let call = call.clone();
+        self.write_async(move |conn| operations::write_api_call(conn, &call))
+            .await
+    }
+
+    async fn record_failed_task(&self, task: &DbFailedTask) -> Result<()> {
+        let task = task.clone();
+        self.write_async(move |conn| operations::write_failed_task(conn, &task))
+            .await
+    }
+
+    async fn resolve_failed_task(
+        &self,
+        task_type: FailedTaskType,
+        target_id: &str,
+    ) -> Result<bool> {
+        let target_id = target_id.to_string();
+        self.write_async(move |conn| operations::delete_failed_task(conn, task_type, &target_id))
+            .await
+    }
+
+    async fn get_failed_tasks(
+        &self,
+        filter: &FailedTaskFilter,
+        status: Option<&str>,
+        limit: i32,
+    ) -> Result<Vec<DbFailedTask>> {
+        let (filter, status) = (filter.clone(), status.map(str::to_string));
+        self.read_async(move |db| db.get_failed_tasks(&filter, status.as_deref(), limit))
+            .await
+    }
+
+    async fn get_failed_task_summary(&self) -> Result<Vec<(String, String, String, i64)>> {
+        self.read_async(|db| db.get_failed_task_summary()).await
+    }
+
+    async fn requeue_failed_tasks(&self, filter: &FailedTaskFilter) -> Result<usize> {
+        let filter = filter.clone();
+        self.write_async(move |conn| operations::requeue_failed(conn, &filter))
+            .await
+    }
+
+    async fn save_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        let tasks = tasks.to_vec();
+        self.transaction_async(move |tx| operations::write_pending_tasks(tx, &tasks, true))
+            .await
+    }
+
+    async fn append_pending_tasks(&self, tasks: &[SummonerTask]) -> Result<()> {
+        let tasks = tasks.to_vec();
+        self.transaction_async(move |tx| operations::write_pending_tasks(tx, &tasks, false))
+            .await
+    }
+
+    async fn take_pending_tasks(&self) -> Result<Vec<SummonerTask>> {
+        self.transaction_async(|tx| operations::take_pending(tx))
+            .await
+    }
+
+    async fn get_pending_tasks_count(&self) -> Result<i64> {
+        self.read_async(|db| db.get_pending_tasks_count()).await
+    }
+
+    async fn get_raw_payload(
+        &self,
+        match_id: &str,
+        kind: RawPayloadKind,
+    ) -> Result<Option<DbRawPayload>> {
+        let match_id = match_id.to_string();
+        self.read_async(move |db| db.get_raw_payload(&match_id, kind))
+            .await
+    }
+
+    async fn get_archived_match_ids(&self, after: Option<&str>, limit: i32) -> Result<Vec<String>> {
+        let after = after.map(str::to_string);
+        self.read_async(move |db| db.get_archived_match_ids(after.as_deref(), limit))
+            .await
+    }
+
+    async fn rollup_api_calls(&self, before: DateTime<Utc>, limit: i64) -> Result<u64> {
+        self.transaction_async(move |tx| retention::rollup_api_calls(tx, before, limit))
+            .await
+    }
+
+    async fn get_game_versions(&self) -> Result<Vec<String>> {
+        self.read_async(|db| db.get_game_versions()).await
+    }
+
+    async fn prune_matches(
+        &self,
+        game_versions: &[String],
+        keep_raw_payloads: bool,
+        limit: i64,
+    ) -> Result<u64> {
+        if game_versions.is_empty() {
+            return Ok(0);
+        }
+        let game_versions = game_versions.to_vec();
+        self.transaction_async(move |tx| {
+            retention::prune_matches(tx, &game_versions, keep_raw_payloads, limit)
+        })
+        .await
+    }
+
+    async fn reclaim_space(&self) -> Result<()> {
+        self.write_async(|conn| retention::reclaim_space(conn))
+            .await
+    }
+
+    async fn get_crawler_state(&self) -> Result<Option<DbCrawlerState>> {
+        self.read_async(|db| db.get_crawler_state()).await
+    }
+
+    async fn update_crawler_state(&self, state: &DbCrawlerState) -> Result<()> {
+        let state = state.clone();
+        self.write_async(move |conn| operations::write_crawler_state(conn, &state))
+            .await
+    }
+
+    async fn get_matches_count(&self) -> Result<i64> {
+        self.read_async(|db| db.get_matches_count()).await
+    }
+
+    async fn get_summoners_count(&self) -> Result<i64> {
+        self.read_async(|db| db.get_summoners_count()).await
+    }
+
+    async fn get_participants_count(&self) -> Result<i64> {
+        self.read_async(|db| db.get_participants_count()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_selected_by_scheme() {
+        assert!(is_postgres_url("postgres://crawler@localhost/lol"));
+        assert!(is_postgres_url("postgresql://crawler@localhost:5432/lol"));
+        assert!(!is_postgres_url("data/lol_crawler.db"));
+        assert!(!is_postgres_url(":memory:"));
+    }
+
+    #[test]
+    fn test_redact_url() {
+        assert_eq!(
+            redact_url("postgres://crawler:hunter2@db:5432/lol"),
+            "postgres://crawler:***@db:5432/lol"
+        );
+        assert_eq!(
+            redact_url("postgres://crawler@db/lol"),
+            "postgres://crawler@db/lol"
+        );
+        assert_eq!(redact_url("data/lol.db"), "data/lol.db");
+    }
+
+    #[tokio::test]
+    async fn test_connect_defaults_to_sqlite() {
+        let storage = connect(":memory:").await.unwrap();
+        assert_eq!(storage.get_matches_count().await.unwrap(), 0);
+        assert_eq!(storage.get_pending_tasks_count().await.unwrap(), 0);
+    }
+}
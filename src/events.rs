@@ -0,0 +1,362 @@
+use crate::config::EventsConfig;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    CrawlerStarted,
+    CrawlerStopped,
+    /// A run budget was used up and the crawl is draining
+    BudgetReached,
+    /// Tasks in a region kept failing with 5xx responses after their retries
+    ServerErrors,
+    /// Tasks in a region kept failing on 429 responses after their retries
+    RateLimitStorm,
+    /// Storing a task's or a match's data failed
+    StorageFailure,
+    /// `verify` found broken matches or orphaned rows
+    IntegrityIssues,
+    /// A key was taken out of rotation after repeated 401/403 responses
+    ApiKeyRetired,
+    /// No API key is left to send requests with; the crawler paused itself
+    ApiKeysExpired,
+    /// A working key is back and the crawler resumed
+    ApiKeysRecovered,
+    DailySummary,
+}
+
+impl EventKind {
+    /// Whether someone should look at the crawler
+    pub fn is_incident(self) -> bool {
+        matches!(
+            self,
+            EventKind::ServerErrors
+                | EventKind::RateLimitStorm
+                | EventKind::StorageFailure
+                | EventKind::IntegrityIssues
+                | EventKind::ApiKeyRetired
+                | EventKind::ApiKeysExpired
+        )
+    }
+}
+
+/// What sinks receive, and the body of the webhook request
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub kind: EventKind,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            region: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn in_region(mut self, region: &str) -> Self {
+        self.region = Some(region.to_string());
+        self
+    }
+}
+
+/// Destination for crawler events
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Name used when delivery fails
+    fn name(&self) -> &str;
+
+    async fn send(&self, event: &Event) -> crate::Result<()>;
+}
+
+/// POSTs each event as JSON, retrying failed deliveries with exponential backoff
+pub struct WebhookSink {
+    client: Client,
+    url: String,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, retries: u32, retry_delay: Duration) -> crate::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent("lol-crawler/1.0")
+            .build()?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            retries,
+            retry_delay,
+        })
+    }
+
+    /// Delay before retry `attempt`, doubling from `retry_delay` and saturating rather
+    /// than overflowing
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+
+    async fn post(&self, event: &Event) -> reqwest::Result<()> {
+        self.client
+            .post(&self.url)
+            .json(event)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    async fn send(&self, event: &Event) -> crate::Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.post(event).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    let delay = self.backoff(attempt);
+                    log::debug!(
+                        "Webhook delivery failed (attempt {}/{}): {}. Retrying in {:?}",
+                        attempt,
+                        self.retries + 1,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Appends each event to a file as one JSON object per line
+pub struct FileSink {
+    path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl EventSink for FileSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn send(&self, event: &Event) -> crate::Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        // Opened per event so the file can be rotated underneath the crawler
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", self.path.display(), e))?;
+        file.write_all(&line).await?;
+        // tokio writes in the background; wait until the line reached the file
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+enum Message {
+    Event(Event),
+    /// Answered once every event sent before it was delivered
+    Flush(oneshot::Sender<()>),
+}
+
+/// Fans events out to the sinks. Events are always logged, and delivered in order by a
+/// background task so a slow or unreachable sink never holds up the crawler.
+pub struct EventBus {
+    sinks: Arc<RwLock<Vec<Arc<dyn EventSink>>>>,
+    sender: OnceLock<mpsc::UnboundedSender<Message>>,
+}
+
+impl EventBus {
+    pub fn new(config: &EventsConfig) -> crate::Result<Self> {
+        let mut sinks: Vec<Arc<dyn EventSink>> = Vec::new();
+        if let Some(url) = &config.webhook_url {
+            sinks.push(Arc::new(WebhookSink::new(
+                url,
+                config.webhook_retries,
+                Duration::from_millis(config.webhook_retry_delay_ms),
+            )?));
+        }
+        if let Some(path) = &config.file {
+            sinks.push(Arc::new(FileSink::new(path.clone())));
+        }
+        Ok(Self {
+            sinks: Arc::new(RwLock::new(sinks)),
+            sender: OnceLock::new(),
+        })
+    }
+
+    /// Also deliver events to `sink`
+    pub fn add_sink(&self, sink: Arc<dyn EventSink>) {
+        self.sinks.write().unwrap().push(sink);
+    }
+
+    fn has_sinks(&self) -> bool {
+        !self.sinks.read().unwrap().is_empty()
+    }
+
+    pub fn emit(&self, event: Event) {
+        let region = event.region.as_deref().unwrap_or("-");
+        if event.kind.is_incident() {
+            tracing::warn!(kind = ?event.kind, region, "{}", event.message);
+        } else {
+            tracing::info!(kind = ?event.kind, region, "{}", event.message);
+        }
+        if self.has_sinks() {
+            // The receiver lives as long as the bus
+            let _ = self.sender().send(Message::Event(event));
+        }
+    }
+
+    /// Wait until every event emitted so far has been delivered or given up on
+    pub async fn flush(&self) {
+        if !self.has_sinks() {
+            return;
+        }
+        let (done, delivered) = oneshot::channel();
+        if self.sender().send(Message::Flush(done)).is_ok() {
+            let _ = delivered.await;
+        }
+    }
+
+    /// The dispatcher is started by the first event, from within the runtime
+    fn sender(&self) -> &mpsc::UnboundedSender<Message> {
+        self.sender.get_or_init(|| {
+            let (sender, mut receiver) = mpsc::unbounded_channel();
+            let sinks = self.sinks.clone();
+            tokio::spawn(async move {
+                while let Some(message) = receiver.recv().await {
+                    match message {
+                        Message::Event(event) => {
+                            let sinks = sinks.read().unwrap().clone();
+                            for sink in &sinks {
+                                if let Err(e) = sink.send(&event).await {
+                                    log::error!(
+                                        "Failed to deliver {:?} event to {}: {}",
+                                        event.kind,
+                                        sink.name(),
+                                        e
+                                    );
+                                }
+                            }
+                        }
+                        Message::Flush(done) => {
+                            let _ = done.send(());
+                        }
+                    }
+                }
+            });
+            sender
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    #[test]
+    fn test_webhook_backoff_saturates() {
+        let sink = WebhookSink::new("http://localhost", 40, Duration::from_secs(1)).unwrap();
+        assert_eq!(sink.backoff(1), Duration::from_secs(1));
+        assert_eq!(sink.backoff(4), Duration::from_secs(8));
+        assert_eq!(sink.backoff(40), Duration::from_secs(u32::MAX.into()));
+    }
+
+    #[tokio::test]
+    async fn test_events_reach_webhook_and_file() {
+        let mut server = Server::new_async().await;
+        let hook = server
+            .mock("POST", "/hooks/crawler")
+            .match_body(Matcher::PartialJson(serde_json::json!({
+                "kind": "server_errors",
+                "region": "euw1",
+            })))
+            .with_status(204)
+            .create_async()
+            .await;
+        let path =
+            std::env::temp_dir().join(format!("lol-crawler-events-{}", uuid::Uuid::new_v4()));
+
+        let bus = EventBus::new(&EventsConfig {
+            webhook_url: Some(format!("{}/hooks/crawler", server.url())),
+            webhook_retries: 0,
+            file: Some(path.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+        bus.emit(Event::new(EventKind::ServerErrors, "5xx in a row").in_region("euw1"));
+        bus.emit(Event::new(EventKind::CrawlerStopped, "done"));
+        bus.flush().await;
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["kind"], "server_errors");
+        assert_eq!(lines[1]["kind"], "crawler_stopped");
+        assert!(lines[1].get("region").is_none());
+        std::fs::remove_file(path).unwrap();
+        // The second event went to the webhook too, but did not match this mock
+        hook.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_failed_deliveries() {
+        let mut server = Server::new_async().await;
+        let hook = server
+            .mock("POST", "/hooks/crawler")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let sink = WebhookSink::new(
+            &format!("{}/hooks/crawler", server.url()),
+            2,
+            Duration::from_millis(10),
+        )
+        .unwrap();
+        let result = sink
+            .send(&Event::new(EventKind::DailySummary, "summary"))
+            .await;
+        assert!(result.is_err());
+
+        hook.assert_async().await;
+    }
+}
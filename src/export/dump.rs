@@ -0,0 +1,481 @@
+use super::{latest_stored_seq, page_end, table_columns, Column, ColumnKind};
+use crate::database::Database;
+use crate::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::types::Value;
+use std::io::Write;
+
+/// Line-oriented output format of a dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// One JSON object per line
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+impl std::str::FromStr for DumpFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "ndjson" | "jsonl" => Ok(DumpFormat::Ndjson),
+            "csv" => Ok(DumpFormat::Csv),
+            _ => Err(format!("Unknown dump format '{}'", s)),
+        }
+    }
+}
+
+/// Which rows a dump contains
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpTable {
+    Matches,
+    Participants,
+}
+
+impl std::str::FromStr for DumpTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "matches" => Ok(DumpTable::Matches),
+            "participants" => Ok(DumpTable::Participants),
+            _ => Err(format!("Unknown dump table '{}'", s)),
+        }
+    }
+}
+
+/// Restricts a dump to matching rows; unset fields match everything. Champion and puuid
+/// filters keep the matches in which such a participant played when dumping matches.
+#[derive(Debug, Clone, Default)]
+pub struct DumpFilter {
+    pub region: Option<String>,
+    pub queue_id: Option<i32>,
+    /// Patch such as `14.1`, matched against the start of the game version
+    pub patch: Option<String>,
+    /// Games created at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Games created before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Champion id or name (case-insensitive)
+    pub champion: Option<String>,
+    pub puuid: Option<String>,
+}
+
+/// What to dump and how
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    pub table: DumpTable,
+    pub format: DumpFormat,
+    pub filter: DumpFilter,
+    /// Add the participant's match (`match_*`) and team (`team_*`) columns to each row
+    pub join: bool,
+    /// Matches read per page; bounds memory use
+    pub batch_size: usize,
+}
+
+impl DumpOptions {
+    pub fn new(table: DumpTable, format: DumpFormat) -> Self {
+        Self {
+            table,
+            format,
+            filter: DumpFilter::default(),
+            join: false,
+            batch_size: 500,
+        }
+    }
+}
+
+/// Start of the day for `YYYY-MM-DD`, or an RFC 3339 timestamp
+pub fn parse_date_bound(value: &str) -> std::result::Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| {
+            format!(
+                "Expected YYYY-MM-DD or an RFC 3339 timestamp, got '{}'",
+                value
+            )
+        })
+}
+
+/// Write the rows selected by `options` to `out`, a page of matches at a time, and
+/// return the number of rows written
+pub fn dump_rows(db: &Database, options: &DumpOptions, out: &mut dyn Write) -> Result<u64> {
+    let (columns, sql, mut params) = build_query(db, options)?;
+    let mut writer = RowWriter::new(options.format, &columns, out)?;
+
+    let to_seq = latest_stored_seq(db)?;
+    let mut page_start = 0;
+    while page_start < to_seq {
+        let page_stop = page_end(db, page_start, to_seq, options.batch_size)?;
+        params[0] = Value::Integer(page_start);
+        params[1] = Value::Integer(page_stop);
+        let refs: Vec<&dyn rusqlite::ToSql> =
+            params.iter().map(|p| p as &dyn rusqlite::ToSql).collect();
+
+        let width = columns.len();
+        let rows = db.query_map(&sql, &refs, |row| {
+            (0..width)
+                .map(|i| row.get::<_, Value>(i))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })?;
+        for row in rows {
+            writer.write_row(&row)?;
+        }
+        page_start = page_stop;
+    }
+
+    writer.finish()
+}
+
+/// Output columns, SQL and parameters of a dump; the first two parameters are the
+/// `stored_seq` page bounds, filled in per page
+fn build_query(db: &Database, options: &DumpOptions) -> Result<(Vec<Column>, String, Vec<Value>)> {
+    let mut columns = Vec::new();
+    let mut select = Vec::new();
+    let mut add = |alias: &str, prefix: &str, skip: &[&str], table: &[Column]| {
+        for column in table.iter().filter(|c| !skip.contains(&c.name.as_str())) {
+            select.push(format!("{}.\"{}\"", alias, column.name));
+            columns.push(Column {
+                name: format!("{}{}", prefix, column.name),
+                kind: column.kind,
+            });
+        }
+    };
+
+    let from = match options.table {
+        DumpTable::Matches => {
+            add("m", "", &[], &table_columns(db, "matches")?);
+            "matches m".to_string()
+        }
+        DumpTable::Participants if options.join => {
+            add("p", "", &[], &table_columns(db, "participants")?);
+            add("m", "match_", &["match_id"], &table_columns(db, "matches")?);
+            add(
+                "t",
+                "team_",
+                &["id", "match_id", "team_id"],
+                &table_columns(db, "teams")?,
+            );
+            "participants p JOIN matches m ON m.match_id = p.match_id
+             LEFT JOIN teams t ON t.match_id = p.match_id AND t.team_id = p.team_id"
+                .to_string()
+        }
+        DumpTable::Participants => {
+            add("p", "", &[], &table_columns(db, "participants")?);
+            "participants p JOIN matches m ON m.match_id = p.match_id".to_string()
+        }
+    };
+
+    let mut params = vec![Value::Null, Value::Null];
+    let mut conditions = vec!["m.stored_seq > ?1 AND m.stored_seq <= ?2".to_string()];
+
+    let filter = &options.filter;
+    if let Some(region) = &filter.region {
+        let region = Value::Text(region.to_ascii_lowercase());
+        conditions.push(bind(&mut params, "m.region = ?", region));
+    }
+    if let Some(queue_id) = filter.queue_id {
+        let queue_id = Value::Integer(queue_id.into());
+        conditions.push(bind(&mut params, "m.queue_id = ?", queue_id));
+    }
+    if let Some(patch) = &filter.patch {
+        conditions.push(bind(
+            &mut params,
+            "(m.game_version = ? OR m.game_version LIKE ? || '.%')",
+            Value::Text(patch.clone()),
+        ));
+    }
+    if let Some(since) = filter.since {
+        let since = Value::Integer(since.timestamp_millis());
+        conditions.push(bind(&mut params, "m.game_creation >= ?", since));
+    }
+    if let Some(until) = filter.until {
+        let until = Value::Integer(until.timestamp_millis());
+        conditions.push(bind(&mut params, "m.game_creation < ?", until));
+    }
+
+    // Dumping matches keeps those with a matching participant
+    let alias = match options.table {
+        DumpTable::Matches => "x",
+        DumpTable::Participants => "p",
+    };
+    let mut participant_conditions = Vec::new();
+    if let Some(champion) = &filter.champion {
+        participant_conditions.push(match champion.parse::<i64>() {
+            Ok(id) => bind(
+                &mut params,
+                &format!("{}.champion_id = ?", alias),
+                Value::Integer(id),
+            ),
+            Err(_) => bind(
+                &mut params,
+                &format!("{}.champion_name = ? COLLATE NOCASE", alias),
+                Value::Text(champion.clone()),
+            ),
+        });
+    }
+    if let Some(puuid) = &filter.puuid {
+        participant_conditions.push(bind(
+            &mut params,
+            &format!("{}.puuid = ?", alias),
+            Value::Text(puuid.clone()),
+        ));
+    }
+    if !participant_conditions.is_empty() {
+        if options.table == DumpTable::Matches {
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM participants x WHERE x.match_id = m.match_id AND {})",
+                participant_conditions.join(" AND ")
+            ));
+        } else {
+            conditions.extend(participant_conditions);
+        }
+    }
+
+    let order = if options.table == DumpTable::Matches {
+        "m.stored_seq"
+    } else {
+        "m.stored_seq, p.id"
+    };
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} ORDER BY {}",
+        select.join(", "),
+        from,
+        conditions.join(" AND "),
+        order
+    );
+    Ok((columns, sql, params))
+}
+
+/// `condition` with its `?` placeholders numbered for `value`, which is added to `params`
+fn bind(params: &mut Vec<Value>, condition: &str, value: Value) -> String {
+    params.push(value);
+    condition.replace('?', &format!("?{}", params.len()))
+}
+
+enum RowWriter<'a> {
+    Ndjson {
+        columns: Vec<Column>,
+        out: &'a mut dyn Write,
+        rows: u64,
+    },
+    Csv {
+        columns: Vec<Column>,
+        writer: Box<csv::Writer<&'a mut dyn Write>>,
+        rows: u64,
+    },
+}
+
+impl<'a> RowWriter<'a> {
+    fn new(format: DumpFormat, columns: &[Column], out: &'a mut dyn Write) -> Result<Self> {
+        let columns = columns.to_vec();
+        Ok(match format {
+            DumpFormat::Ndjson => RowWriter::Ndjson {
+                columns,
+                out,
+                rows: 0,
+            },
+            DumpFormat::Csv => {
+                let mut writer = Box::new(csv::Writer::from_writer(out));
+                writer.write_record(columns.iter().map(|c| c.name.as_str()))?;
+                RowWriter::Csv {
+                    columns,
+                    writer,
+                    rows: 0,
+                }
+            }
+        })
+    }
+
+    fn write_row(&mut self, row: &[Value]) -> Result<()> {
+        match self {
+            RowWriter::Ndjson { columns, out, rows } => {
+                // Built by hand so keys keep the table's column order
+                let mut line = String::from("{");
+                for (i, (column, value)) in columns.iter().zip(row).enumerate() {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    line.push_str(&serde_json::to_string(&column.name)?);
+                    line.push(':');
+                    line.push_str(&json_value(column.kind, value).to_string());
+                }
+                line.push('}');
+                writeln!(out, "{}", line)?;
+                *rows += 1;
+            }
+            RowWriter::Csv {
+                columns,
+                writer,
+                rows,
+            } => {
+                writer.write_record(
+                    columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| csv_field(column.kind, value)),
+                )?;
+                *rows += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<u64> {
+        match self {
+            RowWriter::Ndjson { out, rows, .. } => {
+                out.flush()?;
+                Ok(rows)
+            }
+            RowWriter::Csv {
+                mut writer, rows, ..
+            } => {
+                writer.flush()?;
+                Ok(rows)
+            }
+        }
+    }
+}
+
+fn json_value(kind: ColumnKind, value: &Value) -> serde_json::Value {
+    match (kind, value) {
+        (_, Value::Null) => serde_json::Value::Null,
+        (ColumnKind::Boolean, Value::Integer(i)) => serde_json::Value::Bool(*i != 0),
+        (_, Value::Integer(i)) => (*i).into(),
+        (_, Value::Real(f)) => (*f).into(),
+        (_, Value::Text(s)) => s.clone().into(),
+        (_, Value::Blob(b)) => b.iter().map(|byte| format!("{:02x}", byte)).collect(),
+    }
+}
+
+fn csv_field(kind: ColumnKind, value: &Value) -> String {
+    match json_value(kind, value) {
+        serde_json::Value::Null => String::new(),
+        serde_json::Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::database::{DbMatch, DbParticipant};
+    use crate::models::fixtures::db_match;
+
+    fn seed(db: &Database) {
+        for (i, (region, champion)) in [("na1", "Ahri"), ("euw1", "Garen")].iter().enumerate() {
+            let match_id = format!("DUMP_{}", i);
+            db.insert_match(&DbMatch {
+                game_creation: 1_700_000_000_000 + i as i64 * 86_400_000,
+                game_id: i as i64,
+                platform_id: region.to_ascii_uppercase(),
+                region: region.to_string(),
+                ..db_match(&match_id)
+            })
+            .unwrap();
+            db.insert_participant(&DbParticipant {
+                match_id,
+                puuid: format!("puuid-{}", i),
+                champion_name: Some(champion.to_string()),
+                champion_id: i as i32 + 1,
+                team_id: 100,
+                win: true,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+    }
+
+    fn dump(db: &Database, options: &DumpOptions) -> (u64, String) {
+        let mut out = Vec::new();
+        let rows = dump_rows(db, options, &mut out).unwrap();
+        (rows, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_parse_date_bound() {
+        assert_eq!(
+            parse_date_bound("2024-01-02").unwrap().to_rfc3339(),
+            "2024-01-02T00:00:00+00:00"
+        );
+        assert!(parse_date_bound("2024-01-02T03:04:05Z").is_ok());
+        assert!(parse_date_bound("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_dump_filters() {
+        let db = Database::new(":memory:").unwrap();
+        seed(&db);
+
+        let mut options = DumpOptions::new(DumpTable::Matches, DumpFormat::Ndjson);
+        assert_eq!(dump(&db, &options).0, 2);
+
+        options.filter.region = Some("EUW1".to_string());
+        assert_eq!(dump(&db, &options).0, 1);
+
+        options.filter = DumpFilter {
+            champion: Some("ahri".to_string()),
+            patch: Some("14.1".to_string()),
+            ..Default::default()
+        };
+        let (rows, out) = dump(&db, &options);
+        assert_eq!(rows, 1);
+        assert!(out.contains("\"match_id\":\"DUMP_0\""));
+
+        options.filter = DumpFilter {
+            since: Some(parse_date_bound("2023-11-15").unwrap()),
+            patch: Some("14.10".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(dump(&db, &options).0, 0);
+
+        options.filter = DumpFilter {
+            since: Some(parse_date_bound("2023-11-15").unwrap()),
+            champion: Some("2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(dump(&db, &options).0, 1);
+    }
+
+    #[test]
+    fn test_dump_joined_participants_as_csv() {
+        let db = Database::new(":memory:").unwrap();
+        seed(&db);
+
+        let mut options = DumpOptions::new(DumpTable::Participants, DumpFormat::Csv);
+        options.join = true;
+        options.filter.puuid = Some("puuid-1".to_string());
+        let (rows, out) = dump(&db, &options);
+        assert_eq!(rows, 1);
+
+        let mut reader = csv::Reader::from_reader(out.as_bytes());
+        let headers = reader.headers().unwrap().clone();
+        let record = reader.records().next().unwrap().unwrap();
+        let field = |name: &str| {
+            let i = headers.iter().position(|h| h == name).unwrap();
+            record[i].to_string()
+        };
+        assert_eq!(field("champion_name"), "Garen");
+        assert_eq!(field("match_region"), "euw1");
+        assert_eq!(field("win"), "true");
+        // No team row was stored, so the team columns are empty
+        assert_eq!(field("team_win"), "");
+    }
+
+    #[test]
+    fn test_ndjson_rows_are_typed() {
+        let db = Database::new(":memory:").unwrap();
+        seed(&db);
+
+        let options = DumpOptions::new(DumpTable::Participants, DumpFormat::Ndjson);
+        let (_, out) = dump(&db, &options);
+        let first: serde_json::Value = serde_json::from_str(out.lines().next().unwrap()).unwrap();
+        assert_eq!(first["win"], serde_json::Value::Bool(true));
+        assert_eq!(first["champion_id"], 1);
+        assert_eq!(first["puuid"], "puuid-0");
+    }
+}
@@ -0,0 +1,407 @@
+mod dump;
+mod parquet;
+
+pub use self::dump::{dump_rows, parse_date_bound, DumpFilter, DumpFormat, DumpOptions, DumpTable};
+pub use self::parquet::export_parquet;
+
+use crate::crawler::patch_from_game_version;
+use crate::database::{decode_payload, Database};
+use crate::models::database::{DbRawPayload, RawPayloadKind};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use rusqlite::types::Value;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Tables exported row for row, partitioned by the region and patch of their match
+pub const EXPORT_TABLES: &[&str] = &[
+    "matches",
+    "participants",
+    "teams",
+    "bans",
+    "participant_perks",
+    "participant_challenges",
+];
+
+/// Events flattened out of archived match timelines
+pub const TIMELINE_EVENTS_TABLE: &str = "timeline_events";
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Where and how much to export
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub output_dir: PathBuf,
+    /// Export everything instead of only matches stored since the last watermark
+    pub full: bool,
+    /// Matches read per page; bounds memory use
+    pub batch_size: usize,
+}
+
+impl ExportOptions {
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            output_dir: output_dir.into(),
+            full: false,
+            batch_size: 500,
+        }
+    }
+}
+
+/// Export history kept next to the exported files. `watermark` is the highest match
+/// `stored_seq` exported so far; the next incremental export starts after it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub watermark: i64,
+    pub exports: Vec<ManifestExport>,
+}
+
+/// One export run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestExport {
+    pub exported_at: DateTime<Utc>,
+    /// Matches with `from_seq < stored_seq <= to_seq` were exported
+    pub from_seq: i64,
+    pub to_seq: i64,
+    pub matches: u64,
+    pub files: Vec<ManifestFile>,
+}
+
+/// A file written by an export run, relative to the output directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub table: String,
+    pub region: String,
+    pub patch: String,
+    pub path: String,
+    pub rows: u64,
+}
+
+impl Manifest {
+    /// The manifest in `dir`, or an empty one before the first export
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Replace the manifest in `dir`; written to a temporary file first so readers never
+    /// see a partial manifest
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(tmp, dir.join(MANIFEST_FILE))?;
+        Ok(())
+    }
+}
+
+/// Region and patch directory a row is written to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Partition {
+    pub region: String,
+    pub patch: String,
+}
+
+impl Partition {
+    fn new(region: Option<String>, game_version: Option<String>) -> Self {
+        let patch = game_version
+            .map(|v| patch_from_game_version(&v))
+            .filter(|p| !p.is_empty());
+        Self {
+            region: sanitize(region.as_deref().unwrap_or("unknown")),
+            patch: sanitize(patch.as_deref().unwrap_or("unknown")),
+        }
+    }
+
+    /// Hive-style directory, e.g. `participants/region=na1/patch=14.1`
+    pub fn dir(&self, table: &str) -> PathBuf {
+        Path::new(table)
+            .join(format!("region={}", self.region))
+            .join(format!("patch={}", self.patch))
+    }
+}
+
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Type of an exported column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Integer,
+    Real,
+    Boolean,
+    Text,
+    Blob,
+}
+
+impl ColumnKind {
+    /// Column kind for a declared SQLite type, following SQLite's affinity rules
+    fn from_declared(declared: &str) -> Self {
+        let declared = declared.to_ascii_uppercase();
+        if declared.contains("BOOL") {
+            ColumnKind::Boolean
+        } else if declared.contains("INT") {
+            ColumnKind::Integer
+        } else if declared.contains("CHAR")
+            || declared.contains("CLOB")
+            || declared.contains("TEXT")
+        {
+            ColumnKind::Text
+        } else if declared.contains("BLOB") {
+            ColumnKind::Blob
+        } else if declared.contains("REAL")
+            || declared.contains("FLOA")
+            || declared.contains("DOUB")
+        {
+            ColumnKind::Real
+        } else {
+            ColumnKind::Text
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    pub kind: ColumnKind,
+}
+
+impl Column {
+    fn new(name: &str, kind: ColumnKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+        }
+    }
+}
+
+/// Columns of an exported table, in table order
+pub fn table_columns(db: &Database, table: &str) -> Result<Vec<Column>> {
+    if table == TIMELINE_EVENTS_TABLE {
+        return Ok(vec![
+            Column::new("match_id", ColumnKind::Text),
+            Column::new("timestamp", ColumnKind::Integer),
+            Column::new("type", ColumnKind::Text),
+            Column::new("participant_id", ColumnKind::Integer),
+            Column::new("event", ColumnKind::Text),
+        ]);
+    }
+    db.query_map(
+        &format!("SELECT name, type FROM pragma_table_info('{}')", table),
+        &[],
+        |row| {
+            let declared: String = row.get(1)?;
+            Ok(Column::new(
+                &row.get::<_, String>(0)?,
+                ColumnKind::from_declared(&declared),
+            ))
+        },
+    )
+}
+
+/// Highest `stored_seq` of any match; exports stop here so matches stored while an
+/// export runs are left for the next one
+pub fn latest_stored_seq(db: &Database) -> Result<i64> {
+    db.query_row(
+        "SELECT COALESCE(MAX(stored_seq), 0) FROM matches",
+        &[],
+        |row| row.get(0),
+    )
+}
+
+/// End of the page of at most `batch_size` matches that starts after `from_seq`
+pub fn page_end(db: &Database, from_seq: i64, to_seq: i64, batch_size: usize) -> Result<i64> {
+    let offset = batch_size.max(1) as i64 - 1;
+    let end: Vec<i64> = db.query_map(
+        "SELECT stored_seq FROM matches WHERE stored_seq > ?1 AND stored_seq <= ?2
+         ORDER BY stored_seq LIMIT 1 OFFSET ?3",
+        &[&from_seq, &to_seq, &offset],
+        |row| row.get(0),
+    )?;
+    Ok(end.first().copied().unwrap_or(to_seq))
+}
+
+/// Number of matches with `from_seq < stored_seq <= to_seq`
+pub fn count_matches(db: &Database, from_seq: i64, to_seq: i64) -> Result<u64> {
+    let count: i64 = db.query_row(
+        "SELECT COUNT(*) FROM matches WHERE stored_seq > ?1 AND stored_seq <= ?2",
+        &[&from_seq, &to_seq],
+        |row| row.get(0),
+    )?;
+    Ok(count as u64)
+}
+
+/// Rows of `table` belonging to matches with `from_seq < stored_seq <= to_seq`, each
+/// with the partition of its match
+pub fn read_rows(
+    db: &Database,
+    table: &str,
+    columns: &[Column],
+    from_seq: i64,
+    to_seq: i64,
+) -> Result<Vec<(Partition, Vec<Value>)>> {
+    if table == TIMELINE_EVENTS_TABLE {
+        return read_timeline_events(db, from_seq, to_seq);
+    }
+
+    let select: Vec<String> = columns
+        .iter()
+        .map(|c| format!("t.\"{}\"", c.name))
+        .collect();
+    let sql = if table == "matches" {
+        format!(
+            "SELECT {}, t.region, t.game_version FROM matches t
+             WHERE t.stored_seq > ?1 AND t.stored_seq <= ?2",
+            select.join(", ")
+        )
+    } else {
+        format!(
+            "SELECT {}, m.region, m.game_version FROM {} t
+             JOIN matches m ON m.match_id = t.match_id
+             WHERE m.stored_seq > ?1 AND m.stored_seq <= ?2",
+            select.join(", "),
+            table
+        )
+    };
+
+    let width = columns.len();
+    db.query_map(&sql, &[&from_seq, &to_seq], |row| {
+        let values = (0..width)
+            .map(|i| row.get::<_, Value>(i))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let partition = Partition::new(row.get(width)?, row.get(width + 1)?);
+        Ok((partition, values))
+    })
+}
+
+fn read_timeline_events(
+    db: &Database,
+    from_seq: i64,
+    to_seq: i64,
+) -> Result<Vec<(Partition, Vec<Value>)>> {
+    let payloads = db.query_map(
+        "SELECT r.match_id, r.region, r.payload, r.raw_size, m.region, m.game_version
+         FROM raw_payloads r JOIN matches m ON m.match_id = r.match_id
+         WHERE r.kind = ?1 AND m.stored_seq > ?2 AND m.stored_seq <= ?3",
+        &[&RawPayloadKind::Timeline.as_str(), &from_seq, &to_seq],
+        |row| {
+            let payload = DbRawPayload {
+                match_id: row.get(0)?,
+                kind: RawPayloadKind::Timeline,
+                region: row.get(1)?,
+                payload: row.get(2)?,
+                raw_size: row.get(3)?,
+                fetched_at: Utc::now(),
+            };
+            Ok((payload, Partition::new(row.get(4)?, row.get(5)?)))
+        },
+    )?;
+
+    let mut rows = Vec::new();
+    for (payload, partition) in payloads {
+        let timeline: serde_json::Value =
+            match decode_payload(&payload).and_then(|json| Ok(serde_json::from_str(&json)?)) {
+                Ok(timeline) => timeline,
+                Err(e) => {
+                    log::warn!(
+                        "Skipping unreadable timeline for {}: {}",
+                        payload.match_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+        let frames = timeline["info"]["frames"].as_array().cloned();
+        for event in frames.iter().flatten().flat_map(|frame| {
+            frame["events"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+        }) {
+            let participant_id = ["participantId", "killerId", "creatorId"]
+                .iter()
+                .find_map(|key| event[*key].as_i64());
+            rows.push((
+                partition.clone(),
+                vec![
+                    Value::Text(payload.match_id.clone()),
+                    event["timestamp"]
+                        .as_i64()
+                        .map_or(Value::Null, Value::Integer),
+                    event["type"]
+                        .as_str()
+                        .map_or(Value::Null, |t| Value::Text(t.to_string())),
+                    participant_id.map_or(Value::Null, Value::Integer),
+                    Value::Text(event.to_string()),
+                ],
+            ));
+        }
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_kinds_follow_declared_types() {
+        assert_eq!(ColumnKind::from_declared("INTEGER"), ColumnKind::Integer);
+        assert_eq!(ColumnKind::from_declared("BIGINT"), ColumnKind::Integer);
+        assert_eq!(ColumnKind::from_declared("BOOLEAN"), ColumnKind::Boolean);
+        assert_eq!(ColumnKind::from_declared("REAL"), ColumnKind::Real);
+        assert_eq!(ColumnKind::from_declared("TEXT"), ColumnKind::Text);
+        assert_eq!(ColumnKind::from_declared("BLOB"), ColumnKind::Blob);
+        assert_eq!(ColumnKind::from_declared(""), ColumnKind::Text);
+    }
+
+    #[test]
+    fn test_partition_directories() {
+        let partition = Partition::new(Some("na1".to_string()), Some("14.1.555.1234".to_string()));
+        assert_eq!(
+            partition.dir("participants"),
+            Path::new("participants/region=na1/patch=14.1")
+        );
+
+        let unknown = Partition::new(None, Some("../x".to_string()));
+        assert_eq!(unknown.region, "unknown");
+        assert!(!unknown.patch.contains('/'));
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("lol-crawler-manifest-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(Manifest::load(&dir).unwrap(), Manifest::default());
+        let manifest = Manifest {
+            watermark: 42,
+            exports: vec![ManifestExport {
+                exported_at: Utc::now(),
+                from_seq: 0,
+                to_seq: 42,
+                matches: 42,
+                files: Vec::new(),
+            }],
+        };
+        manifest.save(&dir).unwrap();
+        assert_eq!(Manifest::load(&dir).unwrap(), manifest);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
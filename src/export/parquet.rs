@@ -0,0 +1,230 @@
+use super::{
+    count_matches, latest_stored_seq, page_end, read_rows, table_columns, Column, ColumnKind,
+    ExportOptions, Manifest, ManifestExport, ManifestFile, Partition, EXPORT_TABLES,
+    TIMELINE_EVENTS_TABLE,
+};
+use crate::database::Database;
+use crate::Result;
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use chrono::Utc;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use rusqlite::types::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Rows buffered per row group; bounds the memory held by each open file
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+/// Export matches stored since the last watermark (or everything with `full`) to
+/// Parquet files partitioned by region and patch, then record the run in the manifest.
+/// Tables are read a page of matches at a time, so memory use does not grow with the
+/// database.
+pub fn export_parquet(db: &Database, options: &ExportOptions) -> Result<ManifestExport> {
+    std::fs::create_dir_all(&options.output_dir)?;
+    let mut manifest = Manifest::load(&options.output_dir)?;
+
+    let from_seq = if options.full { 0 } else { manifest.watermark };
+    let to_seq = latest_stored_seq(db)?;
+    let mut export = ManifestExport {
+        exported_at: Utc::now(),
+        from_seq,
+        to_seq,
+        matches: 0,
+        files: Vec::new(),
+    };
+    if to_seq <= from_seq {
+        log::info!("Nothing to export since watermark {}", from_seq);
+        return Ok(export);
+    }
+    export.matches = count_matches(db, from_seq, to_seq)?;
+
+    let mut tables = Vec::new();
+    for table in EXPORT_TABLES.iter().chain([&TIMELINE_EVENTS_TABLE]) {
+        let columns = table_columns(db, table)?;
+        let schema = arrow_schema(&columns);
+        tables.push((*table, columns, schema));
+    }
+
+    let mut writers: BTreeMap<(&str, Partition), PartitionWriter> = BTreeMap::new();
+    let mut page_start = from_seq;
+    while page_start < to_seq {
+        let page_stop = page_end(db, page_start, to_seq, options.batch_size)?;
+        for (table, columns, schema) in &tables {
+            let mut partitions: BTreeMap<Partition, Vec<Vec<Value>>> = BTreeMap::new();
+            for (partition, row) in read_rows(db, table, columns, page_start, page_stop)? {
+                partitions.entry(partition).or_default().push(row);
+            }
+
+            for (partition, rows) in partitions {
+                let key = (*table, partition);
+                if !writers.contains_key(&key) {
+                    let writer = PartitionWriter::create(
+                        options,
+                        table,
+                        &key.1,
+                        schema.clone(),
+                        from_seq,
+                        to_seq,
+                    )?;
+                    writers.insert(key.clone(), writer);
+                }
+                let writer = writers.get_mut(&key).expect("writer was just inserted");
+                writer.write(&record_batch(schema, columns, &rows)?)?;
+            }
+        }
+        log::debug!("Exported matches up to stored_seq {}", page_stop);
+        page_start = page_stop;
+    }
+
+    for ((table, partition), writer) in writers {
+        export.files.push(writer.finish(table, partition)?);
+    }
+
+    manifest.watermark = to_seq;
+    manifest.exports.push(export.clone());
+    manifest.save(&options.output_dir)?;
+    Ok(export)
+}
+
+/// Parquet file for one table partition, written under a temporary name until finished
+struct PartitionWriter {
+    writer: ArrowWriter<File>,
+    tmp_path: PathBuf,
+    path: PathBuf,
+    relative_path: String,
+    rows: u64,
+}
+
+impl PartitionWriter {
+    fn create(
+        options: &ExportOptions,
+        table: &str,
+        partition: &Partition,
+        schema: SchemaRef,
+        from_seq: i64,
+        to_seq: i64,
+    ) -> Result<Self> {
+        let dir = partition.dir(table);
+        std::fs::create_dir_all(options.output_dir.join(&dir))?;
+        let relative = dir.join(format!("{}-{}-{}.parquet", table, from_seq + 1, to_seq));
+        let path = options.output_dir.join(&relative);
+        let tmp_path = path.with_extension("parquet.tmp");
+
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(File::create(&tmp_path)?, schema, Some(properties))?;
+
+        Ok(Self {
+            writer,
+            tmp_path,
+            path,
+            relative_path: relative.to_string_lossy().replace('\\', "/"),
+            rows: 0,
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<()> {
+        self.writer.write(batch)?;
+        self.rows += batch.num_rows() as u64;
+        Ok(())
+    }
+
+    fn finish(self, table: &str, partition: Partition) -> Result<ManifestFile> {
+        self.writer.close()?;
+        std::fs::rename(&self.tmp_path, &self.path)?;
+        Ok(ManifestFile {
+            table: table.to_string(),
+            region: partition.region,
+            patch: partition.patch,
+            path: self.relative_path,
+            rows: self.rows,
+        })
+    }
+}
+
+fn arrow_schema(columns: &[Column]) -> SchemaRef {
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|column| {
+            let data_type = match column.kind {
+                ColumnKind::Integer => DataType::Int64,
+                ColumnKind::Real => DataType::Float64,
+                ColumnKind::Boolean => DataType::Boolean,
+                ColumnKind::Text => DataType::Utf8,
+                ColumnKind::Blob => DataType::Binary,
+            };
+            Field::new(&column.name, data_type, true)
+        })
+        .collect();
+    Arc::new(Schema::new(fields))
+}
+
+fn record_batch(
+    schema: &SchemaRef,
+    columns: &[Column],
+    rows: &[Vec<Value>],
+) -> Result<RecordBatch> {
+    let arrays: Vec<ArrayRef> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let values = rows.iter().map(|row| &row[i]);
+            let array: ArrayRef = match column.kind {
+                ColumnKind::Integer => Arc::new(values.map(as_i64).collect::<Int64Array>()),
+                ColumnKind::Real => Arc::new(values.map(as_f64).collect::<Float64Array>()),
+                ColumnKind::Boolean => Arc::new(
+                    values
+                        .map(|v| as_i64(v).map(|i| i != 0))
+                        .collect::<BooleanArray>(),
+                ),
+                ColumnKind::Text => Arc::new(values.map(as_text).collect::<StringArray>()),
+                ColumnKind::Blob => Arc::new(
+                    values
+                        .map(|v| match v {
+                            Value::Blob(b) => Some(b.as_slice()),
+                            _ => None,
+                        })
+                        .collect::<BinaryArray>(),
+                ),
+            };
+            array
+        })
+        .collect();
+    Ok(RecordBatch::try_new(schema.clone(), arrays)?)
+}
+
+fn as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(i) => Some(*i),
+        Value::Real(f) => Some(*f as i64),
+        Value::Text(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Real(f) => Some(*f),
+        Value::Text(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Text(s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Real(f) => Some(f.to_string()),
+        _ => None,
+    }
+}
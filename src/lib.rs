@@ -1,12 +1,17 @@
+pub mod admin;
 pub mod api;
 pub mod config;
 pub mod crawler;
 pub mod database;
+pub mod events;
+pub mod export;
+pub mod logging;
+pub mod metrics;
 pub mod models;
 pub mod rate_limiter;
 
 pub use config::Config;
 pub use crawler::CrawlerEngine;
-pub use database::Database;
+pub use database::{Database, Storage};
 
 pub type Result<T> = anyhow::Result<T>;
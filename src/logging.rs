@@ -0,0 +1,167 @@
+use crate::config::LoggingConfig;
+use std::sync::OnceLock;
+use tracing::level_filters::LevelFilter;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Swaps the filter of the global subscriber installed by `init`
+pub type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+static FILTER: OnceLock<FilterHandle> = OnceLock::new();
+
+/// Install the global subscriber, writing to stderr so command output on stdout stays
+/// clean. Records from the `log` crate are forwarded and carry the fields of the span
+/// they were emitted in.
+pub fn init(config: &LoggingConfig) -> crate::Result<()> {
+    let (subscriber, handle) = subscriber(config, std::io::stderr)?;
+    subscriber
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Failed to initialise logging: {}", e))?;
+    FILTER.get_or_init(|| handle);
+    Ok(())
+}
+
+/// Change the level of the global subscriber, e.g. when the configuration is reloaded.
+/// Does nothing before `init` or while `RUST_LOG` is set, which takes precedence.
+pub fn set_level(level: &str) -> crate::Result<()> {
+    let Some(handle) = FILTER.get() else {
+        return Ok(());
+    };
+    if std::env::var(EnvFilter::DEFAULT_ENV).is_ok_and(|directives| !directives.is_empty()) {
+        return Ok(());
+    }
+    handle.reload(EnvFilter::try_new(level)?)?;
+    // The `log` bridge skips records above the level it was installed with
+    log::set_max_level(match LevelFilter::current() {
+        LevelFilter::OFF => log::LevelFilter::Off,
+        LevelFilter::ERROR => log::LevelFilter::Error,
+        LevelFilter::WARN => log::LevelFilter::Warn,
+        LevelFilter::INFO => log::LevelFilter::Info,
+        LevelFilter::DEBUG => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    });
+    Ok(())
+}
+
+/// Subscriber for `config.format`: one JSON object per line with the event's fields at
+/// the top level and its spans in `spans`, or `pretty`/`compact` text. `RUST_LOG`
+/// takes precedence over `config.level`. The handle changes the level afterwards.
+pub fn subscriber<W>(
+    config: &LoggingConfig,
+    writer: W,
+) -> crate::Result<(Box<dyn Subscriber + Send + Sync>, FilterHandle)>
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let filter = match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(directives) if !directives.is_empty() => EnvFilter::try_new(directives)?,
+        _ => EnvFilter::try_new(&config.level)?,
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+    let registry = tracing_subscriber::registry().with(filter);
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+
+    let subscriber: Box<dyn Subscriber + Send + Sync> = match config.format.as_str() {
+        "json" => Box::new(
+            registry.with(
+                layer
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(false)
+                    .with_span_list(true),
+            ),
+        ),
+        "pretty" => Box::new(registry.with(layer.pretty())),
+        "compact" => Box::new(registry.with(layer.compact())),
+        other => anyhow::bail!(
+            "Unknown log format {:?}, expected json, pretty or compact",
+            other
+        ),
+    };
+    Ok((subscriber, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_lines_carry_span_fields() {
+        let buffer = Buffer::default();
+        let (subscriber, _) = subscriber(&LoggingConfig::default(), buffer.clone()).unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(
+                "summoner_task",
+                puuid = "puuid-1",
+                region = "na1",
+                attempt = 2
+            );
+            let _entered = span.enter();
+            tracing::info!(discovered = 3, "Processed summoner");
+            tracing::debug!("filtered out at info");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let event: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(event["level"], "INFO");
+        assert_eq!(event["message"], "Processed summoner");
+        assert_eq!(event["discovered"], 3);
+        assert_eq!(event["spans"][0]["name"], "summoner_task");
+        assert_eq!(event["spans"][0]["puuid"], "puuid-1");
+        assert_eq!(event["spans"][0]["attempt"], 2);
+    }
+
+    #[test]
+    fn test_filter_handle_changes_the_level() {
+        let buffer = Buffer::default();
+        let (subscriber, handle) = subscriber(&LoggingConfig::default(), buffer.clone()).unwrap();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!("filtered out at info");
+            handle.reload(EnvFilter::new("debug")).unwrap();
+            tracing::debug!("kept at debug");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains("kept at debug"));
+    }
+
+    #[test]
+    fn test_unknown_format_is_rejected() {
+        let config = LoggingConfig {
+            level: "info".to_string(),
+            format: "logfmt".to_string(),
+        };
+        assert!(subscriber(&config, std::io::sink).is_err());
+    }
+}
@@ -1,72 +1,929 @@
-use lol_crawler::{Config, CrawlerEngine, Database};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use lol_crawler::admin;
+use lol_crawler::api::{ApiKeyPool, RiotApiClient};
+use lol_crawler::config::LoggingConfig;
+use lol_crawler::crawler::{parse_seed_list, reprocess_archive, CrawlerWorker, SeedPlayer};
+use lol_crawler::database::{self, redact_url};
+use lol_crawler::events::{Event, EventBus, EventKind};
+use lol_crawler::export::{
+    dump_rows, export_parquet, parse_date_bound, DumpFilter, DumpFormat, DumpOptions, DumpTable,
+    ExportOptions,
+};
+use lol_crawler::models::database::{FailedTaskFilter, FailedTaskType, SummonerPriority};
+use lol_crawler::{logging, Config, CrawlerEngine, Database, Storage};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+#[command(
+    name = "lol-crawler",
+    version,
+    about = "League of Legends match crawler"
+)]
+struct Cli {
+    /// TOML, YAML or JSON config file; defaults to lol-crawler.toml (or .yaml, .yml,
+    /// .json) in the working directory when present
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Runs `crawl` with the configured settings when no command is given
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the crawler until it is stopped or a budget runs out
+    Crawl(CrawlArgs),
+    /// Queue players for the next crawl
+    Seed {
+        #[command(subcommand)]
+        source: SeedCommand,
+    },
+    /// Fetch one player or match from the API and print it as JSON
+    Lookup {
+        #[command(subcommand)]
+        target: LookupCommand,
+    },
+    /// Show stored row counts, the persisted queue and dead-letter totals
+    Stats,
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Inspect and requeue permanently failed summoners and matches
+    Queue {
+        #[command(subcommand)]
+        action: QueueCommand,
+    },
+    /// Apply pending database schema migrations
+    Migrate {
+        #[command(subcommand)]
+        action: Option<MigrateCommand>,
+        /// List the migrations that would be applied without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rebuild the normalised match tables from the raw payload archive
+    Reprocess {
+        /// Only rebuild this match
+        #[arg(long)]
+        match_id: Option<String>,
+    },
+    /// Write stored matches to Parquet files partitioned by region and patch
+    Export {
+        /// Directory for the Parquet files and manifest.json
+        #[arg(long)]
+        output: PathBuf,
+        /// Export every match instead of only those stored since the last export
+        #[arg(long)]
+        full: bool,
+        /// Matches read from the database at a time
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+    },
+    /// Check stored matches for orphaned rows, missing players, duplicates and impossible values
+    Verify {
+        /// Delete broken matches (after trying --refetch, if given) and orphaned rows
+        #[arg(long)]
+        delete: bool,
+        /// Download broken matches again from the API
+        #[arg(long)]
+        refetch: bool,
+    },
+    /// Write matches or participants as NDJSON or CSV to stdout or a file
+    Dump {
+        /// matches or participants
+        table: DumpTable,
+        /// ndjson or csv
+        #[arg(long, default_value = "ndjson")]
+        format: DumpFormat,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Add each participant's match and team columns
+        #[arg(long)]
+        join: bool,
+        #[arg(long)]
+        region: Option<String>,
+        #[arg(long = "queue")]
+        queue_id: Option<i32>,
+        /// Patch such as 14.1
+        #[arg(long)]
+        patch: Option<String>,
+        /// Games created on or after this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        since: Option<DateTime<Utc>>,
+        /// Games created before this date (YYYY-MM-DD or RFC 3339)
+        #[arg(long, value_parser = parse_date_bound)]
+        until: Option<DateTime<Utc>>,
+        /// Champion id or name
+        #[arg(long)]
+        champion: Option<String>,
+        #[arg(long)]
+        puuid: Option<String>,
+    },
+}
+
+#[derive(clap::Args, Clone, Default)]
+struct CrawlArgs {
+    /// Regions to crawl instead of REGIONS, e.g. euw1,kr
+    #[arg(long, value_delimiter = ',')]
+    regions: Vec<String>,
+    /// Queue id of the matches to store instead of CRAWL_QUEUE_ID (420 solo, 440 flex)
+    #[arg(long = "queue")]
+    queue_id: Option<i32>,
+    /// Stop after this many API requests
+    #[arg(long)]
+    max_requests: Option<u64>,
+    /// Stop after storing this many matches
+    #[arg(long)]
+    max_matches: Option<u64>,
+    /// Stop after this many seconds
+    #[arg(long)]
+    max_duration: Option<u64>,
+    /// Stop when no match has been stored for this many minutes
+    #[arg(long)]
+    idle_minutes: Option<u64>,
+    /// Queue the players in this file first, in the format of `seed file`
+    #[arg(long)]
+    seed_file: Option<PathBuf>,
+    /// Don't seed from the Master+ ladder on startup
+    #[arg(long)]
+    no_ladder_seed: bool,
+}
+
+impl CrawlArgs {
+    fn apply(&self, config: &mut Config) -> lol_crawler::Result<()> {
+        if !self.regions.is_empty() {
+            config.regions = self.regions.clone();
+        }
+        // Flags apply to every region, replacing the per-region settings too
+        if let Some(queue_id) = self.queue_id {
+            config.crawler.queue_id = queue_id;
+            for region in config.region_overrides.values_mut() {
+                region.queue_id = None;
+            }
+        }
+        if self.no_ladder_seed {
+            config.crawler.seed_from_ladder = false;
+            for region in config.region_overrides.values_mut() {
+                region.seed_from_ladder = None;
+            }
+        }
+        let budget = &mut config.budget;
+        budget.max_requests = self.max_requests.or(budget.max_requests);
+        budget.max_matches = self.max_matches.or(budget.max_matches);
+        budget.max_duration_seconds = self.max_duration.or(budget.max_duration_seconds);
+        budget.idle_minutes = self.idle_minutes.or(budget.idle_minutes);
+        config.validate()
+    }
+}
+
+#[derive(Subcommand)]
+enum SeedCommand {
+    /// Players from the Master, Grandmaster and Challenger ladders
+    Ladder {
+        /// Regions to seed instead of REGIONS
+        #[arg(long, value_delimiter = ',')]
+        regions: Vec<String>,
+    },
+    /// Players by Riot ID (Name#TAG) or PUUID
+    RiotId {
+        #[arg(required = true)]
+        players: Vec<String>,
+        #[arg(long)]
+        region: String,
+    },
+    /// Players listed one per line as `<Riot ID or PUUID>[,<region>]`; `#` starts a comment
+    File {
+        path: PathBuf,
+        /// Region for lines that don't name one
+        #[arg(long)]
+        region: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum LookupCommand {
+    /// A player's account, summoner profile and ranked entries
+    Player {
+        /// Riot ID (Name#TAG) or PUUID
+        player: String,
+        #[arg(long)]
+        region: String,
+    },
+    /// A match as returned by the match API
+    Match {
+        match_id: String,
+        /// Defaults to the platform prefix of the match id
+        #[arg(long)]
+        region: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate the configuration and print the effective settings with secrets masked
+    Check,
+}
+
+#[derive(Subcommand)]
+enum MigrateCommand {
+    /// Show applied and pending schema versions
+    Status,
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// List dead-letter entries, newest failure first
+    Inspect {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Only show entries with this status (failed or requeued)
+        #[arg(long)]
+        status: Option<String>,
+        #[arg(long, default_value_t = 50)]
+        limit: i32,
+    },
+    /// Mark dead-letter entries to be retried on the next crawler start
+    Requeue {
+        #[command(flatten)]
+        filter: FilterArgs,
+        /// Requeue every failed entry when no filter is given
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(clap::Args)]
+struct FilterArgs {
+    /// Dead-letter entry id
+    #[arg(long)]
+    id: Option<i64>,
+    /// Task type: summoner or match
+    #[arg(long = "type")]
+    task_type: Option<FailedTaskType>,
+    /// ApiError variant, e.g. NotFound, Authentication, Json
+    #[arg(long)]
+    kind: Option<String>,
+}
+
+impl FilterArgs {
+    fn is_empty(&self) -> bool {
+        self.id.is_none() && self.task_type.is_none() && self.kind.is_none()
+    }
+
+    fn into_filter(self) -> FailedTaskFilter {
+        FailedTaskFilter {
+            id: self.id,
+            task_type: self.task_type,
+            error_kind: self.kind,
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    // Initialize logging
-    env_logger::init();
+    let cli = Cli::parse();
+    let config_file = cli.config.as_deref();
+
+    // Initialize logging. A configuration that fails to load is reported by the command.
+    let logging_config = Config::load_unchecked(config_file)
+        .map(|config| config.logging)
+        .unwrap_or_else(|_| LoggingConfig::from_env());
+    if let Err(e) = logging::init(&logging_config) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
 
-    // Load configuration
-    let config = match Config::from_env() {
-        Ok(config) => config,
-        Err(e) => {
-            eprintln!("Failed to load configuration: {}", e);
-            process::exit(1);
+    let command = cli.command.unwrap_or(Command::Crawl(CrawlArgs::default()));
+    let result = match command {
+        Command::Crawl(args) => run_crawl_command(config_file, args).await,
+        Command::Seed { source } => run_seed_command(config_file, source).await,
+        Command::Lookup { target } => run_lookup_command(config_file, target).await,
+        Command::Stats => run_stats_command(config_file).await,
+        Command::Config {
+            action: ConfigCommand::Check,
+        } => run_config_check_command(config_file),
+        Command::Queue { action } => run_queue_command(config_file, action).await,
+        Command::Migrate { action, dry_run } => run_migrate_command(config_file, action, dry_run),
+        Command::Reprocess { match_id } => run_reprocess_command(config_file, match_id).await,
+        Command::Export {
+            output,
+            full,
+            batch_size,
+        } => run_export_command(config_file, output, full, batch_size),
+        Command::Verify { delete, refetch } => {
+            run_verify_command(config_file, delete, refetch).await
+        }
+        Command::Dump {
+            table,
+            format,
+            output,
+            join,
+            region,
+            queue_id,
+            patch,
+            since,
+            until,
+            champion,
+            puuid,
+        } => {
+            let mut options = DumpOptions::new(table, format);
+            options.join = join;
+            options.filter = DumpFilter {
+                region,
+                queue_id,
+                patch,
+                since,
+                until,
+                champion,
+                puuid,
+            };
+            run_dump_command(config_file, options, output)
         }
     };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+async fn run_crawl_command(config_file: Option<&Path>, args: CrawlArgs) -> lol_crawler::Result<()> {
+    let mut config = Config::load(config_file)
+        .map_err(|e| anyhow::anyhow!("Failed to load configuration: {}", e))?;
+    args.apply(&mut config)?;
 
     log::info!("Starting League of Legends crawler with config:");
     log::info!("- Regions: {:?}", config.regions);
-    log::info!("- Database: {}", config.database_url);
+    log::info!("- Queue: {}", config.crawler.queue_id);
+    log::info!("- Database: {}", redact_url(&config.database_url));
     log::info!(
         "- Rate limits: {} per second, {} per 2 minutes",
         config.rate_limits.application_limit_per_second,
         config.rate_limits.application_limit_per_two_minutes
     );
 
-    // Initialize database
-    let database = match Database::new(&config.database_url) {
-        Ok(db) => db,
-        Err(e) => {
-            eprintln!("Failed to initialize database: {}", e);
-            process::exit(1);
+    let database = database::connect(&config.database_url)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to initialize database: {}", e))?;
+    log::info!("Database initialized successfully");
+
+    let drain_timeout = Duration::from_secs(config.crawler.drain_timeout_seconds);
+    let admin_config = config.admin.clone();
+    // Reloads go through the same layers, flags included
+    let reload_file = config_file.map(Path::to_path_buf);
+    let reload_args = args.clone();
+    let config_source = Box::new(move || {
+        let mut config = Config::load(reload_file.as_deref())?;
+        reload_args.apply(&mut config)?;
+        Ok(config)
+    });
+    let crawler = Arc::new(
+        CrawlerEngine::new(config, database)
+            .map_err(|e| anyhow::anyhow!("Failed to create crawler engine: {}", e))?
+            .with_config_source(config_source),
+    );
+
+    if let Some(path) = &args.seed_file {
+        let players = read_seed_file(path, None)?;
+        let queued = enqueue_seed_players(&crawler, players).await?;
+        log::info!("Queued {} players from {}", queued, path.display());
+    }
+
+    if let Some(address) = &admin_config.bind_address {
+        let listener = admin::bind(address).await?;
+        let router = admin::router(crawler.clone(), &admin_config, drain_timeout);
+        tokio::spawn(async move {
+            if let Err(e) = admin::serve(listener, router).await {
+                log::error!("Admin API stopped: {}", e);
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    spawn_reload_on_sighup(crawler.clone())?;
+
+    // Drain on Ctrl-C or SIGTERM. The crawler future keeps being polled during the drain
+    // so in-flight work can finish.
+    let run = crawler.start();
+    tokio::pin!(run);
+
+    let result = tokio::select! {
+        result = &mut run => result,
+        _ = shutdown_signal() => {
+            log::info!("Received shutdown signal");
+            let (drained, result) = tokio::join!(crawler.drain(drain_timeout), &mut run);
+            if let Err(e) = drained {
+                log::error!("Failed to persist queue during drain: {}", e);
+            }
+            result
         }
     };
 
-    log::info!("Database initialized successfully");
+    println!("{}", crawler.run_summary().await);
+
+    match result {
+        Ok(()) => {
+            log::info!("Crawler finished successfully");
+            Ok(())
+        }
+        Err(e) => Err(anyhow::anyhow!("Crawler failed: {}", e)),
+    }
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("Failed to listen for ctrl+c");
+}
+
+/// Reload the configuration whenever the process receives SIGHUP
+#[cfg(unix)]
+fn spawn_reload_on_sighup(crawler: Arc<CrawlerEngine>) -> lol_crawler::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|e| anyhow::anyhow!("Failed to listen for SIGHUP: {}", e))?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            log::info!("Received SIGHUP, reloading configuration");
+            if let Err(e) = crawler.reload().await {
+                log::error!("Failed to reload configuration: {:#}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// An API client for commands that call the API outside a crawl
+fn api_client(config: &Config, storage: Arc<dyn Storage>) -> lol_crawler::Result<RiotApiClient> {
+    let api_keys = Arc::new(ApiKeyPool::new(config)?);
+    Ok(RiotApiClient::new(config.clone(), api_keys, storage)?)
+}
+
+fn read_seed_file(
+    path: &Path,
+    default_region: Option<&str>,
+) -> lol_crawler::Result<Vec<(SeedPlayer, String)>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    parse_seed_list(&contents, default_region)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+/// Resolve each player to a PUUID and queue it, returning how many were queued.
+/// Players that fail to resolve are reported and skipped.
+async fn enqueue_seed_players(
+    engine: &CrawlerEngine,
+    players: Vec<(SeedPlayer, String)>,
+) -> lol_crawler::Result<usize> {
+    let mut queued = 0;
+    for (player, region) in players {
+        let puuid = match player.resolve(engine.api_client(), &region).await {
+            Ok(puuid) => puuid,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+        engine
+            .enqueue_player(&puuid, &region, SummonerPriority::High)
+            .await?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+async fn run_seed_command(
+    config_file: Option<&Path>,
+    source: SeedCommand,
+) -> lol_crawler::Result<()> {
+    let mut config = Config::load(config_file)?;
+    if let SeedCommand::Ladder { regions } = &source {
+        if !regions.is_empty() {
+            config.regions = regions.clone();
+            config.validate()?;
+        }
+    }
+    let database = database::connect(&config.database_url).await?;
+    let engine = CrawlerEngine::new(config, database)?;
+
+    match source {
+        SeedCommand::Ladder { .. } => engine.seed_with_master_league().await?,
+        SeedCommand::RiotId { players, region } => {
+            let players = players
+                .iter()
+                .map(|player| Ok((player.parse().map_err(anyhow::Error::msg)?, region.clone())))
+                .collect::<lol_crawler::Result<Vec<_>>>()?;
+            enqueue_seed_players(&engine, players).await?;
+        }
+        SeedCommand::File { path, region } => {
+            let players = read_seed_file(&path, region.as_deref())?;
+            enqueue_seed_players(&engine, players).await?;
+        }
+    }
 
-    // Create and start crawler
-    let crawler = match CrawlerEngine::new(config, database) {
-        Ok(crawler) => crawler,
-        Err(e) => {
-            eprintln!("Failed to create crawler engine: {}", e);
-            process::exit(1);
+    let queued = engine.persist_queue().await?;
+    println!(
+        "Queued {} players; they will be crawled on the next crawler start",
+        queued
+    );
+    Ok(())
+}
+
+async fn run_lookup_command(
+    config_file: Option<&Path>,
+    target: LookupCommand,
+) -> lol_crawler::Result<()> {
+    let config = Config::load(config_file)?;
+    let database = database::connect(&config.database_url).await?;
+    let client = api_client(&config, database)?;
+
+    let output = match target {
+        LookupCommand::Player { player, region } => {
+            let player: SeedPlayer = player.parse().map_err(anyhow::Error::msg)?;
+            let puuid = player.resolve(&client, &region).await?;
+            let summoner = client.get_summoner_by_puuid(&region, &puuid).await?;
+            let ranks = client.get_league_entries_by_puuid(&region, &puuid).await?;
+            serde_json::json!({
+                "puuid": puuid,
+                "region": region,
+                "summoner": summoner,
+                "ranks": ranks,
+            })
+        }
+        LookupCommand::Match { match_id, region } => {
+            let region = match region {
+                Some(region) => region,
+                None => match_id
+                    .split_once('_')
+                    .map(|(platform, _)| platform.to_lowercase())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Cannot tell the region of {}; pass --region", match_id)
+                    })?,
+            };
+            serde_json::to_value(client.get_match_by_id(&region, &match_id).await?)?
         }
     };
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    Ok(())
+}
+
+async fn run_stats_command(config_file: Option<&Path>) -> lol_crawler::Result<()> {
+    let database = database::connect(&maintenance_database_url(config_file)?).await?;
+
+    println!("Matches:       {}", database.get_matches_count().await?);
+    println!("Summoners:     {}", database.get_summoners_count().await?);
+    println!(
+        "Participants:  {}",
+        database.get_participants_count().await?
+    );
+    println!(
+        "Pending tasks: {}",
+        database.get_pending_tasks_count().await?
+    );
+    if let Some(state) = database.get_crawler_state().await? {
+        println!(
+            "Last crawl:    {} ({} summoners, {} matches processed)",
+            state.last_update.format("%Y-%m-%d %H:%M:%S"),
+            state.total_summoners_processed,
+            state.total_matches_processed
+        );
+    }
+
+    let failed = database.get_failed_task_summary().await?;
+    if !failed.is_empty() {
+        println!();
+        println!("Dead-letter queue:");
+        for (task_type, error_kind, status, count) in failed {
+            println!(
+                "  {:<9} {:<20} {:<9} {}",
+                task_type, error_kind, status, count
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_config_check_command(config_file: Option<&Path>) -> lol_crawler::Result<()> {
+    let config = Config::load(config_file)?;
+    println!("{}", serde_json::to_string_pretty(&config.redacted())?);
+    Ok(())
+}
+
+/// Database URL for maintenance commands, which only need the database and not an API key
+fn maintenance_database_url(config_file: Option<&Path>) -> lol_crawler::Result<String> {
+    Ok(Config::load_unchecked(config_file)?.database_url)
+}
+
+fn run_migrate_command(
+    config_file: Option<&Path>,
+    action: Option<MigrateCommand>,
+    dry_run: bool,
+) -> lol_crawler::Result<()> {
+    let database_url = maintenance_database_url(config_file)?;
+    if database::is_postgres_url(&database_url) {
+        anyhow::bail!(
+            "migrate manages SQLite databases; Postgres migrations run when the crawler connects"
+        );
+    }
+    let database = Database::open(&database_url)?;
 
-    // Handle graceful shutdown
-    let crawler_ref = &crawler;
-    let shutdown_task = async {
-        tokio::signal::ctrl_c()
-            .await
-            .expect("Failed to listen for ctrl+c");
-        log::info!("Received shutdown signal");
-        crawler_ref.stop().await;
+    match action {
+        Some(MigrateCommand::Status) => {
+            for status in database.migration_status()? {
+                println!(
+                    "{:>4}  {:<36} {}",
+                    status.version,
+                    status.name,
+                    status.applied_at.as_deref().unwrap_or("pending")
+                );
+            }
+        }
+        None if dry_run => {
+            let pending: Vec<_> = database
+                .migration_status()?
+                .into_iter()
+                .filter(|s| s.applied_at.is_none())
+                .collect();
+            if pending.is_empty() {
+                println!("Schema is up to date");
+            }
+            for status in pending {
+                println!("Would apply {:>4}  {}", status.version, status.name);
+            }
+        }
+        None => {
+            let applied = database.migrate()?;
+            if applied.is_empty() {
+                println!("Schema is up to date");
+            }
+            for status in applied {
+                println!("Applied {:>4}  {}", status.version, status.name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_queue_command(
+    config_file: Option<&Path>,
+    action: QueueCommand,
+) -> lol_crawler::Result<()> {
+    // Dead-letter inspection only needs the database, not an API key
+    let database = database::connect(&maintenance_database_url(config_file)?).await?;
+
+    match action {
+        QueueCommand::Inspect {
+            filter,
+            status,
+            limit,
+        } => {
+            for (task_type, error_kind, status, count) in database.get_failed_task_summary().await?
+            {
+                println!(
+                    "{:<9} {:<20} {:<9} {}",
+                    task_type, error_kind, status, count
+                );
+            }
+            println!();
+
+            let tasks = database
+                .get_failed_tasks(&filter.into_filter(), status.as_deref(), limit)
+                .await?;
+            for task in tasks {
+                println!(
+                    "#{} {} {} [{}] {} {} attempts={} last={}\n    {}",
+                    task.id.unwrap_or_default(),
+                    task.task_type,
+                    task.target_id,
+                    task.region,
+                    task.error_kind,
+                    task.http_status
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    task.attempts,
+                    task.last_failed_at.format("%Y-%m-%d %H:%M:%S"),
+                    task.last_error
+                );
+            }
+        }
+        QueueCommand::Requeue { filter, all } => {
+            if filter.is_empty() && !all {
+                anyhow::bail!("Refusing to requeue everything without --all");
+            }
+            let count = database.requeue_failed_tasks(&filter.into_filter()).await?;
+            println!(
+                "Requeued {} tasks; they will be retried on the next crawler start",
+                count
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_reprocess_command(
+    config_file: Option<&Path>,
+    match_id: Option<String>,
+) -> lol_crawler::Result<()> {
+    // Rebuilding from the archive never calls the API
+    let database = database::connect(&maintenance_database_url(config_file)?).await?;
+    let summary = reprocess_archive(database.as_ref(), match_id.as_deref()).await?;
+    println!(
+        "Reprocessed {} matches from the raw archive ({} failed)",
+        summary.reprocessed, summary.failed
+    );
+    if summary.failed > 0 {
+        anyhow::bail!(
+            "{} archived matches could not be reprocessed",
+            summary.failed
+        );
+    }
+    Ok(())
+}
+
+fn run_export_command(
+    config_file: Option<&Path>,
+    output: PathBuf,
+    full: bool,
+    batch_size: usize,
+) -> lol_crawler::Result<()> {
+    let database_url = maintenance_database_url(config_file)?;
+    if database::is_postgres_url(&database_url) {
+        anyhow::bail!("export reads SQLite databases; Postgres is not supported yet");
+    }
+    let database = Database::new(&database_url)?;
+
+    let options = ExportOptions {
+        output_dir: output,
+        full,
+        batch_size,
+    };
+    let export = export_parquet(&database, &options)?;
+    if export.files.is_empty() {
+        println!("Nothing to export since the last watermark");
+        return Ok(());
+    }
+    for file in &export.files {
+        println!("{:>8}  {}", file.rows, file.path);
+    }
+    println!(
+        "Exported {} matches (stored_seq {}..={}) to {}",
+        export.matches,
+        export.from_seq + 1,
+        export.to_seq,
+        options.output_dir.display()
+    );
+    Ok(())
+}
+
+fn run_dump_command(
+    config_file: Option<&Path>,
+    options: DumpOptions,
+    output: Option<PathBuf>,
+) -> lol_crawler::Result<()> {
+    let database_url = maintenance_database_url(config_file)?;
+    if database::is_postgres_url(&database_url) {
+        anyhow::bail!("dump reads SQLite databases; Postgres is not supported yet");
+    }
+    let database = Database::new(&database_url)?;
+
+    let rows = match &output {
+        Some(path) => {
+            let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+            dump_rows(&database, &options, &mut file)?
+        }
+        None => dump_rows(&database, &options, &mut std::io::stdout().lock())?,
     };
+    // Keep stdout clean for the rows themselves
+    eprintln!("Dumped {} rows", rows);
+    Ok(())
+}
+
+async fn run_verify_command(
+    config_file: Option<&Path>,
+    delete: bool,
+    refetch: bool,
+) -> lol_crawler::Result<()> {
+    let database_url = maintenance_database_url(config_file)?;
+    if database::is_postgres_url(&database_url) {
+        anyhow::bail!("verify checks SQLite databases; Postgres is not supported yet");
+    }
+    let database = Database::new(&database_url)?;
+
+    let report = database.verify_integrity()?;
+    for issue in &report.issues {
+        println!(
+            "{:<16} {:<20} {}",
+            issue.kind.as_str(),
+            issue.match_id,
+            issue.detail
+        );
+    }
+    let broken = report.broken_matches();
+    println!(
+        "Checked {} matches: {} issues, {} broken matches",
+        report.matches_checked,
+        report.issues.len(),
+        broken.len()
+    );
+    if report.is_clean() {
+        return Ok(());
+    }
+
+    let events = EventBus::new(&Config::load_unchecked(config_file)?.events)?;
+    events.emit(Event::new(
+        EventKind::IntegrityIssues,
+        format!(
+            "Integrity check of {} matches found {} issues in {} matches",
+            report.matches_checked,
+            report.issues.len(),
+            broken.len()
+        ),
+    ));
+    events.flush().await;
+
+    if !delete && !refetch {
+        anyhow::bail!("Integrity check failed; rerun with --refetch or --delete to repair");
+    }
+
+    let mut unrepaired = broken;
+    if refetch {
+        // Re-downloading needs the API key, unlike the other maintenance commands
+        let config = Config::load(config_file)?;
+        let storage: Arc<dyn Storage> = Arc::new(database.clone());
+        let mut worker = CrawlerWorker::new(api_client(&config, storage.clone())?, storage);
+        worker.set_queue_ids(&config);
+        if config.archive.enabled {
+            worker = worker.with_archive(config.archive.clone());
+        }
 
-    // Run crawler and shutdown handler
-    tokio::select! {
-        result = crawler.start() => {
-            match result {
-                Ok(_) => log::info!("Crawler finished successfully"),
+        let mut failed = Vec::new();
+        let mut refetched = Vec::new();
+        for (match_id, region) in unrepaired {
+            match worker.refetch_match(&match_id, &region).await {
+                Ok(()) => refetched.push((match_id, region)),
                 Err(e) => {
-                    log::error!("Crawler failed: {}", e);
-                    process::exit(1);
+                    eprintln!("Could not refetch {}: {}", match_id, e);
+                    failed.push((match_id, region));
                 }
             }
         }
-        _ = shutdown_task => {
-            log::info!("Shutdown completed");
+
+        // The payload itself may be what is broken, so check the refetched matches again
+        let still_broken = database.verify_integrity()?.broken_matches();
+        for refetched in refetched {
+            if still_broken.contains(&refetched) {
+                eprintln!("Refetched {} but it is still broken", refetched.0);
+                failed.push(refetched);
+            } else {
+                println!("Refetched {}", refetched.0);
+            }
+        }
+        unrepaired = failed;
+    }
+
+    if delete {
+        for (match_id, _) in unrepaired.drain(..) {
+            database.delete_match(&match_id)?;
+            println!("Deleted {}", match_id);
         }
+        if report.has_orphans() {
+            println!("Deleted {} orphaned rows", database.delete_orphaned_rows()?);
+        }
+    }
+
+    let mut left = Vec::new();
+    if !unrepaired.is_empty() {
+        left.push(format!("{} broken matches", unrepaired.len()));
+    }
+    if report.has_orphans() && !delete {
+        left.push(format!("{} orphaned rows", report.orphaned_rows));
+    }
+    if !left.is_empty() {
+        anyhow::bail!(
+            "{} were left in place; rerun with --delete to remove them",
+            left.join(" and ")
+        );
     }
+    Ok(())
 }
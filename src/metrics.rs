@@ -0,0 +1,161 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Process-wide metrics, exported in the Prometheus text format by the admin API's
+/// `/metrics` endpoint
+pub struct Metrics {
+    registry: Registry,
+    /// Riot API responses by endpoint template, region and HTTP status (`error` when no
+    /// response arrived)
+    pub api_requests: IntCounterVec,
+    pub api_request_duration: HistogramVec,
+    /// 429 responses by the `X-Rate-Limit-Type` header: application, method or service,
+    /// or `unspecified` when the underlying service rejected the request
+    pub api_rate_limited: IntCounterVec,
+    /// Time spent waiting for a rate limiter permit, including failed waits
+    pub limiter_wait: Histogram,
+    /// Queued tasks by priority (`delayed` for scheduled retries) and region
+    pub queue_depth: IntGaugeVec,
+    pub matches_stored: IntCounterVec,
+    pub participants_stored: IntCounterVec,
+    /// Worker errors by `ApiError` variant, or `Database`/`Other`
+    pub worker_errors: IntCounterVec,
+    /// Match hooks that failed or panicked, by hook and stage
+    pub hook_errors: IntCounterVec,
+    pub db_write_duration: HistogramVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("lol_crawler".to_string()), None)
+            .expect("metric prefix is valid");
+
+        let api_requests = IntCounterVec::new(
+            Opts::new("api_requests_total", "Riot API requests"),
+            &["endpoint", "region", "status"],
+        )
+        .unwrap();
+        let api_request_duration = HistogramVec::new(
+            HistogramOpts::new("api_request_duration_seconds", "Riot API request latency")
+                .buckets(vec![0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["endpoint", "region"],
+        )
+        .unwrap();
+        let api_rate_limited = IntCounterVec::new(
+            Opts::new("api_rate_limited_total", "Riot API 429 responses"),
+            &["limit_type"],
+        )
+        .unwrap();
+        let limiter_wait = Histogram::with_opts(
+            HistogramOpts::new(
+                "rate_limiter_wait_seconds",
+                "Time spent waiting for a rate limit permit",
+            )
+            .buckets(vec![0.001, 0.01, 0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+        )
+        .unwrap();
+        let queue_depth = IntGaugeVec::new(
+            Opts::new("queue_depth", "Summoner tasks waiting in the queue"),
+            &["priority", "region"],
+        )
+        .unwrap();
+        let matches_stored = IntCounterVec::new(
+            Opts::new("matches_stored_total", "Matches stored"),
+            &["region"],
+        )
+        .unwrap();
+        let participants_stored = IntCounterVec::new(
+            Opts::new("participants_stored_total", "Participants stored"),
+            &["region"],
+        )
+        .unwrap();
+        let worker_errors = IntCounterVec::new(
+            Opts::new(
+                "worker_errors_total",
+                "Errors while processing summoners and matches",
+            ),
+            &["kind"],
+        )
+        .unwrap();
+        let hook_errors = IntCounterVec::new(
+            Opts::new("hook_errors_total", "Match hooks that failed or panicked"),
+            &["hook", "stage"],
+        )
+        .unwrap();
+        let db_write_duration = HistogramVec::new(
+            HistogramOpts::new("db_write_duration_seconds", "Database write latency")
+                .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]),
+            &["operation"],
+        )
+        .unwrap();
+
+        for collector in [
+            Box::new(api_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(api_request_duration.clone()),
+            Box::new(api_rate_limited.clone()),
+            Box::new(limiter_wait.clone()),
+            Box::new(queue_depth.clone()),
+            Box::new(matches_stored.clone()),
+            Box::new(participants_stored.clone()),
+            Box::new(worker_errors.clone()),
+            Box::new(hook_errors.clone()),
+            Box::new(db_write_duration.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            api_requests,
+            api_request_duration,
+            api_rate_limited,
+            limiter_wait,
+            queue_depth,
+            matches_stored,
+            participants_stored,
+            worker_errors,
+            hook_errors,
+            db_write_duration,
+        }
+    }
+
+    /// Every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding does not fail");
+        String::from_utf8(buffer).expect("text encoding is UTF-8")
+    }
+}
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_includes_prefixed_metrics() {
+        metrics()
+            .api_requests
+            .with_label_values(&["/lol/match/v5/matches/{}", "test1", "200"])
+            .inc();
+        metrics().limiter_wait.observe(0.2);
+
+        let text = metrics().render();
+        assert!(text.contains(
+            "lol_crawler_api_requests_total{endpoint=\"/lol/match/v5/matches/{}\",region=\"test1\",status=\"200\"}"
+        ));
+        assert!(text.contains("lol_crawler_rate_limiter_wait_seconds_bucket"));
+    }
+}
@@ -33,7 +33,7 @@ pub struct DbMatch {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct DbParticipant {
     pub id: Option<i64>,
     pub match_id: String,
@@ -71,6 +71,101 @@ pub struct DbParticipant {
     pub win: bool,
     pub first_blood_kill: bool,
     pub first_tower_kill: bool,
+    pub all_in_pings: Option<i32>,
+    pub assist_me_pings: Option<i32>,
+    pub baron_kills: i32,
+    pub bounty_level: Option<i32>,
+    pub champ_experience: i32,
+    pub command_pings: Option<i32>,
+    pub champion_transform: Option<i32>,
+    pub consumables_purchased: i32,
+    pub damage_dealt_to_buildings: i32,
+    pub damage_dealt_to_objectives: i32,
+    pub damage_dealt_to_turrets: i32,
+    pub damage_self_mitigated: i32,
+    pub detector_wards_placed: i32,
+    pub double_kills: i32,
+    pub dragon_kills: i32,
+    pub eligible_for_progression: Option<bool>,
+    pub enemy_missing_pings: Option<i32>,
+    pub enemy_vision_pings: Option<i32>,
+    pub first_blood_assist: bool,
+    pub first_tower_assist: bool,
+    pub game_ended_in_early_surrender: bool,
+    pub game_ended_in_surrender: bool,
+    pub hold_pings: Option<i32>,
+    pub get_back_pings: Option<i32>,
+    pub inhibitor_takedowns: i32,
+    pub inhibitors_lost: i32,
+    pub items_purchased: i32,
+    pub killing_sprees: i32,
+    pub largest_critical_strike: i32,
+    pub largest_killing_spree: i32,
+    pub largest_multi_kill: i32,
+    pub longest_time_spent_living: i32,
+    pub magic_damage_dealt: i32,
+    pub magic_damage_dealt_to_champions: i32,
+    pub magic_damage_taken: i32,
+    pub need_vision_pings: Option<i32>,
+    pub nexus_kills: i32,
+    pub nexus_takedowns: i32,
+    pub nexus_lost: i32,
+    pub objectives_stolen: i32,
+    pub objectives_stolen_assists: i32,
+    pub on_my_way_pings: Option<i32>,
+    pub participant_id: i32,
+    pub penta_kills: i32,
+    pub physical_damage_dealt: i32,
+    pub physical_damage_dealt_to_champions: i32,
+    pub physical_damage_taken: i32,
+    pub placement: Option<i32>,
+    pub player_augment1: Option<i32>,
+    pub player_augment2: Option<i32>,
+    pub player_augment3: Option<i32>,
+    pub player_augment4: Option<i32>,
+    pub player_subteam_id: Option<i32>,
+    pub push_pings: Option<i32>,
+    pub profile_icon: i32,
+    pub quadra_kills: i32,
+    pub riot_id_game_name: Option<String>,
+    pub riot_id_tagline: Option<String>,
+    pub role: String,
+    pub sight_wards_bought_in_game: i32,
+    pub spell1_casts: i32,
+    pub spell2_casts: i32,
+    pub spell3_casts: i32,
+    pub spell4_casts: i32,
+    pub subteam_placement: Option<i32>,
+    pub summoner1_casts: i32,
+    pub summoner2_casts: i32,
+    pub summoner_id: String,
+    pub summoner_level: i32,
+    pub team_early_surrendered: bool,
+    pub team_position: String,
+    pub time_ccing_others: i32,
+    pub time_played: i32,
+    pub total_ally_jungle_minions_killed: i32,
+    pub total_damage_shielded_on_teammates: i32,
+    pub total_enemy_jungle_minions_killed: i32,
+    pub total_heal: i32,
+    pub total_heals_on_teammates: i32,
+    pub total_time_cc_dealt: i32,
+    pub total_time_spent_dead: i32,
+    pub total_units_healed: i32,
+    pub triple_kills: i32,
+    pub true_damage_dealt: i32,
+    pub true_damage_dealt_to_champions: i32,
+    pub true_damage_taken: i32,
+    pub turret_takedowns: i32,
+    pub turrets_lost: i32,
+    pub unreal_kills: i32,
+    pub vision_score: i32,
+    pub vision_cleared_pings: Option<i32>,
+    pub vision_wards_bought_in_game: i32,
+    pub wards_killed: i32,
+    pub wards_placed: i32,
+    /// Arena mission scores as JSON
+    pub missions: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -100,6 +195,48 @@ pub struct DbBan {
     pub pick_turn: i32,
 }
 
+/// One rune or stat shard on a participant's rune page
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbParticipantPerk {
+    pub match_id: String,
+    pub puuid: String,
+    /// `primaryStyle`, `subStyle` or `statPerks`
+    pub style: String,
+    /// Rune tree; `None` for stat shards
+    pub style_id: Option<i32>,
+    /// Position within the style, starting at 0 (offense, flex, defense for stat shards)
+    pub slot: i32,
+    pub perk_id: i32,
+    pub var1: Option<i32>,
+    pub var2: Option<i32>,
+    pub var3: Option<i32>,
+}
+
+/// One entry of a participant's challenges: numeric values in `value`, anything
+/// else (item lists and the like) as JSON in `value_json`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbParticipantChallenge {
+    pub match_id: String,
+    pub puuid: String,
+    pub name: String,
+    pub value: Option<f64>,
+    pub value_json: Option<String>,
+}
+
+/// A match and its child rows, stored together in one transaction
+#[derive(Debug, Clone)]
+pub struct DbMatchBundle {
+    pub match_data: DbMatch,
+    pub teams: Vec<DbTeam>,
+    pub bans: Vec<DbBan>,
+    pub participants: Vec<DbParticipant>,
+    pub perks: Vec<DbParticipantPerk>,
+    pub challenges: Vec<DbParticipantChallenge>,
+    pub stratum: Option<DbMatchStratum>,
+    /// Archived API responses for the match, kept when the raw archive is enabled
+    pub raw_payloads: Vec<DbRawPayload>,
+}
+
 #[derive(Debug, Clone)]
 pub struct DbActiveGame {
     pub game_id: i64,
@@ -133,6 +270,21 @@ pub struct DbApiCall {
     pub rate_limit_remaining: Option<i32>,
 }
 
+/// API calls of one endpoint template and region within an hour, kept after the
+/// individual `api_calls` rows are pruned
+#[derive(Debug, Clone, PartialEq)]
+pub struct DbApiCallRollup {
+    pub hour: DateTime<Utc>,
+    /// Endpoint with ids replaced by `{}`, e.g. `/lol/match/v5/matches/{}`
+    pub endpoint: String,
+    pub region: String,
+    pub calls: i64,
+    /// Responses with a 4xx or 5xx status
+    pub errors: i64,
+    /// 429 responses, also counted in `errors`
+    pub rate_limited: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SummonerPriority {
     High,   // Master+ tier, recently active
@@ -140,6 +292,254 @@ pub enum SummonerPriority {
     Low,    // Other tiers, older activity
 }
 
+impl SummonerPriority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SummonerPriority::High => "High",
+            SummonerPriority::Medium => "Medium",
+            SummonerPriority::Low => "Low",
+        }
+    }
+}
+
+impl std::str::FromStr for SummonerPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "High" => Ok(SummonerPriority::High),
+            "Medium" => Ok(SummonerPriority::Medium),
+            "Low" => Ok(SummonerPriority::Low),
+            _ => Err(format!("Unknown priority '{}'", s)),
+        }
+    }
+}
+
+/// Ranked tier used to stratify the crawl (solo/duo ladder)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Tier {
+    Iron,
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Emerald,
+    Diamond,
+    Master,
+    Grandmaster,
+    Challenger,
+}
+
+impl Tier {
+    pub const ALL: [Tier; 10] = [
+        Tier::Iron,
+        Tier::Bronze,
+        Tier::Silver,
+        Tier::Gold,
+        Tier::Platinum,
+        Tier::Emerald,
+        Tier::Diamond,
+        Tier::Master,
+        Tier::Grandmaster,
+        Tier::Challenger,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tier::Iron => "IRON",
+            Tier::Bronze => "BRONZE",
+            Tier::Silver => "SILVER",
+            Tier::Gold => "GOLD",
+            Tier::Platinum => "PLATINUM",
+            Tier::Emerald => "EMERALD",
+            Tier::Diamond => "DIAMOND",
+            Tier::Master => "MASTER",
+            Tier::Grandmaster => "GRANDMASTER",
+            Tier::Challenger => "CHALLENGER",
+        }
+    }
+
+    /// Apex tiers have a single ladder and no divisions
+    pub fn is_apex(&self) -> bool {
+        matches!(self, Tier::Master | Tier::Grandmaster | Tier::Challenger)
+    }
+}
+
+impl std::fmt::Display for Tier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Tier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tier::ALL
+            .iter()
+            .find(|tier| tier.as_str().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| format!("Unknown tier '{}'", s))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DbSummonerRank {
+    pub puuid: String,
+    pub queue_type: String,
+    pub tier: String,
+    pub rank: String,
+    pub league_points: i32,
+    pub wins: i32,
+    pub losses: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Sampling stratum a stored match was attributed to
+#[derive(Debug, Clone)]
+pub struct DbMatchStratum {
+    pub match_id: String,
+    pub region: String,
+    pub tier: Option<Tier>,
+    pub patch: String,
+}
+
+/// Stored matches per stratum: (region, tier, patch, count)
+pub type StratumCount = (String, Option<Tier>, String, i64);
+
+/// API response kept in the raw payload archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawPayloadKind {
+    Match,
+    Timeline,
+}
+
+impl RawPayloadKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RawPayloadKind::Match => "match",
+            RawPayloadKind::Timeline => "timeline",
+        }
+    }
+}
+
+impl std::fmt::Display for RawPayloadKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for RawPayloadKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "match" => Ok(RawPayloadKind::Match),
+            "timeline" => Ok(RawPayloadKind::Timeline),
+            _ => Err(format!("Unknown payload kind '{}'", s)),
+        }
+    }
+}
+
+/// Original API response for a match, compressed with zstd
+#[derive(Debug, Clone)]
+pub struct DbRawPayload {
+    pub match_id: String,
+    pub kind: RawPayloadKind,
+    /// Platform the match was crawled from
+    pub region: String,
+    /// zstd-compressed JSON
+    pub payload: Vec<u8>,
+    /// Size of the JSON before compression
+    pub raw_size: i64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// A row a match hook produced for a stored match
+#[derive(Debug, Clone)]
+pub struct DbMatchHookRow {
+    pub match_id: String,
+    /// Name of the hook that produced the row
+    pub hook: String,
+    /// What the row holds, chosen by the hook
+    pub kind: String,
+    pub data: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A match a match hook declined to store
+#[derive(Debug, Clone)]
+pub struct DbVetoedMatch {
+    pub match_id: String,
+    pub region: String,
+    /// Name of the hook that declined the match
+    pub hook: String,
+    pub vetoed_at: DateTime<Utc>,
+}
+
+/// Kind of work item recorded in the dead-letter queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedTaskType {
+    Summoner,
+    Match,
+}
+
+impl FailedTaskType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailedTaskType::Summoner => "summoner",
+            FailedTaskType::Match => "match",
+        }
+    }
+}
+
+impl std::fmt::Display for FailedTaskType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for FailedTaskType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "summoner" => Ok(FailedTaskType::Summoner),
+            "match" => Ok(FailedTaskType::Match),
+            _ => Err(format!("Unknown task type '{}'", s)),
+        }
+    }
+}
+
+/// A summoner or match that permanently failed, kept for inspection and requeueing
+#[derive(Debug, Clone)]
+pub struct DbFailedTask {
+    pub id: Option<i64>,
+    pub task_type: FailedTaskType,
+    /// PUUID for summoner tasks, match ID for match tasks
+    pub target_id: String,
+    pub region: String,
+    pub summoner_name: Option<String>,
+    /// `ApiError` variant name, or "Database"/"Other" for non-API failures
+    pub error_kind: String,
+    pub http_status: Option<u16>,
+    pub attempts: i32,
+    pub last_error: String,
+    /// "failed" or "requeued"
+    pub status: String,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+/// Filter for dead-letter queries; `None` fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct FailedTaskFilter {
+    pub id: Option<i64>,
+    pub task_type: Option<FailedTaskType>,
+    pub error_kind: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct SummonerTask {
     pub puuid: String,
@@ -148,4 +548,76 @@ pub struct SummonerTask {
     pub priority: SummonerPriority,
     pub added_at: DateTime<Utc>,
     pub retries: u32,
+    /// Known or estimated solo/duo tier, used for stratified sampling
+    pub tier: Option<Tier>,
+    /// The queue holds the task back until this time (set when a retry is scheduled)
+    pub not_before: Option<DateTime<Utc>>,
+    /// Class of the error that caused the last failure, which drives the retry backoff
+    pub retry_class: Option<RetryClass>,
+}
+
+/// How a failed task should be retried, derived from the error that failed it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RetryClass {
+    RateLimited,
+    ServiceUnavailable,
+    Network,
+    Authentication,
+    Parse,
+    Other,
+    /// Retrying cannot help (e.g. 404, 400); goes straight to the dead-letter queue
+    Permanent,
+}
+
+impl RetryClass {
+    pub const ALL: [RetryClass; 7] = [
+        RetryClass::RateLimited,
+        RetryClass::ServiceUnavailable,
+        RetryClass::Network,
+        RetryClass::Authentication,
+        RetryClass::Parse,
+        RetryClass::Other,
+        RetryClass::Permanent,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetryClass::RateLimited => "RateLimited",
+            RetryClass::ServiceUnavailable => "ServiceUnavailable",
+            RetryClass::Network => "Network",
+            RetryClass::Authentication => "Authentication",
+            RetryClass::Parse => "Parse",
+            RetryClass::Other => "Other",
+            RetryClass::Permanent => "Permanent",
+        }
+    }
+
+    /// Delay before the first retry; doubled on every further attempt
+    pub fn base_delay_seconds(&self) -> i64 {
+        match self {
+            RetryClass::RateLimited => 30,
+            RetryClass::ServiceUnavailable => 120,
+            RetryClass::Network => 20,
+            RetryClass::Authentication => 300,
+            RetryClass::Parse => 5,
+            RetryClass::Other => 10,
+            RetryClass::Permanent => 0,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, RetryClass::Permanent)
+    }
+}
+
+impl std::str::FromStr for RetryClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        RetryClass::ALL
+            .iter()
+            .find(|class| class.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown retry class '{}'", s))
+    }
 }
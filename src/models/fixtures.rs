@@ -0,0 +1,108 @@
+//! Match fixtures shared by unit tests
+
+use super::database::{DbMatch, DbMatchBundle, DbParticipant, DbTeam};
+use super::match_v5::MatchDto;
+use chrono::Utc;
+
+/// Match-v5 payload of a ranked solo match with one team and two bans
+pub fn match_json(match_id: &str) -> String {
+    let objective = r#"{"first": false, "kills": 0}"#;
+    format!(
+        r#"{{
+            "metadata": {{"dataVersion": "2", "matchId": "{match_id}", "participants": []}},
+            "info": {{
+                "gameCreation": 1640000000000,
+                "gameDuration": 1800,
+                "gameId": 1234567890,
+                "gameMode": "CLASSIC",
+                "gameStartTimestamp": 1640000000000,
+                "gameType": "MATCHED_GAME",
+                "gameVersion": "14.1.555.1234",
+                "mapId": 11,
+                "platformId": "NA1",
+                "queueId": 420,
+                "participants": [],
+                "teams": [{{
+                    "teamId": 100,
+                    "win": true,
+                    "bans": [{{"championId": 157, "pickTurn": 1}}, {{"championId": 238, "pickTurn": 2}}],
+                    "objectives": {{
+                        "baron": {objective}, "champion": {objective}, "dragon": {objective},
+                        "inhibitor": {objective}, "riftHerald": {objective}, "tower": {objective}
+                    }}
+                }}]
+            }}
+        }}"#
+    )
+}
+
+/// `match_json` parsed
+pub fn match_dto(match_id: &str) -> MatchDto {
+    serde_json::from_str(&match_json(match_id)).unwrap()
+}
+
+/// Stored row of a ranked solo match on patch 14.1 in na1
+pub fn db_match(match_id: &str) -> DbMatch {
+    DbMatch {
+        match_id: match_id.to_string(),
+        game_creation: 1_700_000_000_000,
+        game_duration: 1800,
+        game_end_timestamp: None,
+        game_id: 1,
+        game_mode: "CLASSIC".to_string(),
+        game_name: None,
+        game_type: "MATCHED_GAME".to_string(),
+        game_version: "14.1.555.1234".to_string(),
+        map_id: 11,
+        platform_id: "NA1".to_string(),
+        queue_id: 420,
+        tournament_code: None,
+        region: "na1".to_string(),
+        created_at: Utc::now(),
+    }
+}
+
+/// Team row with no objectives taken
+pub fn team(match_id: &str, team_id: i32, win: bool) -> DbTeam {
+    DbTeam {
+        id: None,
+        match_id: match_id.to_string(),
+        team_id,
+        win,
+        first_baron: false,
+        first_dragon: false,
+        first_inhibitor: false,
+        first_rift_herald: false,
+        first_tower: false,
+        baron_kills: 0,
+        dragon_kills: 0,
+        inhibitor_kills: 0,
+        rift_herald_kills: 0,
+        tower_kills: 0,
+    }
+}
+
+/// A well-formed 5v5 match: `db_match` with both teams and ten participants, team 100
+/// winning
+pub fn bundle(match_id: &str) -> DbMatchBundle {
+    DbMatchBundle {
+        match_data: db_match(match_id),
+        teams: vec![team(match_id, 100, true), team(match_id, 200, false)],
+        bans: Vec::new(),
+        participants: (0..10)
+            .map(|i| DbParticipant {
+                match_id: match_id.to_string(),
+                puuid: format!("{}-puuid-{}", match_id, i),
+                participant_id: i + 1,
+                team_id: if i < 5 { 100 } else { 200 },
+                win: i < 5,
+                champion_level: 18,
+                ..Default::default()
+            })
+            .collect(),
+        perks: Vec::new(),
+        challenges: Vec::new(),
+        stratum: None,
+        raw_payloads: Vec::new(),
+    }
+}
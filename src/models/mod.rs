@@ -1,4 +1,6 @@
 pub mod database;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod match_v5;
 pub mod riot;
 
@@ -15,6 +15,15 @@ pub struct SummonerResponse {
     pub summoner_level: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountResponse {
+    pub puuid: String,
+    #[serde(rename = "gameName")]
+    pub game_name: Option<String>,
+    #[serde(rename = "tagLine")]
+    pub tag_line: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchListResponse {
     pub matches: Vec<MatchReference>,
@@ -1,8 +1,10 @@
 use super::TokenBucket;
 use crate::config::RateLimitConfig;
+use crate::metrics::metrics;
 use dashmap::DashMap;
+use serde::Serialize;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tokio::time::sleep;
 
@@ -12,7 +14,7 @@ pub struct RateLimiter {
     application_limiter_per_two_minutes: Arc<RwLock<TokenBucket>>,
     method_limiters: Arc<DashMap<String, Arc<RwLock<TokenBucket>>>>,
     service_limiters: Arc<DashMap<String, Arc<RwLock<TokenBucket>>>>,
-    config: RateLimitConfig,
+    config: std::sync::RwLock<RateLimitConfig>,
 }
 
 impl RateLimiter {
@@ -30,16 +32,58 @@ impl RateLimiter {
             )),
             method_limiters: Arc::new(DashMap::new()),
             service_limiters: Arc::new(DashMap::new()),
-            config,
+            config: std::sync::RwLock::new(config),
         }
     }
 
+    /// Current limits and retry settings
+    pub fn config(&self) -> RateLimitConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Apply new limits and retry settings to a running limiter. Application buckets
+    /// whose size changed are resized with the tokens spent in the current window still
+    /// counted against them.
+    pub async fn update_config(&self, config: RateLimitConfig) {
+        let current = self.config();
+        if config.application_limit_per_second != current.application_limit_per_second {
+            self.application_limiter_per_second.write().await.resize(
+                config.application_limit_per_second,
+                config.application_limit_per_second,
+            );
+        }
+        if config.application_limit_per_two_minutes != current.application_limit_per_two_minutes {
+            self.application_limiter_per_two_minutes
+                .write()
+                .await
+                .resize(
+                    config.application_limit_per_two_minutes,
+                    config.application_limit_per_two_minutes,
+                );
+        }
+        *self.config.write().unwrap() = config;
+    }
+
     pub async fn acquire_permit(
         &self,
         endpoint: &str,
         region: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let retries = self.config.max_retries;
+        let started = Instant::now();
+        let result = self.acquire_with_retries(endpoint, region).await;
+        metrics()
+            .limiter_wait
+            .observe(started.elapsed().as_secs_f64());
+        result
+    }
+
+    async fn acquire_with_retries(
+        &self,
+        endpoint: &str,
+        region: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.config();
+        let retries = config.max_retries;
         let mut retry_count = 0;
 
         while retry_count < retries {
@@ -51,7 +95,7 @@ impl RateLimiter {
             // If we failed, wait and retry
             retry_count += 1;
             if retry_count < retries {
-                let delay = Duration::from_millis(self.config.retry_delay_ms * (1 << retry_count)); // Exponential backoff
+                let delay = Duration::from_millis(config.retry_delay_ms * (1 << retry_count)); // Exponential backoff
                 log::debug!(
                     "Rate limit hit, retrying in {:?} (attempt {}/{})",
                     delay,
@@ -244,7 +288,7 @@ impl RateLimiter {
         let delay = if let Some(retry_after_secs) = retry_after {
             Duration::from_secs(retry_after_secs)
         } else {
-            Duration::from_millis(self.config.retry_delay_ms)
+            Duration::from_millis(self.config().retry_delay_ms)
         };
 
         log::warn!("Received 429 response, waiting {:?} before retry", delay);
@@ -271,7 +315,7 @@ impl RateLimiter {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct RateLimitStatus {
     pub application_tokens_per_second: u32,
     pub application_tokens_per_two_minutes: u32,
@@ -495,6 +539,23 @@ mod tests {
         assert!(status.service_limiters_count > 0); // Service limiters created
     }
 
+    #[tokio::test]
+    async fn test_update_config_resizes_changed_buckets() {
+        let limiter = RateLimiter::new(test_config());
+        limiter.acquire_permit("/test", "na1").await.unwrap();
+
+        let mut config = test_config();
+        config.application_limit_per_second = 5;
+        config.max_retries = 1;
+        limiter.update_config(config).await;
+
+        let status = limiter.get_rate_limit_status().await;
+        // Both buckets still count the token already spent; the two-minute one kept its size
+        assert_eq!(status.application_tokens_per_second, 4);
+        assert_eq!(status.application_tokens_per_two_minutes, 99);
+        assert_eq!(limiter.config().max_retries, 1);
+    }
+
     #[tokio::test]
     async fn test_exponential_backoff_behavior() {
         let mut config = test_config();
@@ -71,6 +71,16 @@ impl TokenBucket {
         }
     }
 
+    /// Change the size and refill rate, keeping the tokens already spent in the current
+    /// window spent, so a bigger bucket does not allow a burst on top of them
+    pub fn resize(&mut self, capacity: u32, refill_rate: u32) {
+        self.refill();
+        let spent = self.capacity.saturating_sub(self.tokens);
+        self.capacity = capacity;
+        self.refill_rate = refill_rate;
+        self.tokens = capacity.saturating_sub(spent);
+    }
+
     pub fn available_tokens(&mut self) -> u32 {
         self.refill();
         self.tokens
@@ -100,6 +110,20 @@ mod tests {
     use super::*;
     use tokio::time::{sleep, Duration};
 
+    #[test]
+    fn test_resize_keeps_spent_tokens() {
+        let mut bucket = TokenBucket::per_two_minutes(100, 100);
+        assert!(bucket.try_acquire(30));
+
+        // A bigger bucket does not hand back the tokens already spent
+        bucket.resize(200, 200);
+        assert_eq!(bucket.available_tokens(), 170);
+
+        // A smaller one is empty once more was spent than it holds
+        bucket.resize(20, 20);
+        assert_eq!(bucket.available_tokens(), 0);
+    }
+
     #[tokio::test]
     async fn test_token_bucket_basic() {
         let mut bucket = TokenBucket::per_second(10, 10);
@@ -1,10 +1,15 @@
 use chrono::Utc;
 use lol_crawler::config::{Config, CrawlerConfig, LoggingConfig, RateLimitConfig};
-use lol_crawler::models::database::{DbMatch, DbParticipant, DbSummoner};
+use lol_crawler::models::database::{
+    DbMatch, DbParticipant, DbSummoner, SummonerPriority, SummonerTask,
+};
 
 pub fn test_config() -> Config {
     Config {
         riot_api_key: "RGAPI-test-integration-key".to_string(),
+        api_keys: Vec::new(),
+        api_project: None,
+        api_base_url: None,
         database_url: ":memory:".to_string(),
         regions: vec!["na1".to_string()],
         rate_limits: RateLimitConfig {
@@ -19,11 +24,22 @@ pub fn test_config() -> Config {
             batch_size: 50,
             health_check_interval_seconds: 60,
             state_save_interval_seconds: 300,
+            drain_timeout_seconds: 30,
+            hook_timeout_seconds: 10,
+            queue_id: 420,
+            seed_from_ladder: true,
         },
         logging: LoggingConfig {
             level: "info".to_string(),
             format: "json".to_string(),
         },
+        sampling: Default::default(),
+        budget: Default::default(),
+        archive: Default::default(),
+        retention: Default::default(),
+        admin: Default::default(),
+        events: Default::default(),
+        region_overrides: Default::default(),
     }
 }
 
@@ -99,5 +115,214 @@ pub fn create_test_participant(match_id: &str, puuid: &str) -> DbParticipant {
         win: true,
         first_blood_kill: false,
         first_tower_kill: true,
+        vision_score: 38,
+        wards_placed: 11,
+        damage_dealt_to_objectives: 12000,
+        ..Default::default()
+    }
+}
+
+/// Required Match-v5 participant fields, all zeroed in `match_json`
+const PARTICIPANT_NUMBERS: &[&str] = &[
+    "assists",
+    "baronKills",
+    "champExperience",
+    "champLevel",
+    "championId",
+    "consumablesPurchased",
+    "damageDealtToBuildings",
+    "damageDealtToObjectives",
+    "damageDealtToTurrets",
+    "damageSelfMitigated",
+    "deaths",
+    "detectorWardsPlaced",
+    "doubleKills",
+    "dragonKills",
+    "goldEarned",
+    "goldSpent",
+    "inhibitorKills",
+    "inhibitorTakedowns",
+    "inhibitorsLost",
+    "item0",
+    "item1",
+    "item2",
+    "item3",
+    "item4",
+    "item5",
+    "item6",
+    "itemsPurchased",
+    "killingSprees",
+    "kills",
+    "largestCriticalStrike",
+    "largestKillingSpree",
+    "largestMultiKill",
+    "longestTimeSpentLiving",
+    "magicDamageDealt",
+    "magicDamageDealtToChampions",
+    "magicDamageTaken",
+    "neutralMinionsKilled",
+    "nexusKills",
+    "nexusTakedowns",
+    "nexusLost",
+    "objectivesStolen",
+    "objectivesStolenAssists",
+    "pentaKills",
+    "physicalDamageDealt",
+    "physicalDamageDealtToChampions",
+    "physicalDamageTaken",
+    "profileIcon",
+    "quadraKills",
+    "sightWardsBoughtInGame",
+    "spell1Casts",
+    "spell2Casts",
+    "spell3Casts",
+    "spell4Casts",
+    "summoner1Casts",
+    "summoner1Id",
+    "summoner2Casts",
+    "summoner2Id",
+    "summonerLevel",
+    "timeCCingOthers",
+    "timePlayed",
+    "totalAllyJungleMinionsKilled",
+    "totalDamageDealt",
+    "totalDamageDealtToChampions",
+    "totalDamageShieldedOnTeammates",
+    "totalDamageTaken",
+    "totalEnemyJungleMinionsKilled",
+    "totalHeal",
+    "totalHealsOnTeammates",
+    "totalMinionsKilled",
+    "totalTimeCCDealt",
+    "totalTimeSpentDead",
+    "totalUnitsHealed",
+    "tripleKills",
+    "trueDamageDealt",
+    "trueDamageDealtToChampions",
+    "trueDamageTaken",
+    "turretKills",
+    "turretTakedowns",
+    "turretsLost",
+    "unrealKills",
+    "visionScore",
+    "visionWardsBoughtInGame",
+    "wardsKilled",
+    "wardsPlaced",
+];
+const PARTICIPANT_STRINGS: &[&str] = &[
+    "championName",
+    "individualPosition",
+    "lane",
+    "role",
+    "summonerId",
+    "teamPosition",
+];
+const PARTICIPANT_FLAGS: &[&str] = &[
+    "firstBloodAssist",
+    "firstBloodKill",
+    "firstTowerAssist",
+    "firstTowerKill",
+    "gameEndedInEarlySurrender",
+    "gameEndedInSurrender",
+    "teamEarlySurrendered",
+    "win",
+];
+
+/// Match-v5 response for `match_id` with one blue-side participant per PUUID
+pub fn match_json(match_id: &str, queue_id: i32, puuids: &[&str]) -> serde_json::Value {
+    let participants: Vec<serde_json::Value> = puuids
+        .iter()
+        .enumerate()
+        .map(|(index, puuid)| {
+            let mut participant = serde_json::Map::new();
+            for field in PARTICIPANT_NUMBERS {
+                participant.insert(field.to_string(), 0.into());
+            }
+            for field in PARTICIPANT_STRINGS {
+                participant.insert(field.to_string(), "".into());
+            }
+            for field in PARTICIPANT_FLAGS {
+                participant.insert(field.to_string(), false.into());
+            }
+            participant.insert("participantId".to_string(), (index + 1).into());
+            participant.insert("teamId".to_string(), 100.into());
+            participant.insert("puuid".to_string(), (*puuid).into());
+            participant.insert(
+                "summonerName".to_string(),
+                format!("Player{}", index).into(),
+            );
+            participant.into()
+        })
+        .collect();
+    let objective = serde_json::json!({ "first": false, "kills": 0 });
+    serde_json::json!({
+        "metadata": { "dataVersion": "2", "matchId": match_id, "participants": puuids },
+        "info": {
+            "gameCreation": 1640000000000i64,
+            "gameDuration": 1800,
+            "gameId": 1234567890,
+            "gameMode": "CLASSIC",
+            "gameStartTimestamp": 1640000000000i64,
+            "gameType": "MATCHED_GAME",
+            "gameVersion": "14.1.555.1234",
+            "mapId": 11,
+            "platformId": "NA1",
+            "queueId": queue_id,
+            "participants": participants,
+            "teams": [{
+                "teamId": 100,
+                "win": true,
+                "bans": [],
+                "objectives": {
+                    "baron": objective, "champion": objective, "dragon": objective,
+                    "inhibitor": objective, "riftHerald": objective, "tower": objective
+                }
+            }]
+        }
+    })
+}
+
+/// Mock the summoner profile and match history of `puuid`; the matches themselves are
+/// left to the test
+pub async fn mock_player(server: &mut mockito::ServerGuard, puuid: &str, match_ids: &[&str]) {
+    server
+        .mock(
+            "GET",
+            format!("/lol/summoner/v4/summoners/by-puuid/{}", puuid).as_str(),
+        )
+        .with_body(
+            serde_json::json!({
+                "puuid": puuid,
+                "profileIconId": 1,
+                "revisionDate": 1640000000000i64,
+                "summonerLevel": 30
+            })
+            .to_string(),
+        )
+        .create_async()
+        .await;
+    server
+        .mock(
+            "GET",
+            format!("/lol/match/v5/matches/by-puuid/{}/ids", puuid).as_str(),
+        )
+        .match_query(mockito::Matcher::Any)
+        .with_body(serde_json::json!(match_ids).to_string())
+        .create_async()
+        .await;
+}
+
+/// Task for crawling `puuid` in na1
+pub fn summoner_task(puuid: &str) -> SummonerTask {
+    SummonerTask {
+        puuid: puuid.to_string(),
+        summoner_name: format!("Player_{}", puuid),
+        region: "na1".to_string(),
+        priority: SummonerPriority::High,
+        added_at: Utc::now(),
+        retries: 0,
+        tier: None,
+        not_before: None,
+        retry_class: None,
     }
 }